@@ -3,35 +3,128 @@
 //! This module ports the C# Arima.cs implementation to Rust for WASM compilation.
 //! The model uses:
 //! - p=2 AR terms
-//! - d=1 differencing
+//! - d=1 differencing (or chosen by the stationarity tests when not given)
 //! - q=1 MA term
 //! - Seasonal period of 12 (monthly data)
 //! - Optional Easter regressor for ARIMAX
+//!
+//! Forecasts, intervals and residuals come from the state-space form of the
+//! fitted model (see `state_space`), which also handles missing values.
+
+use serde::{Deserialize, Serialize};
 
-use crate::easter::create_easter_regressor;
+use crate::diagnostics::{diagnose_residuals, ResidualDiagnostics};
+use crate::easter::{create_easter_regressor, month_offset};
+use crate::intervals::{interval_bounds, z_score};
+use crate::polynomial::{enforce_invertibility, enforce_stationarity};
+use crate::outliers::{detect_outliers, intervention_effects, DetectedOutlier, OutlierOptions, SearchModel};
+use crate::stationarity::{suggest_differencing, DifferencingSuggestion};
+use crate::state_space::{FilterOutput, StateSpace};
+use crate::warnings::{clamp_non_negative, Warning, WarningCode};
 
 /// Result of fitting and forecasting with ARIMA
 #[derive(Debug, Clone)]
 pub struct ForecastResult {
     /// Point forecasts for each future period
     pub forecast: Vec<f64>,
-    /// Lower bound of 80% confidence interval
+    /// Lower bound of the prediction interval (at the requested confidence)
     pub lower: Vec<f64>,
-    /// Upper bound of 80% confidence interval
+    /// Upper bound of the prediction interval
     pub upper: Vec<f64>,
     /// Seasonal factors (12 values for monthly data)
     pub seasonal_factors: Vec<f64>,
-    /// Estimated Easter effect coefficient (if ARIMAX)
+    /// Estimated Easter effect coefficient (0 without the calendar regressor)
     pub easter_coefficient: f64,
+    /// Estimated effect of a custom regressor, when one replaced Easter
+    pub regressor_coefficient: Option<f64>,
     /// Estimated AR coefficients
     pub ar_coefficients: Vec<f64>,
     /// Estimated MA coefficients
     pub ma_coefficients: Vec<f64>,
     /// Model intercept
     pub intercept: f64,
+    /// Detected and forced interventions
+    pub outliers: Vec<DetectedOutlier>,
+    /// Differencing orders suggested by the stationarity tests (when d was not given)
+    pub differencing: Option<DifferencingSuggestion>,
+    /// Defaults substituted and corrections made while fitting
+    pub warnings: Vec<Warning>,
+    /// Sample size, likelihood and information criteria (ARIMA only)
+    pub fit: Option<FitMetadata>,
+}
+
+/// Fit-quality summary of an ARIMA model
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FitMetadata {
+    /// Observations contributing to the likelihood
+    pub n_obs: usize,
+    /// Estimated parameters (ARMA, intercept, regressors, interventions, sigma²)
+    pub n_params: usize,
+    /// Innovation variance
+    pub sigma2: f64,
+    /// Gaussian log-likelihood
+    pub log_likelihood: f64,
+    /// Akaike information criterion
+    pub aic: f64,
+    /// AIC with the small-sample correction
+    pub aicc: f64,
+    /// Bayesian information criterion
+    pub bic: f64,
+}
+
+impl FitMetadata {
+    /// Summary with information criteria computed from the likelihood
+    pub fn new(n_obs: usize, n_params: usize, sigma2: f64, log_likelihood: f64) -> Self {
+        let (n, k) = (n_obs as f64, n_params as f64);
+        let aic = -2.0 * log_likelihood + 2.0 * k;
+        let aicc = if n > k + 1.0 { aic + 2.0 * k * (k + 1.0) / (n - k - 1.0) } else { f64::INFINITY };
+        let bic = -2.0 * log_likelihood + k * n.max(1.0).ln();
+        FitMetadata { n_obs, n_params, sigma2, log_likelihood, aic, aicc, bic }
+    }
+}
+
+/// Settings for `fit_and_forecast_with_options`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastOptions {
+    /// Whether to use the Easter regressor
+    pub use_easter: bool,
+    /// Outlier detection and user interventions
+    pub outliers: OutlierOptions,
+    /// AR order
+    pub p: usize,
+    /// Differencing order; None picks it from the stationarity tests
+    pub d: Option<usize>,
+    /// MA order
+    pub q: usize,
+    /// Seasonal period
+    pub seasonal_period: usize,
+    /// Prediction interval coverage (tabulated: 0.80, 0.90, 0.95, 0.99)
+    pub confidence: f64,
+    /// Regressor aligned with the series and continuing into the forecast
+    /// horizon, used instead of the calendar Easter regressor
+    pub regressor: Option<Vec<f64>>,
+}
+
+impl Default for ForecastOptions {
+    fn default() -> Self {
+        ForecastOptions {
+            use_easter: true,
+            outliers: OutlierOptions::default(),
+            p: 2,
+            d: Some(1),
+            q: 1,
+            seasonal_period: 12,
+            confidence: 0.80,
+            regressor: None,
+        }
+    }
 }
 
 /// ARIMA model with optional exogenous variables
+///
+/// Coefficients are estimated on the differenced, deseasonalized series; the
+/// fitted model is then cast in state-space form and run through a Kalman
+/// filter, which provides residuals, likelihood and forecasts.
 pub struct Arima {
     p: usize,              // AR order
     d: usize,              // Differencing order
@@ -44,12 +137,16 @@ pub struct Arima {
     seasonal_factors: Vec<f64>,
     intercept: f64,
     original_series: Vec<f64>,
-    differenced_series: Vec<f64>,
-    residuals: Vec<f64>,
+    deseasonalized: Vec<f64>,
+    state_space: StateSpace,
+    filtered: FilterOutput,
     
     // ARIMAX support
     exog_coeffs: Vec<f64>,
     exog_data: Option<Vec<f64>>,
+
+    // Defaults substituted and corrections made while fitting
+    warnings: Vec<Warning>,
 }
 
 impl Arima {
@@ -65,14 +162,18 @@ impl Arima {
             seasonal_factors: vec![],
             intercept: 0.0,
             original_series: vec![],
-            differenced_series: vec![],
-            residuals: vec![],
+            deseasonalized: vec![],
+            state_space: StateSpace::default(),
+            filtered: FilterOutput::default(),
             exog_coeffs: vec![],
             exog_data: None,
+            warnings: vec![],
         }
     }
 
     /// Fit the model to a time series
+    ///
+    /// Missing observations may be passed as NaN.
     pub fn fit(&mut self, series: &[f64]) {
         self.fit_with_exog(series, None);
     }
@@ -81,9 +182,17 @@ impl Arima {
     pub fn fit_with_exog(&mut self, series: &[f64], exog: Option<&[f64]>) {
         self.original_series = series.to_vec();
         self.exog_data = exog.map(|e| e.to_vec());
+        self.warnings.clear();
 
         // 1. Regress out exogenous effects (if present)
         let adjusted_series = if let Some(exog_data) = exog {
+            let observed = series.iter().zip(exog_data).any(|(y, &x)| x > 0.5 && y.is_finite());
+            if !observed {
+                self.warnings.push(Warning::new(
+                    WarningCode::EasterNotObserved,
+                    "Easter regressor is never active in the observed history; its coefficient is 0",
+                ));
+            }
             let (adj, coef) = regress_out_exogenous(series, exog_data);
             self.exog_coeffs = vec![coef];
             adj
@@ -94,16 +203,27 @@ impl Arima {
 
         // 2. Calculate seasonal factors
         self.seasonal_factors = calculate_seasonal_factors(&adjusted_series, self.seasonal_period);
+        for slot in 0..self.seasonal_period {
+            if !adjusted_series.iter().skip(slot).step_by(self.seasonal_period).any(|&x| x > 0.0) {
+                self.warnings.push(Warning::new(
+                    WarningCode::EmptySeasonalMonth,
+                    format!("No positive observations at position {} of the seasonal cycle; factor 1.0 used", slot + 1),
+                ));
+            }
+        }
 
         // 3. Deseasonalize
-        let deseasonalized = deseasonalize(&adjusted_series, &self.seasonal_factors);
+        self.deseasonalized = deseasonalize(&adjusted_series, &self.seasonal_factors);
 
-        // 4. Apply differencing
-        self.differenced_series = difference(&deseasonalized, self.d);
+        // 4. Apply differencing (gaps left by missing values are skipped)
+        let differenced: Vec<f64> = difference(&self.deseasonalized, self.d)
+            .into_iter()
+            .filter(|x| x.is_finite())
+            .collect();
 
         // 5. Estimate AR/MA coefficients
-        self.intercept = mean(&self.differenced_series);
-        let centered: Vec<f64> = self.differenced_series.iter()
+        self.intercept = mean(&differenced);
+        let centered: Vec<f64> = differenced.iter()
             .map(|&x| x - self.intercept)
             .collect();
 
@@ -115,15 +235,33 @@ impl Arima {
             self.ar_coeffs = vec![];
         }
 
-        // Calculate residuals for MA estimation
-        self.residuals = calculate_residuals(&centered, &self.ar_coeffs);
-
-        // Estimate MA coefficients from residual autocorrelation
+        // Estimate MA coefficients from the AR residual autocorrelation
         if self.q > 0 {
-            self.ma_coeffs = estimate_ma_coefficients(&self.residuals, self.q);
+            let ar_residuals = calculate_residuals(&centered, &self.ar_coeffs);
+            self.ma_coeffs = estimate_ma_coefficients(&ar_residuals, self.q);
         } else {
             self.ma_coeffs = vec![];
         }
+
+        // 6. Keep the model stationary and invertible
+        if let Some(ar) = enforce_stationarity(&self.ar_coeffs) {
+            self.warnings.push(Warning::new(
+                WarningCode::NonStationaryAr,
+                format!("AR coefficients {:?} were non-stationary and were shrunk to {:?}", self.ar_coeffs, ar),
+            ));
+            self.ar_coeffs = ar;
+        }
+        if let Some(ma) = enforce_invertibility(&self.ma_coeffs) {
+            self.warnings.push(Warning::new(
+                WarningCode::NonInvertibleMa,
+                format!("MA coefficients {:?} were non-invertible and were replaced by {:?}", self.ma_coeffs, ma),
+            ));
+            self.ma_coeffs = ma;
+        }
+
+        // 7. Kalman filter over the deseasonalized levels
+        self.state_space = StateSpace::arima(&self.ar_coeffs, &self.ma_coeffs, self.d, self.intercept);
+        self.filtered = self.state_space.filter(&self.deseasonalized);
     }
 
     /// Generate forecasts
@@ -132,51 +270,22 @@ impl Arima {
     }
 
     /// Generate forecasts with future exogenous values
+    ///
+    /// Negative forecasts are clamped to zero.
     pub fn forecast_with_exog(&self, steps: usize, future_exog: Option<&[f64]>) -> Vec<f64> {
-        // Work with differenced series
-        let mut extended = self.differenced_series.clone();
-        let mut extended_residuals = self.residuals.clone();
-
-        // 1. Forecast differenced series
-        for _ in 0..steps {
-            let mut prediction = self.intercept;
-
-            // AR component
-            for (i, &coef) in self.ar_coeffs.iter().enumerate() {
-                if i < extended.len() {
-                    prediction += coef * (extended[extended.len() - 1 - i] - self.intercept);
-                }
-            }
-
-            // MA component (residuals decay to 0 for forecasts)
-            for (i, &coef) in self.ma_coeffs.iter().enumerate() {
-                let res_idx = extended_residuals.len() - 1 - i;
-                if res_idx < self.residuals.len() {
-                    prediction += coef * extended_residuals[res_idx];
-                }
-            }
-
-            extended.push(prediction);
-            extended_residuals.push(0.0); // Future residuals are 0
-        }
+        self.forecast_unclamped(steps, future_exog).iter().map(|&x| x.max(0.0)).collect()
+    }
 
-        // Extract forecast values
-        let forecast_diff: Vec<f64> = extended[self.differenced_series.len()..].to_vec();
+    /// Forecasts before clamping at zero
+    pub(crate) fn forecast_unclamped(&self, steps: usize, future_exog: Option<&[f64]>) -> Vec<f64> {
+        // 1. Forecast deseasonalized levels from the final filtered state
+        let (forecast_deseas, _) = self.state_space.forecast(&self.filtered, steps);
 
-        // 2. Undifference - get deseasonalized original series first
-        let deseasonalized: Vec<f64> = self.original_series.iter().enumerate()
-            .map(|(i, &v)| {
-                let factor = self.seasonal_factors[i % self.seasonal_period];
-                if factor > 0.0 { v / factor } else { v }
-            })
-            .collect();
-        let forecast_deseas = undifference(&forecast_diff, &deseasonalized, self.d);
-
-        // 3. Reseasonalize
+        // 2. Reseasonalize
         let start_month = self.original_series.len() % self.seasonal_period;
         let mut forecast = reseasonalize(&forecast_deseas, &self.seasonal_factors, start_month);
 
-        // 4. Add back exogenous effects for future periods
+        // 3. Add back exogenous effects for future periods
         if let Some(future_exog_data) = future_exog {
             if !self.exog_coeffs.is_empty() {
                 for (s, f) in forecast.iter_mut().enumerate() {
@@ -187,53 +296,277 @@ impl Arima {
             }
         }
 
-        // Ensure non-negative
-        forecast.iter().map(|&x| x.max(0.0)).collect()
+        forecast
     }
 
     /// Calculate confidence intervals for forecasts
+    ///
+    /// Widths come from the state-space forecast variance, scaled by the
+    /// seasonal factor of each forecast month.
     pub fn confidence_intervals(&self, steps: usize, confidence: f64) -> (Vec<f64>, Vec<f64>) {
-        let forecast = self.forecast(steps);
-
-        // Estimate standard error from residuals
-        let se = (self.residuals.iter().map(|&r| r * r).sum::<f64>() / self.residuals.len().max(1) as f64).sqrt();
-
-        // Z-score for confidence level
-        let z = if (confidence - 0.99).abs() < 0.001 {
-            2.576
-        } else if (confidence - 0.95).abs() < 0.001 {
-            1.96
-        } else if (confidence - 0.90).abs() < 0.001 {
-            1.645
-        } else if (confidence - 0.80).abs() < 0.001 {
-            1.28
-        } else {
-            1.96
-        };
+        self.confidence_intervals_with_exog(steps, confidence, None)
+    }
 
-        let lower: Vec<f64> = forecast.iter().enumerate().map(|(i, &f)| {
-            // Error grows with forecast horizon
-            let horizon_se = se * (1.0 + i as f64 * 0.1).sqrt();
-            // Scale by seasonal factor for proper interval width
-            let seasonal_scale = self.seasonal_factors[(self.original_series.len() + i) % self.seasonal_period];
-            let interval = z * horizon_se * seasonal_scale;
-            (f - interval).max(0.0)
-        }).collect();
+    /// Calculate confidence intervals around forecasts with future exogenous values
+    pub fn confidence_intervals_with_exog(
+        &self,
+        steps: usize,
+        confidence: f64,
+        future_exog: Option<&[f64]>,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let forecast = self.forecast_with_exog(steps, future_exog);
+        interval_bounds(&forecast, &self.standard_errors(steps), z_score(confidence))
+    }
 
-        let upper: Vec<f64> = forecast.iter().enumerate().map(|(i, &f)| {
-            let horizon_se = se * (1.0 + i as f64 * 0.1).sqrt();
-            let seasonal_scale = self.seasonal_factors[(self.original_series.len() + i) % self.seasonal_period];
-            let interval = z * horizon_se * seasonal_scale;
-            f + interval
-        }).collect();
+    /// Forecast standard errors on the original scale
+    pub(crate) fn standard_errors(&self, steps: usize) -> Vec<f64> {
+        let (_, variances) = self.state_space.forecast(&self.filtered, steps);
+        variances.iter().enumerate().map(|(i, &v)| {
+            v.sqrt() * self.seasonal_factors[(self.original_series.len() + i) % self.seasonal_period]
+        }).collect()
+    }
+
+    /// One-step-ahead residuals on the deseasonalized scale (NaN where missing)
+    ///
+    /// The first d observations seed the levels and have no residual.
+    pub fn residuals(&self) -> &[f64] {
+        &self.filtered.innovations
+    }
+
+    /// Residuals divided by their one-step standard deviation
+    ///
+    /// Early residuals carry extra state uncertainty; standardizing puts
+    /// them on a common N(0, 1) scale for diagnostics.
+    pub fn standardized_residuals(&self) -> Vec<f64> {
+        let sigma2 = self.filtered.sigma2;
+        self.filtered.innovations.iter().zip(&self.filtered.innovation_vars)
+            .map(|(&v, &f)| v / (f * sigma2).sqrt())
+            .collect()
+    }
+
+    /// Residual diagnostics (Ljung-Box, Jarque-Bera, heteroscedasticity,
+    /// ACF/PACF) on the standardized residuals
+    ///
+    /// `lags` defaults to min(2m, n/5).
+    pub fn diagnostics(&self, lags: Option<usize>) -> ResidualDiagnostics {
+        diagnose_residuals(&self.standardized_residuals(), self.seasonal_period, self.p + self.q, lags)
+    }
+
+    /// Maximum likelihood innovation variance
+    pub fn sigma2(&self) -> f64 {
+        self.filtered.sigma2
+    }
+
+    /// Exact Gaussian log-likelihood of the deseasonalized series
+    pub fn log_likelihood(&self) -> f64 {
+        self.filtered.log_likelihood
+    }
 
-        (lower, upper)
+    /// Filtered states a_{t|t}, starting after the first d observations
+    pub fn filtered_states(&self) -> &[Vec<f64>] {
+        &self.filtered.filtered_states
+    }
+
+    /// Smoothed states E[a_t | all observations], aligned with `filtered_states`
+    pub fn smoothed_states(&self) -> Vec<Vec<f64>> {
+        self.state_space.smooth(&self.filtered)
+    }
+
+    /// Smoothed values of the series on the original scale
+    ///
+    /// Missing observations are filled with their smoothed estimate; exogenous
+    /// effects are not added back.
+    pub fn smoothed_values(&self) -> Vec<f64> {
+        let start = self.filtered.start;
+        let mut values = self.original_series[..start].to_vec();
+        values.extend(self.smoothed_states().iter().enumerate().map(|(i, state)| {
+            self.state_space.observe(state) * self.seasonal_factors[(start + i) % self.seasonal_period]
+        }));
+        values
     }
 
     /// Get the estimated exogenous coefficients
     pub fn exog_coefficients(&self) -> &[f64] {
         &self.exog_coeffs
     }
+
+    /// Get the estimated AR coefficients
+    pub fn ar_coefficients(&self) -> &[f64] {
+        &self.ar_coeffs
+    }
+
+    /// Get the estimated MA coefficients
+    pub fn ma_coefficients(&self) -> &[f64] {
+        &self.ma_coeffs
+    }
+
+    /// Get the estimated intercept of the differenced series
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    /// Get the multiplicative seasonal factors
+    pub fn seasonal_factors(&self) -> &[f64] {
+        &self.seasonal_factors
+    }
+
+    /// Defaults substituted and corrections made during the last fit
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Sample size, likelihood and information criteria of the last fit
+    ///
+    /// Parameters counted: AR and MA terms, intercept, regressors and sigma².
+    pub fn fit_metadata(&self) -> FitMetadata {
+        let n_params = self.ar_coeffs.len() + self.ma_coeffs.len() + self.exog_coeffs.len() + 2;
+        FitMetadata::new(self.filtered.n_obs, n_params, self.filtered.sigma2, self.filtered.log_likelihood)
+    }
+
+    /// Add new observations with the parameters held fixed
+    ///
+    /// The Kalman filter is rolled forward from the end of the history, so
+    /// later forecasts start from the new data. Returns the one-step-ahead
+    /// error of each new point (on the original scale, before clamping),
+    /// standardized by the variance of the model before the update. A model
+    /// with a regressor takes `new_exog` as its future values (0 if omitted).
+    pub fn update(&mut self, new_obs: &[f64], new_exog: Option<&[f64]>) -> Vec<OneStepError> {
+        let n = self.original_series.len();
+        let sigma2 = self.filtered.sigma2;
+        let coef = self.exog_coeffs.first().copied().unwrap_or(0.0);
+        let exog: Vec<f64> = (0..new_obs.len())
+            .map(|i| new_exog.and_then(|e| e.get(i).copied()).unwrap_or(0.0))
+            .collect();
+
+        // Same adjustments as fit_with_exog, at the new positions
+        let effects: Vec<f64> = exog.iter().map(|&x| if x > 0.5 { coef } else { 0.0 }).collect();
+        let factors: Vec<f64> = (0..new_obs.len()).map(|i| {
+            let factor = self.seasonal_factors[(n + i) % self.seasonal_period];
+            if factor > 0.0 { factor } else { 1.0 }
+        }).collect();
+        let deseasonalized: Vec<f64> = new_obs.iter().zip(&effects).zip(&factors)
+            .map(|((y, e), f)| (y - e) / f)
+            .collect();
+
+        let next = self.state_space.filter_from(
+            &deseasonalized,
+            self.filtered.next_state.clone(),
+            self.filtered.next_cov.clone(),
+            n,
+        );
+
+        let errors = (0..new_obs.len()).map(|i| {
+            let forecast = self.state_space.observe(&next.predicted_states[i]) * factors[i] + effects[i];
+            let error = next.innovations[i] * factors[i];
+            OneStepError {
+                index: n + i,
+                actual: new_obs[i],
+                forecast,
+                error,
+                standardized_error: next.innovations[i] / (next.innovation_vars[i] * sigma2).sqrt(),
+            }
+        }).collect();
+
+        self.original_series.extend_from_slice(new_obs);
+        self.deseasonalized.extend(deseasonalized);
+        if let Some(exog_data) = self.exog_data.as_mut() {
+            exog_data.extend(exog);
+        }
+        self.filtered.append(next);
+        errors
+    }
+
+    /// Re-estimate the parameters on the full history, keeping the orders
+    pub fn refit(&mut self) {
+        let series = std::mem::take(&mut self.original_series);
+        let exog = self.exog_data.take();
+        self.fit_with_exog(&series, exog.as_deref());
+    }
+
+    /// Fitted parameters and history, for snapshots
+    pub fn state(&self) -> ArimaState {
+        ArimaState {
+            p: self.p,
+            d: self.d,
+            q: self.q,
+            seasonal_period: self.seasonal_period,
+            ar_coeffs: self.ar_coeffs.clone(),
+            ma_coeffs: self.ma_coeffs.clone(),
+            seasonal_factors: self.seasonal_factors.clone(),
+            intercept: self.intercept,
+            exog_coeffs: self.exog_coeffs.clone(),
+            series: self.original_series.clone(),
+            deseasonalized: self.deseasonalized.clone(),
+            exog: self.exog_data.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Rebuild a fitted model from its state without re-estimating anything
+    ///
+    /// Only the Kalman filter is rerun, so forecasts match the original model
+    /// exactly.
+    pub fn from_state(state: ArimaState) -> Self {
+        let state_space = StateSpace::arima(&state.ar_coeffs, &state.ma_coeffs, state.d, state.intercept);
+        let filtered = state_space.filter(&state.deseasonalized);
+        Arima {
+            p: state.p,
+            d: state.d,
+            q: state.q,
+            seasonal_period: state.seasonal_period,
+            ar_coeffs: state.ar_coeffs,
+            ma_coeffs: state.ma_coeffs,
+            seasonal_factors: state.seasonal_factors,
+            intercept: state.intercept,
+            original_series: state.series,
+            deseasonalized: state.deseasonalized,
+            state_space,
+            filtered,
+            exog_coeffs: state.exog_coeffs,
+            exog_data: state.exog,
+            warnings: state.warnings,
+        }
+    }
+}
+
+/// One-step-ahead forecast error of an observation added by `update`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OneStepError {
+    /// Position in the full series (0-based)
+    pub index: usize,
+    /// Observed value (NaN if missing)
+    pub actual: f64,
+    /// Forecast made one month earlier
+    pub forecast: f64,
+    /// actual - forecast
+    pub error: f64,
+    /// Error divided by its forecast standard deviation
+    pub standardized_error: f64,
+}
+
+/// Serializable state of a fitted `Arima`
+///
+/// Missing observations serialize as `null`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArimaState {
+    pub p: usize,
+    pub d: usize,
+    pub q: usize,
+    pub seasonal_period: usize,
+    pub ar_coeffs: Vec<f64>,
+    pub ma_coeffs: Vec<f64>,
+    pub seasonal_factors: Vec<f64>,
+    pub intercept: f64,
+    pub exog_coeffs: Vec<f64>,
+    /// Fitted history (intervention-adjusted)
+    #[serde(serialize_with = "crate::serialize_series", deserialize_with = "crate::deserialize_series")]
+    pub series: Vec<f64>,
+    /// History after removing the regressor and seasonal factors
+    #[serde(serialize_with = "crate::serialize_series", deserialize_with = "crate::deserialize_series")]
+    pub deseasonalized: Vec<f64>,
+    /// Regressor values over the history
+    pub exog: Option<Vec<f64>>,
+    pub warnings: Vec<Warning>,
 }
 
 // ============================================================================
@@ -241,7 +574,7 @@ impl Arima {
 // ============================================================================
 
 /// Calculate mean of a slice
-fn mean(data: &[f64]) -> f64 {
+pub(crate) fn mean(data: &[f64]) -> f64 {
     if data.is_empty() {
         return 0.0;
     }
@@ -257,14 +590,14 @@ fn mean(data: &[f64]) -> f64 {
 fn regress_out_exogenous(series: &[f64], exog: &[f64]) -> (Vec<f64>, f64) {
     let mut residuals = series.to_vec();
 
-    // Separate observations by exog value
+    // Separate observations by exog value (missing observations skipped)
     let with_exog: Vec<f64> = series.iter().zip(exog.iter())
-        .filter(|(_, &x)| x > 0.5)
+        .filter(|(&y, &x)| x > 0.5 && y.is_finite())
         .map(|(&y, _)| y)
         .collect();
 
     let without_exog: Vec<f64> = series.iter().zip(exog.iter())
-        .filter(|(_, &x)| x <= 0.5)
+        .filter(|(&y, &x)| x <= 0.5 && y.is_finite())
         .map(|(&y, _)| y)
         .collect();
 
@@ -285,7 +618,7 @@ fn regress_out_exogenous(series: &[f64], exog: &[f64]) -> (Vec<f64>, f64) {
 }
 
 /// Calculate multiplicative seasonal factors
-fn calculate_seasonal_factors(series: &[f64], period: usize) -> Vec<f64> {
+pub(crate) fn calculate_seasonal_factors(series: &[f64], period: usize) -> Vec<f64> {
     let mut sums = vec![0.0; period];
     let mut counts = vec![0usize; period];
 
@@ -312,7 +645,7 @@ fn calculate_seasonal_factors(series: &[f64], period: usize) -> Vec<f64> {
 }
 
 /// Deseasonalize series by dividing by seasonal factors
-fn deseasonalize(series: &[f64], factors: &[f64]) -> Vec<f64> {
+pub(crate) fn deseasonalize(series: &[f64], factors: &[f64]) -> Vec<f64> {
     series.iter().enumerate().map(|(i, &value)| {
         let factor = factors[i % factors.len()];
         if factor > 0.0 { value / factor } else { value }
@@ -320,7 +653,7 @@ fn deseasonalize(series: &[f64], factors: &[f64]) -> Vec<f64> {
 }
 
 /// Reseasonalize series by multiplying by seasonal factors
-fn reseasonalize(series: &[f64], factors: &[f64], start_idx: usize) -> Vec<f64> {
+pub(crate) fn reseasonalize(series: &[f64], factors: &[f64], start_idx: usize) -> Vec<f64> {
     series.iter().enumerate().map(|(i, &value)| {
         let factor_idx = (start_idx + i) % factors.len();
         value * factors[factor_idx]
@@ -328,7 +661,7 @@ fn reseasonalize(series: &[f64], factors: &[f64], start_idx: usize) -> Vec<f64>
 }
 
 /// Apply d-order differencing
-fn difference(series: &[f64], d: usize) -> Vec<f64> {
+pub(crate) fn difference(series: &[f64], d: usize) -> Vec<f64> {
     let mut result = series.to_vec();
     
     for _ in 0..d {
@@ -341,29 +674,8 @@ fn difference(series: &[f64], d: usize) -> Vec<f64> {
     result
 }
 
-/// Undo differencing to get back to original scale
-fn undifference(differenced: &[f64], original: &[f64], d: usize) -> Vec<f64> {
-    if d == 0 {
-        return differenced.to_vec();
-    }
-
-    let mut result = differenced.to_vec();
-
-    for ord in 0..d {
-        let last_value = original[original.len() - 1 - (d - ord - 1)];
-        let mut cumsum = vec![last_value + result[0]];
-
-        for i in 1..result.len() {
-            cumsum.push(cumsum[i - 1] + result[i]);
-        }
-        result = cumsum;
-    }
-
-    result
-}
-
 /// Calculate autocorrelation function up to max_lag
-fn autocorrelation(series: &[f64], max_lag: usize) -> Vec<f64> {
+pub(crate) fn autocorrelation(series: &[f64], max_lag: usize) -> Vec<f64> {
     let n = series.len();
     let variance = series.iter().map(|&x| x * x).sum::<f64>() / n as f64;
 
@@ -382,7 +694,7 @@ fn autocorrelation(series: &[f64], max_lag: usize) -> Vec<f64> {
 }
 
 /// Solve Yule-Walker equations using Levinson-Durbin algorithm
-fn solve_yule_walker(autocorr: &[f64]) -> Vec<f64> {
+pub(crate) fn solve_yule_walker(autocorr: &[f64]) -> Vec<f64> {
     let p = autocorr.len() - 1;
     if p == 0 {
         return vec![];
@@ -457,43 +769,244 @@ pub fn fit_and_forecast(
     forecast_months: usize,
     use_easter: bool,
 ) -> ForecastResult {
-    let mut model = Arima::new(2, 1, 1, 12);
-    
-    let (easter_coef, adjusted_series) = if use_easter {
-        let regressor = create_easter_regressor(start_year, start_month, series.len());
-        let (adj, coef) = regress_out_exogenous(series, &regressor);
-        (coef, adj)
-    } else {
-        (0.0, series.to_vec())
+    let options = ForecastOptions { use_easter, ..ForecastOptions::default() };
+    fit_and_forecast_with_options(series, start_year, start_month, forecast_months, &options)
+}
+
+/// A fitted ARIMA model together with what was decided while fitting it
+pub struct FittedArima {
+    /// The fitted model (on the intervention-adjusted series)
+    pub model: Arima,
+    /// Detected and forced interventions
+    pub outliers: Vec<DetectedOutlier>,
+    /// Differencing orders suggested by the stationarity tests (when d was not given)
+    pub differencing: Option<DifferencingSuggestion>,
+    start_year: i32,
+    start_month: u32,
+    use_easter: bool,
+    regressor: Option<Vec<f64>>,
+}
+
+impl FittedArima {
+    /// Regressor for the `steps` months after the history: the custom
+    /// regressor if one was given (0 past its end), else the calendar Easter
+    /// regressor (None if the model has neither)
+    pub fn future_regressor(&self, steps: usize) -> Option<Vec<f64>> {
+        let n = self.model.original_series.len();
+        if let Some(regressor) = &self.regressor {
+            return Some(regressor_window(regressor, n, steps));
+        }
+        self.use_easter.then(|| {
+            let (year, month) = month_offset(self.start_year, self.start_month, n);
+            create_easter_regressor(year, month, steps)
+        })
+    }
+
+    /// Forecast with intervals, carrying intervention effects forward
+    ///
+    /// `future_exog` overrides the future regressor; by default it comes from
+    /// the custom regressor or the calendar.
+    pub fn forecast(&self, steps: usize, confidence: f64, future_exog: Option<&[f64]>) -> ForecastResult {
+        let model = &self.model;
+        let n = model.original_series.len();
+        let future_exog = future_exog.map(|e| e.to_vec()).or_else(|| self.future_regressor(steps));
+
+        let mut forecast = model.forecast_unclamped(steps, future_exog.as_deref());
+
+        // Carry level shifts and decaying changes into the forecast
+        let future_effects = intervention_effects(&self.outliers, n, steps);
+        for (f, &effect) in forecast.iter_mut().zip(&future_effects) {
+            *f += effect;
+        }
+
+        // Bounds share the forecast's centre, and everything is clamped once
+        let (lower, mut upper) = interval_bounds(&forecast, &model.standard_errors(steps), z_score(confidence));
+        upper.iter_mut().for_each(|u| *u = u.max(0.0));
+        let mut warnings = model.warnings.clone();
+        warnings.extend(clamp_non_negative(&mut forecast, "forecasts"));
+
+        ForecastResult {
+            forecast,
+            lower,
+            upper,
+            seasonal_factors: model.seasonal_factors.clone(),
+            easter_coefficient: self.easter_coefficient(),
+            regressor_coefficient: self.regressor_coefficient(),
+            ar_coefficients: model.ar_coeffs.clone(),
+            ma_coefficients: model.ma_coeffs.clone(),
+            intercept: model.intercept,
+            outliers: self.outliers.clone(),
+            differencing: self.differencing,
+            warnings,
+            fit: Some(self.fit_metadata()),
+        }
+    }
+
+    /// Estimated Easter effect (0 without the calendar regressor)
+    pub fn easter_coefficient(&self) -> f64 {
+        match self.regressor {
+            Some(_) => 0.0,
+            None => self.model.exog_coeffs.first().copied().unwrap_or(0.0),
+        }
+    }
+
+    /// Estimated effect of the custom regressor, if one was given
+    pub fn regressor_coefficient(&self) -> Option<f64> {
+        self.regressor.as_ref().map(|_| self.model.exog_coeffs.first().copied().unwrap_or(0.0))
+    }
+
+    /// Fit metadata counting interventions as estimated parameters
+    pub fn fit_metadata(&self) -> FitMetadata {
+        let base = self.model.fit_metadata();
+        FitMetadata::new(base.n_obs, base.n_params + self.outliers.len(), base.sigma2, base.log_likelihood)
+    }
+
+    /// Year and month of a position in the series
+    pub fn date_of(&self, index: usize) -> (i32, u32) {
+        month_offset(self.start_year, self.start_month, index)
+    }
+
+    /// Add new months of actuals, returning their one-step-ahead errors
+    ///
+    /// Interventions and the Easter regressor (from the calendar unless
+    /// `new_exog` is given) carry over to the new months. With `refit`, the
+    /// coefficients, seasonal factors and Easter effect are re-estimated on
+    /// the extended history; the differencing order and the interventions
+    /// from the original fit are kept, so no stationarity tests or outlier
+    /// search are rerun.
+    pub fn update(&mut self, new_obs: &[f64], new_exog: Option<&[f64]>, refit: bool) -> Vec<OneStepError> {
+        let n = self.model.original_series.len();
+        let effects = intervention_effects(&self.outliers, n, new_obs.len());
+        let adjusted: Vec<f64> = new_obs.iter().zip(&effects).map(|(y, e)| y - e).collect();
+        let exog = new_exog.map(|e| e.to_vec()).or_else(|| self.future_regressor(new_obs.len()));
+
+        let mut errors = self.model.update(&adjusted, exog.as_deref());
+        for (error, &effect) in errors.iter_mut().zip(&effects) {
+            error.actual += effect;
+            error.forecast += effect;
+        }
+
+        if refit {
+            self.model.refit();
+            self.name_custom_regressor();
+        }
+        errors
+    }
+
+    /// Report warnings about the regressor against the custom regressor
+    /// when it replaced Easter (the model itself only knows one regressor)
+    fn name_custom_regressor(&mut self) {
+        if self.regressor.is_none() {
+            return;
+        }
+        for warning in self.model.warnings.iter_mut().filter(|w| w.code == WarningCode::EasterNotObserved) {
+            *warning = Warning::new(
+                WarningCode::RegressorNotObserved,
+                "Custom regressor is never active in the observed history; its coefficient is 0",
+            );
+        }
+    }
+
+    /// Serializable state, for snapshots
+    pub fn state(&self) -> FittedState {
+        FittedState {
+            model: self.model.state(),
+            outliers: self.outliers.clone(),
+            differencing: self.differencing,
+            start_year: self.start_year,
+            start_month: self.start_month,
+            use_easter: self.use_easter,
+            regressor: self.regressor.clone(),
+        }
+    }
+
+    /// Rebuild from a snapshot without refitting
+    pub fn from_state(state: FittedState) -> Self {
+        FittedArima {
+            model: Arima::from_state(state.model),
+            outliers: state.outliers,
+            differencing: state.differencing,
+            start_year: state.start_year,
+            start_month: state.start_month,
+            use_easter: state.use_easter,
+            regressor: state.regressor,
+        }
+    }
+}
+
+/// Serializable state of a `FittedArima`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FittedState {
+    pub model: ArimaState,
+    pub outliers: Vec<DetectedOutlier>,
+    pub differencing: Option<DifferencingSuggestion>,
+    pub start_year: i32,
+    pub start_month: u32,
+    pub use_easter: bool,
+    pub regressor: Option<Vec<f64>>,
+}
+
+/// Fit ARIMA with the Easter (or custom) regressor and interventions,
+/// without forecasting
+///
+/// Interventions (detected outliers plus any forced by the user) are removed
+/// from the history before fitting.
+pub fn fit_with_options(
+    series: &[f64],
+    start_year: i32,
+    start_month: u32,
+    options: &ForecastOptions,
+) -> FittedArima {
+    let differencing = options.d.is_none()
+        .then(|| suggest_differencing(series, options.seasonal_period));
+    let d = options.d.or(differencing.map(|s| s.d)).unwrap_or(1);
+
+    let search = SearchModel { p: options.p, d, q: options.q, seasonal_period: options.seasonal_period };
+    let mut model = Arima::new(search.p, search.d, search.q, search.seasonal_period);
+
+    let easter = match &options.regressor {
+        Some(regressor) => Some(regressor_window(regressor, 0, series.len())),
+        None => options.use_easter.then(|| create_easter_regressor(start_year, start_month, series.len())),
     };
-    
-    model.fit(&adjusted_series);
-    
-    // Generate future Easter regressor if needed
-    let forecast = if use_easter {
-        let last_idx = series.len();
-        let last_month = ((start_month as usize - 1 + last_idx) % 12 + 1) as u32;
-        let years_elapsed = (start_month as usize - 1 + last_idx) / 12;
-        let last_year = start_year + years_elapsed as i32;
-        
-        let future_easter = create_easter_regressor(last_year, last_month + 1, forecast_months);
-        model.forecast_with_exog(forecast_months, Some(&future_easter))
-    } else {
-        model.forecast(forecast_months)
+
+    // Remove intervention effects before fitting
+    let outliers = detect_outliers(series, easter.as_deref(), start_year, start_month, search, &options.outliers);
+    let effects = intervention_effects(&outliers, 0, series.len());
+    let adjusted_series: Vec<f64> = series.iter().zip(&effects).map(|(y, e)| y - e).collect();
+
+    model.fit_with_exog(&adjusted_series, easter.as_deref());
+
+    let mut fitted = FittedArima {
+        model,
+        outliers,
+        differencing,
+        start_year,
+        start_month,
+        use_easter: options.use_easter,
+        regressor: options.regressor.clone(),
     };
-    
-    let (lower, upper) = model.confidence_intervals(forecast_months, 0.80);
-    
-    ForecastResult {
-        forecast,
-        lower,
-        upper,
-        seasonal_factors: model.seasonal_factors.clone(),
-        easter_coefficient: easter_coef,
-        ar_coefficients: model.ar_coeffs.clone(),
-        ma_coefficients: model.ma_coeffs.clone(),
-        intercept: model.intercept,
-    }
+    fitted.name_custom_regressor();
+    fitted
+}
+
+/// `len` regressor values from `start`, 0 past the end
+fn regressor_window(regressor: &[f64], start: usize, len: usize) -> Vec<f64> {
+    (start..start + len).map(|i| regressor.get(i).copied().unwrap_or(0.0)).collect()
+}
+
+/// Fit ARIMA model and generate forecast with interventions
+///
+/// Intervention effects are carried into the forecast alongside the Easter
+/// regressor.
+pub fn fit_and_forecast_with_options(
+    series: &[f64],
+    start_year: i32,
+    start_month: u32,
+    forecast_months: usize,
+    options: &ForecastOptions,
+) -> ForecastResult {
+    fit_with_options(series, start_year, start_month, options)
+        .forecast(forecast_months, options.confidence, None)
 }
 
 #[cfg(test)]
@@ -531,5 +1044,156 @@ mod tests {
         assert!((diff[3] - 4.0).abs() < 1e-10);
     }
 
+    #[test]
+    fn test_fit_with_missing_values() {
+        let mut series: Vec<f64> = (0..48)
+            .map(|i| 1000.0 + 10.0 * i as f64 + 100.0 * ((i % 12) as f64).sin())
+            .collect();
+        series[17] = f64::NAN;
+
+        let mut model = Arima::new(2, 1, 1, 12);
+        model.fit(&series);
+
+        assert!(model.log_likelihood().is_finite());
+        assert!(model.residuals()[16].is_nan());
+
+        // The gap is filled with a value close to its neighbours
+        let smoothed = model.smoothed_values();
+        assert_eq!(smoothed.len(), series.len());
+        assert!((smoothed[17] - (1170.0 + 100.0 * 5.0f64.sin())).abs() < 100.0);
+
+        // Intervals come from the state-space variance and widen with horizon
+        let (lower, upper) = model.confidence_intervals(12, 0.80);
+        let forecast = model.forecast(12);
+        assert!(forecast.iter().all(|f| f.is_finite()));
+        assert!(upper[11] - lower[11] > upper[0] - lower[0]);
+    }
+
+    #[test]
+    fn test_forecast_adds_back_easter_effect() {
+        // Flat series with a +500 spike in every Easter invoice month
+        let regressor = create_easter_regressor(2019, 1, 72);
+        let series: Vec<f64> = regressor.iter().map(|&e| 1000.0 + 500.0 * e).collect();
+
+        let result = fit_and_forecast(&series, 2019, 1, 12, true);
+        assert!((result.easter_coefficient - 500.0).abs() < 1e-6);
+
+        // Easter 2025 (Apr 20) is invoiced in January 2025, the first forecast month
+        let future = create_easter_regressor(2025, 1, 12);
+        assert_eq!(future[0], 1.0);
+        assert!(result.forecast[0] - result.forecast[1] > 400.0);
+    }
+
+    #[test]
+    fn test_bounds_are_centred_on_the_shifted_forecast() {
+        // A forced drop late in the series is carried into the forecast
+        let series: Vec<f64> = (0..48)
+            .map(|i| if i < 42 { 1000.0 + 30.0 * (i as f64 * 0.9).sin() } else { 100.0 + 10.0 * (i as f64).cos() })
+            .collect();
+        let mut options = ForecastOptions { use_easter: false, ..Default::default() };
+        options.outliers.force.push(crate::outliers::InterventionSpec {
+            year: 2022, month: 7, kind: Some(crate::outliers::OutlierKind::LevelShift),
+        });
+        let result = fit_and_forecast_with_options(&series, 2019, 1, 6, &options);
+
+        assert!(result.forecast.iter().all(|f| *f < 500.0));
+        for ((f, l), u) in result.forecast.iter().zip(&result.lower).zip(&result.upper) {
+            assert!(l <= f && f <= u);
+            assert!(*l >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let series: Vec<f64> = (0..60)
+            .map(|i| 1000.0 + 10.0 * i as f64 + 100.0 * ((i % 12) as f64).sin() + ((i * 37) % 11) as f64)
+            .collect();
+        let mut model = Arima::new(2, 1, 1, 12);
+        model.fit(&series);
+
+        let report = model.diagnostics(None);
+        assert_eq!(report.residuals.len(), model.residuals().len());
+        assert_eq!(report.acf.len(), 11);  // min(24, 59 / 5)
+        assert_eq!(report.ljung_box.degrees_of_freedom, 8);
+        assert!((0.0..=1.0).contains(&report.ljung_box.p_value));
+    }
+
+    #[test]
+    fn test_fit_enforces_stationarity() {
+        // Without differencing, a long trend gives a lag-1 autocorrelation near 1
+        let series: Vec<f64> = (0..600).map(|i| 100.0 + i as f64).collect();
+        let mut model = Arima::new(1, 0, 0, 12);
+        model.fit(&series);
+
+        assert_eq!(model.warnings().len(), 1);
+        assert_eq!(model.warnings()[0].code, WarningCode::NonStationaryAr);
+        assert!(model.ar_coefficients()[0] <= crate::polynomial::ROOT_MARGIN + 1e-12);
+        assert!(model.forecast(24).iter().all(|f| f.is_finite()));
+
+        // A well-behaved fit has nothing to report
+        let mut model = Arima::new(2, 1, 1, 12);
+        model.fit(&series[..48]);
+        assert!(model.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_fit_warns_about_substituted_defaults() {
+        // Every March missing and no Easter month observed
+        let series: Vec<f64> = (0..36)
+            .map(|i| if i % 12 == 2 { f64::NAN } else { 1000.0 + i as f64 })
+            .collect();
+        let mut model = Arima::new(2, 1, 1, 12);
+        model.fit_with_exog(&series, Some(&[0.0; 36]));
+
+        let codes: Vec<WarningCode> = model.warnings().iter().map(|w| w.code).collect();
+        assert_eq!(codes, vec![WarningCode::EasterNotObserved, WarningCode::EmptySeasonalMonth]);
+        assert!(model.warnings()[1].message.contains("position 3"));
+
+        let fit = model.fit_metadata();
+        assert_eq!(fit.n_obs, 32);
+        assert!(fit.bic > fit.aic);
+
+        // A custom regressor is named as such
+        let options = ForecastOptions { regressor: Some(vec![0.0; 48]), ..Default::default() };
+        let fitted = fit_with_options(&series, 2020, 1, &options);
+        assert_eq!(fitted.model.warnings()[0].code, WarningCode::RegressorNotObserved);
+        assert!(fitted.model.warnings()[0].message.starts_with("Custom regressor"));
+    }
+
+    #[test]
+    fn test_update_rolls_state_forward() {
+        let regressor = create_easter_regressor(2019, 1, 60);
+        let series: Vec<f64> = (0..60)
+            .map(|i| 1000.0 + 4.0 * i as f64 + 80.0 * ((i % 12) as f64 * 0.5).sin() + 300.0 * regressor[i] + 15.0 * ((i * 7 % 11) as f64 - 5.0))
+            .collect();
+
+        let mut model = Arima::new(2, 1, 1, 12);
+        model.fit_with_exog(&series[..48], Some(&regressor[..48]));
+        let expected = model.forecast_unclamped(1, Some(&regressor[48..49]));
+        let coefficients = model.ar_coefficients().to_vec();
+
+        let errors = model.update(&series[48..], Some(&regressor[48..]));
+        assert_eq!(errors.len(), 12);
+        assert_eq!(errors[0].index, 48);
+        assert!((errors[0].forecast - expected[0]).abs() < 1e-9);
+        for e in &errors {
+            assert!((e.error - (e.actual - e.forecast)).abs() < 1e-9);
+            assert!(e.standardized_error.is_finite());
+        }
+
+        // Parameters are fixed; the filter matches one run over the whole history
+        assert_eq!(model.ar_coefficients(), &coefficients[..]);
+        let full = model.state_space.filter(&model.deseasonalized);
+        assert!((model.log_likelihood() - full.log_likelihood).abs() < 1e-9);
+        assert_eq!(model.fit_metadata().n_obs, 59);
+
+        // Refitting matches a fresh fit on the extended history
+        model.refit();
+        let mut fresh = Arima::new(2, 1, 1, 12);
+        fresh.fit_with_exog(&series, Some(&regressor));
+        assert_eq!(model.ar_coefficients(), fresh.ar_coefficients());
+        assert_eq!(model.forecast(6), fresh.forecast(6));
+    }
+
     // TODO: Add more tests as functions are implemented
 }
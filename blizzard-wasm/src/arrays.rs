@@ -0,0 +1,192 @@
+//! Typed-array forecast interface
+//!
+//! `forecast` round-trips every value through JSON text. `forecast_arrays`
+//! takes the series and regressor as `Float64Array`s and hands the forecast
+//! and bounds back as `Float64Array` views onto WASM memory, with only the
+//! remaining settings and metadata as JSON. The numbers are the same as
+//! from `forecast`.
+//!
+//! ```javascript
+//! const result = forecast_arrays(series, 2019, 1, 12, undefined, '{"confidence": 0.95}');
+//! chart.draw(result.forecast, result.lower, result.upper);
+//! const kept = result.forecast.slice();   // copy before freeing
+//! result.free();
+//! ```
+
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::error::{self, to_js_error, Error};
+use crate::{forecast_input, now_ms, ForecastInput};
+
+/// Forecast and bounds held in WASM memory
+///
+/// The array getters return views, not copies: a view is only valid until
+/// the result is freed or WASM memory grows (any later call into the module
+/// may grow it), so copy with `.slice()` anything that must outlive that.
+#[wasm_bindgen]
+pub struct ForecastArrays {
+    forecast: Vec<f64>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    details: String,
+}
+
+#[wasm_bindgen]
+impl ForecastArrays {
+    /// Point forecasts (view)
+    #[wasm_bindgen(getter)]
+    pub fn forecast(&self) -> js_sys::Float64Array {
+        // SAFETY: the view borrows `self.forecast`, which is never modified
+        // after construction; the documented lifetime rules cover the rest
+        unsafe { js_sys::Float64Array::view(&self.forecast) }
+    }
+
+    /// Lower interval bounds (view)
+    #[wasm_bindgen(getter)]
+    pub fn lower(&self) -> js_sys::Float64Array {
+        // SAFETY: as for `forecast`
+        unsafe { js_sys::Float64Array::view(&self.lower) }
+    }
+
+    /// Upper interval bounds (view)
+    #[wasm_bindgen(getter)]
+    pub fn upper(&self) -> js_sys::Float64Array {
+        // SAFETY: as for `forecast`
+        unsafe { js_sys::Float64Array::view(&self.upper) }
+    }
+
+    /// Everything else `forecast` returns, as JSON (without `forecast`,
+    /// `lower` and `upper`)
+    #[wasm_bindgen(getter)]
+    pub fn details(&self) -> String {
+        self.details.clone()
+    }
+}
+
+/// Forecast from typed arrays (NaN marks a missing month)
+///
+/// `regressor` covers the series and the forecast horizon and replaces the
+/// Easter regressor. `options` is optional JSON with any other `forecast`
+/// field (`p`, `d`, `confidence`, `method`, `outliers`, ...). Throws the
+/// JSON error object on failure.
+#[wasm_bindgen]
+pub fn forecast_arrays(
+    series: &[f64],
+    start_year: i32,
+    start_month: u32,
+    forecast_months: usize,
+    regressor: Option<Vec<f64>>,
+    options: Option<String>,
+) -> Result<ForecastArrays, JsError> {
+    run_forecast_arrays(series, start_year, start_month, forecast_months, regressor, options.as_deref())
+        .map_err(to_js_error)
+}
+
+fn run_forecast_arrays(
+    series: &[f64],
+    start_year: i32,
+    start_month: u32,
+    forecast_months: usize,
+    regressor: Option<Vec<f64>>,
+    options: Option<&str>,
+) -> error::Result<ForecastArrays> {
+    let started = now_ms();
+
+    // Settings go through serde for their defaults; the arrays bypass it
+    let mut fields: Map<String, Value> = match options {
+        Some(json) => error::from_json(json)?,
+        None => Map::new(),
+    };
+    fields.insert("series".into(), Value::Array(vec![]));
+    fields.insert("start_year".into(), start_year.into());
+    fields.insert("start_month".into(), start_month.into());
+    fields.insert("forecast_months".into(), forecast_months.into());
+    fields.remove("regressor");
+
+    let mut input: ForecastInput = error::from_value(Value::Object(fields))?;
+    input.series = series.to_vec();
+    input.regressor = regressor;
+
+    let mut output = forecast_input(input, started)?;
+    let forecast = std::mem::take(&mut output.forecast);
+    let lower = std::mem::take(&mut output.lower);
+    let upper = std::mem::take(&mut output.upper);
+
+    let mut details = serde_json::to_value(&output)
+        .map_err(|e| Error::Numerical(format!("Failed to serialize output: {}", e)))?;
+    if let Value::Object(map) = &mut details {
+        for key in ["forecast", "lower", "upper"] {
+            map.remove(key);
+        }
+    }
+
+    Ok(ForecastArrays { forecast, lower, upper, details: details.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series() -> Vec<f64> {
+        (0..48).map(|i| 700.0 + 6.0 * i as f64 + 90.0 * ((i % 12) as f64 * 0.52).sin()).collect()
+    }
+
+    fn json_forecast(input: serde_json::Value) -> serde_json::Value {
+        serde_json::from_str(&crate::forecast(&input.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_matches_json_path() {
+        let mut history = series();
+        history[10] = f64::NAN;
+        let result = run_forecast_arrays(&history, 2020, 1, 12, None, Some(r#"{"confidence": 0.95}"#)).unwrap();
+
+        let json_series: Vec<Option<f64>> = history.iter().map(|v| v.is_finite().then_some(*v)).collect();
+        let expected = json_forecast(serde_json::json!({
+            "series": json_series, "start_year": 2020, "start_month": 1, "forecast_months": 12, "confidence": 0.95,
+        }));
+
+        let as_vec = |v: &serde_json::Value| -> Vec<f64> {
+            v.as_array().unwrap().iter().map(|x| x.as_f64().unwrap()).collect()
+        };
+        assert_eq!(result.forecast, as_vec(&expected["forecast"]));
+        assert_eq!(result.lower, as_vec(&expected["lower"]));
+        assert_eq!(result.upper, as_vec(&expected["upper"]));
+
+        let details: serde_json::Value = serde_json::from_str(&result.details()).unwrap();
+        assert!(details.get("forecast").is_none());
+        assert_eq!(details["seasonal_factors"], expected["seasonal_factors"]);
+    }
+
+    #[test]
+    fn test_custom_regressor() {
+        let mut regressor = vec![0.0; 60];
+        for i in [5, 17, 29, 41, 53] {
+            regressor[i] = 1.0;
+        }
+        // Flat apart from the promotion months
+        let history: Vec<f64> = regressor[..48].iter().map(|r| 1000.0 + 250.0 * r).collect();
+
+        let result = run_forecast_arrays(&history, 2020, 1, 12, Some(regressor.clone()), None).unwrap();
+        let expected = json_forecast(serde_json::json!({
+            "series": history, "start_year": 2020, "start_month": 1, "forecast_months": 12, "regressor": regressor,
+        }));
+        assert_eq!(result.forecast[5], expected["forecast"][5].as_f64().unwrap());
+
+        let details: serde_json::Value = serde_json::from_str(&result.details()).unwrap();
+        assert!((details["regressor_coefficient"].as_f64().unwrap() - 250.0).abs() < 1e-6);
+        assert_eq!(details["easter_coefficient"], 0.0);
+        assert!(result.forecast[5] - result.forecast[4] > 200.0);
+
+        // Too short to cover the horizon
+        let err = run_forecast_arrays(&history, 2020, 1, 12, Some(vec![0.0; 50]), None).err().unwrap();
+        assert_eq!(err.field(), Some("regressor"));
+
+        // A continuous regressor (e.g. a price index) is not a dummy
+        let prices: Vec<f64> = (0..60).map(|i| 100.0 + 0.5 * i as f64).collect();
+        let err = run_forecast_arrays(&history, 2020, 1, 12, Some(prices), None).err().unwrap();
+        assert_eq!(err.field(), Some("regressor"));
+        assert!(err.to_string().contains("0 or 1"));
+    }
+}
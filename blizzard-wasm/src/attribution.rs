@@ -0,0 +1,177 @@
+//! How much each scenario adjustment contributed to the forecast
+//!
+//! Each adjustment is run on its own against the baseline, and its impact is
+//! the change it makes to the baseline forecast, month by month. Adjustments
+//! that overlap or compound do not add up exactly; the difference between
+//! the scenario and the baseline plus every impact is reported as an
+//! interaction remainder. The result reads as a waterfall: the baseline, one
+//! step per adjustment in list order, the interaction, then the scenario.
+//!
+//! New business appends months to the history, so runs can start their
+//! forecasts at different months. Every run is forecast far enough ahead to
+//! cover the scenario's forecast months, and those are the months compared.
+//! This costs one extra forecast per adjustment plus the baseline.
+
+use serde::Serialize;
+
+use crate::easter::month_offset;
+use crate::error::Result;
+use crate::scenario::{forecast_window, AdjustedSeries, Baseline, Scenario};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Baseline, per-adjustment steps and scenario, ready for a waterfall chart
+#[derive(Debug, Clone, Serialize)]
+pub struct Waterfall {
+    /// Forecast months compared, "YYYY-MM"
+    pub months: Vec<String>,
+    pub baseline: Bar,
+    pub steps: Vec<WaterfallStep>,
+    pub scenario: Bar,
+}
+
+/// A forecast over the compared months
+#[derive(Debug, Clone, Serialize)]
+pub struct Bar {
+    pub total: f64,
+    pub monthly: Vec<f64>,
+}
+
+/// What a step stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepKind {
+    Adjustment,
+    /// What the adjustments do together beyond the sum of their own impacts
+    Interaction,
+}
+
+/// One step from the baseline towards the scenario
+#[derive(Debug, Clone, Serialize)]
+pub struct WaterfallStep {
+    pub kind: StepKind,
+    /// Position in the adjustment list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adjustment: Option<usize>,
+    pub label: String,
+    /// Change to the forecast over all months and by month
+    pub total: f64,
+    pub monthly: Vec<f64>,
+    /// Running total before and after the step
+    pub from: f64,
+    pub to: f64,
+}
+
+// ============================================================================
+// Attribution
+// ============================================================================
+
+/// Attribute the scenario forecast to its adjustments
+///
+/// `adjusted` and `forecast` are the scenario's own run, with the
+/// forecast-side adjustments already applied.
+pub fn attribute(baseline: &Baseline, scenario: &Scenario, adjusted: &AdjustedSeries, forecast: &[f64]) -> Result<Waterfall> {
+    let history = adjusted.series.len();
+    let horizon = forecast.len();
+
+    // Forecast the months the scenario forecasts, from a shorter history if need be
+    let run = |adjustments| -> Result<Vec<f64>> {
+        let (_, output) = forecast_window(baseline, adjustments, scenario.composition, &scenario.forecast_options, history, horizon)?;
+        Ok(output.forecast)
+    };
+
+    let base = run(&[])?;
+    let bar = |monthly: Vec<f64>| Bar { total: monthly.iter().sum(), monthly };
+    let mut steps = vec![];
+    let mut explained = base.clone();
+    let mut running = base.iter().sum::<f64>();
+
+    for (index, adjustment) in scenario.adjustments.iter().enumerate() {
+        let alone = run(std::slice::from_ref(adjustment))?;
+        let monthly: Vec<f64> = alone.iter().zip(&base).map(|(a, b)| a - b).collect();
+        explained.iter_mut().zip(&monthly).for_each(|(e, m)| *e += m);
+        steps.push(step(StepKind::Adjustment, Some(index), adjustment.label(index), monthly, &mut running));
+    }
+    let remainder = forecast.iter().zip(&explained).map(|(f, e)| f - e).collect();
+    steps.push(step(StepKind::Interaction, None, "Interaction".to_string(), remainder, &mut running));
+
+    Ok(Waterfall {
+        months: (0..horizon)
+            .map(|k| {
+                let (year, month) = month_offset(adjusted.start_year, adjusted.start_month, history + k);
+                format!("{}-{:02}", year, month)
+            })
+            .collect(),
+        baseline: bar(base),
+        steps,
+        scenario: bar(forecast.to_vec()),
+    })
+}
+
+fn step(kind: StepKind, adjustment: Option<usize>, label: String, monthly: Vec<f64>, running: &mut f64) -> WaterfallStep {
+    let total = monthly.iter().sum::<f64>();
+    let from = *running;
+    *running += total;
+    WaterfallStep { kind, adjustment, label, total, monthly, from, to: *running }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenario::run_scenario;
+    use crate::scenario::tests::baseline_json;
+
+    #[test]
+    fn test_steps_run_from_baseline_to_scenario() {
+        let scenario = serde_json::json!({
+            "name": "Mixed", "attribution": true,
+            "adjustments": [
+                { "id": "uplift", "type": "scale", "target_type": "customer", "target_key": "ACME", "factor": 2.0 },
+                { "type": "remove", "target_type": "geography", "target_key": "EU" },
+                { "type": "floor", "start_month": "2025-06", "months": 2, "limit": 2000.0, "apply_to": "forecast" },
+            ],
+            "forecast_options": { "forecast_months": 6 },
+        });
+        let output = run_scenario(&baseline_json(), &scenario.to_string()).unwrap();
+        let waterfall = output.attribution.unwrap();
+
+        assert_eq!(waterfall.months.first().map(String::as_str), Some("2025-01"));
+        assert_eq!(waterfall.steps.len(), 4);
+        assert_eq!(waterfall.steps[0].label, "uplift");
+        assert_eq!(waterfall.steps[1].label, "Adjustment 2");
+        // The steps chain from the baseline total to the scenario total
+        assert!((waterfall.steps[0].from - waterfall.baseline.total).abs() < 1e-9);
+        assert!((waterfall.steps[3].to - waterfall.scenario.total).abs() < 1e-6);
+        assert!(waterfall.steps.windows(2).all(|w| (w[0].to - w[1].from).abs() < 1e-9));
+        // Scaling the total by 1.1 and then 0.9 leaves a -1% interaction
+        assert!(waterfall.steps[0].total > 0.0 && waterfall.steps[1].total < 0.0);
+        assert!(waterfall.steps[3].total < 0.0);
+        // The floor only touches June and July
+        assert_eq!(waterfall.steps[2].monthly[..5].iter().filter(|m| **m != 0.0).count(), 0);
+        assert_eq!(output.forecast.forecast, waterfall.scenario.monthly);
+    }
+
+    #[test]
+    fn test_new_business_is_compared_over_the_scenario_months() {
+        let scenario = serde_json::json!({
+            "name": "Launch", "attribution": true,
+            "adjustments": [{
+                "type": "new_business", "product_group": "S5", "geography": "MEAE",
+                "start_month": "2024-06", "year1_value": 1200.0, "year2_value": 2400.0, "year3_value": 3600.0,
+            }],
+            "forecast_options": { "forecast_months": 3 },
+        });
+        let output = run_scenario(&baseline_json(), &scenario.to_string()).unwrap();
+        let waterfall = output.attribution.unwrap();
+
+        // The appended year moves the forecast on to 2026
+        assert_eq!(waterfall.months, vec!["2026-01", "2026-02", "2026-03"]);
+        assert_eq!(waterfall.baseline.monthly.len(), 3);
+        // A single adjustment explains everything
+        assert!(waterfall.steps[1].monthly.iter().all(|m| m.abs() < 1e-6));
+
+        let without = serde_json::json!({ "name": "Plain", "adjustments": [] });
+        assert!(run_scenario(&baseline_json(), &without.to_string()).unwrap().attribution.is_none());
+    }
+}
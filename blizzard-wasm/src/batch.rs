@@ -0,0 +1,149 @@
+//! Batch forecasting of many keyed series in one call
+//!
+//! The dashboard forecasts the overall total plus every product group,
+//! customer and geography. A batch shares one set of defaults and lets each
+//! series override any `forecast` field:
+//!
+//! ```json
+//! {
+//!   "defaults": { "start_year": 2019, "start_month": 1, "forecast_months": 12 },
+//!   "series": {
+//!     "total": { "series": [...] },
+//!     "customer/ACME": { "series": [...], "use_easter_regressor": false }
+//!   }
+//! }
+//! ```
+//!
+//! Overrides replace default fields whole (an `outliers` override replaces
+//! all outlier settings). Each series succeeds or fails on its own; a failed
+//! series carries the usual error object in place of its forecast.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{from_json, from_value, Error, Result};
+use crate::{forecast_input, now_ms, ForecastInput, ForecastOutput};
+
+/// Batch request: shared defaults and per-series overrides, by key
+#[derive(Deserialize)]
+pub struct BatchInput {
+    /// Fields applied to every series unless overridden
+    #[serde(default)]
+    pub defaults: Map<String, Value>,
+    /// Per-series fields (at least `series`), by key
+    pub series: Map<String, Value>,
+}
+
+/// Forecast or error for one series
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BatchResult {
+    Forecast(Box<ForecastOutput>),
+    Error(Error),
+}
+
+impl BatchResult {
+    fn is_error(&self) -> bool {
+        matches!(self, BatchResult::Error(_))
+    }
+}
+
+/// Batch results, by key
+#[derive(Serialize)]
+pub struct BatchOutput {
+    pub results: BTreeMap<String, BatchResult>,
+    /// Number of series forecast successfully
+    pub succeeded: usize,
+    /// Number of series that returned an error
+    pub failed: usize,
+    /// Time spent on the whole batch
+    pub runtime_ms: f64,
+}
+
+/// Forecast every series, calling `progress(completed, total, key)` after each
+pub fn run_batch(input_json: &str, mut progress: impl FnMut(usize, usize, &str)) -> Result<BatchOutput> {
+    let started = now_ms();
+    let input: BatchInput = from_json(input_json)?;
+
+    let total = input.series.len();
+    let mut results = BTreeMap::new();
+    for (done, (key, overrides)) in input.series.into_iter().enumerate() {
+        let result = match forecast_one(&input.defaults, overrides) {
+            Ok(output) => BatchResult::Forecast(Box::new(output)),
+            Err(err) => BatchResult::Error(err),
+        };
+        progress(done + 1, total, &key);
+        results.insert(key, result);
+    }
+
+    let failed = results.values().filter(|r| r.is_error()).count();
+    Ok(BatchOutput {
+        succeeded: results.len() - failed,
+        failed,
+        results,
+        runtime_ms: now_ms() - started,
+    })
+}
+
+fn forecast_one(defaults: &Map<String, Value>, overrides: Value) -> Result<ForecastOutput> {
+    let started = now_ms();
+    let Value::Object(overrides) = overrides else {
+        return Err(Error::validation("series", "Each batch entry must be an object of forecast fields"));
+    };
+
+    let mut merged = defaults.clone();
+    merged.extend(overrides);
+    let input: ForecastInput = from_value(Value::Object(merged))?;
+    forecast_input(input, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(level: f64) -> Vec<f64> {
+        (0..36).map(|i| level + 5.0 * i as f64 + 0.1 * level * ((i % 12) as f64).sin()).collect()
+    }
+
+    #[test]
+    fn test_batch_merges_defaults_and_isolates_errors() {
+        let input = serde_json::json!({
+            "defaults": { "start_year": 2020, "start_month": 1, "forecast_months": 6 },
+            "series": {
+                "total": { "series": series(5000.0) },
+                "group/A": { "series": series(800.0), "forecast_months": 3 },
+                "group/B": { "series": [1.0, 2.0] },
+                "group/C": { "series": series(300.0), "start_month": 13 },
+                "group/D": 42,
+            }
+        });
+
+        let mut calls = vec![];
+        let output = run_batch(&input.to_string(), |done, total, key| calls.push((done, total, key.to_string()))).unwrap();
+
+        assert_eq!((output.succeeded, output.failed), (2, 3));
+        assert_eq!(calls.len(), 5);
+        assert_eq!(calls.last().unwrap().0, 5);
+        assert!(calls.iter().all(|c| c.1 == 5));
+
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["results"]["total"]["forecast"].as_array().unwrap().len(), 6);
+        assert_eq!(json["results"]["group/A"]["forecast"].as_array().unwrap().len(), 3);
+        assert_eq!(json["results"]["group/B"]["field"], "series");
+        assert_eq!(json["results"]["group/C"]["field"], "start_month");
+        assert_eq!(json["results"]["group/D"]["code"], "validation_error");
+
+        // The batch matches individual forecast() calls
+        let single = serde_json::json!({ "series": series(5000.0), "start_year": 2020, "start_month": 1, "forecast_months": 6 });
+        let single: serde_json::Value = serde_json::from_str(&crate::forecast(&single.to_string())).unwrap();
+        assert_eq!(json["results"]["total"]["forecast"], single["forecast"]);
+    }
+
+    #[test]
+    fn test_batch_requires_series_map() {
+        let err = run_batch(r#"{"defaults": {}}"#, |_, _, _| {}).err().unwrap();
+        assert_eq!(err.field(), Some("series"));
+    }
+}
@@ -0,0 +1,395 @@
+//! Benchmark forecasters: naive, seasonal naive, drift, mean and Theta
+//!
+//! These give every series something to compare ARIMA against and a safe
+//! fallback when the ARIMA fit is degenerate. They return the same
+//! `ForecastResult` as ARIMA and build intervals with the same helpers.
+//! Standard errors follow the usual closed forms (Hyndman & Athanasopoulos,
+//! "Forecasting: Principles and Practice", section 5.5).
+
+use serde::{Deserialize, Serialize};
+
+use crate::arima::{
+    self, calculate_seasonal_factors, deseasonalize, mean, reseasonalize, ForecastOptions, ForecastResult,
+};
+use crate::ensemble::{fit_ensemble, EnsembleConfig};
+use crate::intervals::{interval_bounds, z_score};
+use crate::warnings::{clamp_non_negative, Warning};
+
+/// Forecasting method selectable from the WASM interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    /// Seasonal ARIMA(2,1,1) with optional Easter regressor
+    #[default]
+    Arima,
+    /// Last observed value
+    Naive,
+    /// Value from the same month last season
+    SeasonalNaive,
+    /// Last value plus the average historical change
+    Drift,
+    /// Historical mean
+    Mean,
+    /// Theta method (SES with half the linear trend), seasonally adjusted
+    Theta,
+    /// Weighted combination of several methods (see `ensemble`)
+    Ensemble,
+}
+
+/// Fit any supported method and forecast in one call
+///
+/// Easter and outlier handling only apply to ARIMA; the benchmarks ignore
+/// them. Missing values (NaN) are handled by ARIMA's Kalman filter and
+/// linearly interpolated for the benchmarks. `ensemble` configures
+/// `Method::Ensemble`.
+pub fn fit_and_forecast_method(
+    method: Method,
+    series: &[f64],
+    start_year: i32,
+    start_month: u32,
+    forecast_months: usize,
+    options: &ForecastOptions,
+    ensemble: &EnsembleConfig,
+) -> ForecastResult {
+    match method {
+        Method::Arima => arima::fit_and_forecast_with_options(series, start_year, start_month, forecast_months, options),
+        Method::Ensemble => {
+            fit_ensemble(ensemble, series, start_year, start_month, forecast_months, options).combined
+        }
+        _ => forecast_benchmark(
+            method,
+            &interpolate_missing(series),
+            options.seasonal_period,
+            forecast_months,
+            options.confidence,
+        ),
+    }
+}
+
+/// Fill NaN gaps by linear interpolation, carrying the ends flat
+fn interpolate_missing(series: &[f64]) -> Vec<f64> {
+    let known: Vec<usize> = (0..series.len()).filter(|&i| series[i].is_finite()).collect();
+    if known.len() == series.len() || known.is_empty() {
+        return series.to_vec();
+    }
+
+    (0..series.len())
+        .map(|i| {
+            let next = known.partition_point(|&k| k < i);
+            match (next.checked_sub(1).map(|j| known[j]), known.get(next)) {
+                (_, Some(&hi)) if hi == i => series[i],
+                (Some(lo), Some(&hi)) => {
+                    let w = (i - lo) as f64 / (hi - lo) as f64;
+                    series[lo] + w * (series[hi] - series[lo])
+                }
+                (Some(lo), None) => series[lo],
+                (None, Some(&hi)) => series[hi],
+                (None, None) => 0.0,
+            }
+        })
+        .collect()
+}
+
+/// Forecast with a benchmark method
+///
+/// `Method::Arima` and `Method::Ensemble` are not benchmarks and yield an
+/// empty result.
+pub fn forecast_benchmark(
+    method: Method,
+    series: &[f64],
+    seasonal_period: usize,
+    steps: usize,
+    confidence: f64,
+) -> ForecastResult {
+    let (forecast, standard_errors, seasonal_factors) = match method {
+        Method::Naive => naive(series, steps),
+        Method::SeasonalNaive => seasonal_naive(series, seasonal_period, steps),
+        Method::Drift => drift(series, steps),
+        Method::Mean => mean_forecast(series, steps),
+        Method::Theta => theta(series, seasonal_period, steps),
+        Method::Arima | Method::Ensemble => (vec![], vec![], vec![]),
+    };
+
+    let mut forecast = forecast;
+    let warnings: Vec<Warning> = clamp_non_negative(&mut forecast, "forecasts").into_iter().collect();
+    let (lower, upper) = interval_bounds(&forecast, &standard_errors, z_score(confidence));
+
+    ForecastResult {
+        forecast,
+        lower,
+        upper,
+        seasonal_factors,
+        easter_coefficient: 0.0,
+        regressor_coefficient: None,
+        ar_coefficients: vec![],
+        ma_coefficients: vec![],
+        intercept: 0.0,
+        outliers: vec![],
+        differencing: None,
+        warnings,
+        fit: None,
+    }
+}
+
+/// Check whether a forecast is unusable and should be replaced by a benchmark
+///
+/// Degenerate means non-finite values, forecasts exploding past ten times the
+/// historical peak, or forecasts collapsed to zero for a positive series.
+pub fn is_degenerate(result: &ForecastResult, series: &[f64]) -> bool {
+    let all_finite = result.forecast.iter()
+        .chain(&result.lower)
+        .chain(&result.upper)
+        .all(|x| x.is_finite());
+    if !all_finite {
+        return true;
+    }
+
+    let peak = series.iter().fold(0.0f64, |acc, &x| acc.max(x.abs()));
+    let exploded = result.forecast.iter().any(|&f| f > 10.0 * peak);
+    let collapsed = peak > 0.0 && mean(series) > 0.0
+        && !result.forecast.is_empty()
+        && result.forecast.iter().all(|&f| f == 0.0);
+
+    exploded || collapsed
+}
+
+// ============================================================================
+// Individual methods - each returns (forecast, standard errors, seasonal factors)
+// ============================================================================
+
+type Benchmark = (Vec<f64>, Vec<f64>, Vec<f64>);
+
+/// Root mean square of one-step errors
+fn residual_sd(errors: &[f64]) -> f64 {
+    if errors.is_empty() {
+        return 0.0;
+    }
+    (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt()
+}
+
+fn naive(series: &[f64], steps: usize) -> Benchmark {
+    let last = series.last().copied().unwrap_or(0.0);
+    let errors: Vec<f64> = series.windows(2).map(|w| w[1] - w[0]).collect();
+    let sigma = residual_sd(&errors);
+
+    let forecast = vec![last; steps];
+    let se = (1..=steps).map(|h| sigma * (h as f64).sqrt()).collect();
+    (forecast, se, vec![])
+}
+
+fn seasonal_naive(series: &[f64], period: usize, steps: usize) -> Benchmark {
+    if series.len() < period || period == 0 {
+        return naive(series, steps);
+    }
+
+    let n = series.len();
+    let errors: Vec<f64> = (period..n).map(|i| series[i] - series[i - period]).collect();
+    let sigma = residual_sd(&errors);
+
+    let forecast = (0..steps).map(|h| series[n - period + h % period]).collect();
+    let se = (0..steps)
+        .map(|h| sigma * ((h / period + 1) as f64).sqrt())
+        .collect();
+    (forecast, se, vec![])
+}
+
+fn drift(series: &[f64], steps: usize) -> Benchmark {
+    let n = series.len();
+    if n < 2 {
+        return naive(series, steps);
+    }
+
+    let slope = (series[n - 1] - series[0]) / (n - 1) as f64;
+    let errors: Vec<f64> = series.windows(2).map(|w| w[1] - w[0] - slope).collect();
+    let sigma = residual_sd(&errors);
+    let t = (n - 1) as f64;
+
+    let forecast = (1..=steps).map(|h| series[n - 1] + slope * h as f64).collect();
+    let se = (1..=steps)
+        .map(|h| {
+            let h = h as f64;
+            sigma * (h * (1.0 + h / t)).sqrt()
+        })
+        .collect();
+    (forecast, se, vec![])
+}
+
+fn mean_forecast(series: &[f64], steps: usize) -> Benchmark {
+    let mu = mean(series);
+    let errors: Vec<f64> = series.iter().map(|&x| x - mu).collect();
+    let sigma = residual_sd(&errors);
+    let t = series.len().max(1) as f64;
+
+    let forecast = vec![mu; steps];
+    let se = vec![sigma * (1.0 + 1.0 / t).sqrt(); steps];
+    (forecast, se, vec![])
+}
+
+/// Theta method in its SES-with-drift form (Hyndman & Billah, 2003)
+///
+/// The series is seasonally adjusted with the same multiplicative factors
+/// as ARIMA when at least two full seasons are available.
+fn theta(series: &[f64], period: usize, steps: usize) -> Benchmark {
+    let n = series.len();
+    if n < 3 {
+        return naive(series, steps);
+    }
+
+    let seasonal = period > 1 && n >= 2 * period;
+    let factors = if seasonal {
+        calculate_seasonal_factors(series, period)
+    } else {
+        vec![]
+    };
+    let adjusted = if seasonal { deseasonalize(series, &factors) } else { series.to_vec() };
+
+    // Theta=0 line: slope of the linear trend
+    let slope = linear_trend_slope(&adjusted);
+
+    // Theta=2 line is extrapolated with SES; alpha chosen by minimising SSE
+    let alpha = golden_section(0.01, 0.99, |a| ses(&adjusted, a).1);
+    let (level, sse) = ses(&adjusted, alpha);
+    let sigma = (sse / (n - 1) as f64).sqrt();
+
+    let decay = (1.0 - alpha).powi(n as i32);
+    let forecast_adj: Vec<f64> = (1..=steps)
+        .map(|h| level + slope / 2.0 * ((h - 1) as f64 + 1.0 / alpha - decay / alpha))
+        .collect();
+    let se_adj: Vec<f64> = (1..=steps)
+        .map(|h| sigma * (1.0 + alpha * alpha * (h - 1) as f64).sqrt())
+        .collect();
+
+    if seasonal {
+        let start = n % period;
+        let forecast = reseasonalize(&forecast_adj, &factors, start);
+        let se = reseasonalize(&se_adj, &factors, start);
+        (forecast, se, factors)
+    } else {
+        (forecast_adj, se_adj, vec![])
+    }
+}
+
+/// Simple exponential smoothing; returns (final level, one-step SSE)
+fn ses(series: &[f64], alpha: f64) -> (f64, f64) {
+    let mut level = series[0];
+    let mut sse = 0.0;
+
+    for &y in &series[1..] {
+        let error = y - level;
+        sse += error * error;
+        level += alpha * error;
+    }
+
+    (level, sse)
+}
+
+/// OLS slope of the series against time
+fn linear_trend_slope(series: &[f64]) -> f64 {
+    let n = series.len() as f64;
+    let t_mean = (n - 1.0) / 2.0;
+    let y_mean = mean(series);
+
+    let (num, den) = series.iter().enumerate().fold((0.0, 0.0), |(num, den), (t, &y)| {
+        let dt = t as f64 - t_mean;
+        (num + dt * (y - y_mean), den + dt * dt)
+    });
+
+    if den > 0.0 { num / den } else { 0.0 }
+}
+
+/// Minimise a unimodal function on [lo, hi]
+fn golden_section<F: Fn(f64) -> f64>(mut lo: f64, mut hi: f64, f: F) -> f64 {
+    let ratio = (5.0f64.sqrt() - 1.0) / 2.0;
+    let mut x1 = hi - ratio * (hi - lo);
+    let mut x2 = lo + ratio * (hi - lo);
+    let mut f1 = f(x1);
+    let mut f2 = f(x2);
+
+    for _ in 0..60 {
+        if f1 < f2 {
+            hi = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = hi - ratio * (hi - lo);
+            f1 = f(x1);
+        } else {
+            lo = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = lo + ratio * (hi - lo);
+            f2 = f(x2);
+        }
+    }
+
+    (lo + hi) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seasonal_series(years: usize) -> Vec<f64> {
+        (0..years * 12)
+            .map(|i| 1000.0 + 5.0 * i as f64 + 200.0 * ((i % 12) as f64 / 12.0 * std::f64::consts::TAU).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_naive_and_seasonal_naive() {
+        let series = seasonal_series(3);
+
+        let naive = forecast_benchmark(Method::Naive, &series, 12, 3, 0.80);
+        assert!(naive.forecast.iter().all(|&f| f == *series.last().unwrap()));
+        // Interval widens with the horizon
+        assert!(naive.upper[2] - naive.lower[2] > naive.upper[0] - naive.lower[0]);
+
+        let snaive = forecast_benchmark(Method::SeasonalNaive, &series, 12, 12, 0.80);
+        assert_eq!(snaive.forecast, series[24..].to_vec());
+    }
+
+    #[test]
+    fn test_drift_and_mean() {
+        let series: Vec<f64> = (0..10).map(|i| 10.0 + 2.0 * i as f64).collect();
+
+        let drift = forecast_benchmark(Method::Drift, &series, 12, 2, 0.80);
+        assert!((drift.forecast[0] - 30.0).abs() < 1e-10);
+        assert!((drift.forecast[1] - 32.0).abs() < 1e-10);
+
+        let mean = forecast_benchmark(Method::Mean, &series, 12, 2, 0.80);
+        assert!((mean.forecast[0] - 19.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_theta_follows_trend() {
+        let series = seasonal_series(5);
+        let result = forecast_benchmark(Method::Theta, &series, 12, 12, 0.80);
+
+        assert_eq!(result.seasonal_factors.len(), 12);
+        // Forecast a year ahead should sit above last year's average
+        assert!(mean(&result.forecast) > mean(&series[48..]));
+        for i in 0..12 {
+            assert!(result.lower[i] <= result.forecast[i]);
+            assert!(result.upper[i] >= result.forecast[i]);
+        }
+    }
+
+    #[test]
+    fn test_interpolate_missing() {
+        let filled = interpolate_missing(&[f64::NAN, 10.0, f64::NAN, 20.0, f64::NAN]);
+        assert_eq!(filled, vec![10.0, 10.0, 15.0, 20.0, 20.0]);
+    }
+
+    #[test]
+    fn test_is_degenerate() {
+        let series = seasonal_series(3);
+        let mut result = forecast_benchmark(Method::Naive, &series, 12, 3, 0.80);
+        assert!(!is_degenerate(&result, &series));
+
+        result.upper[1] = f64::NAN;
+        assert!(is_degenerate(&result, &series));
+
+        result.upper[1] = 0.0;
+        result.forecast = vec![0.0; 3];
+        assert!(is_degenerate(&result, &series));
+    }
+}
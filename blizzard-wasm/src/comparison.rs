@@ -0,0 +1,268 @@
+//! Side-by-side comparison of several scenarios
+//!
+//! The baseline is forecast once and every scenario is run against it over
+//! the same months: the horizon after the longest adjusted history (new
+//! business appends months, so a scenario with more of it starts later, and
+//! the others are forecast further ahead to line up, as for attribution).
+//! The comparison's `forecast_options` apply to every run, over each
+//! scenario's own.
+//!
+//! Each scenario gets its aligned forecast and bounds, the change from the
+//! baseline per month and per calendar year, and a place in a table ranked
+//! by total. Probabilities of one scenario exceeding another need simulated
+//! sample paths; forecasts here carry analytic intervals only, so none are
+//! given.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::conflicts::Composition;
+use crate::easter::month_offset;
+use crate::error::{from_json, Error, Result};
+use crate::scenario::{apply_adjustments, forecast_window, Baseline, Scenario};
+use crate::ForecastOutput;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Input to `compare_scenarios`
+#[derive(Deserialize)]
+pub struct ComparisonInput {
+    pub baseline: Baseline,
+    pub scenarios: Vec<Scenario>,
+    /// `forecast` fields for every run (default: 12 months)
+    #[serde(default)]
+    pub forecast_options: Map<String, Value>,
+}
+
+/// Every scenario against the baseline over the same months
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    /// Months compared, "YYYY-MM"
+    pub months: Vec<String>,
+    pub baseline: ComparedSeries,
+    /// In input order
+    pub scenarios: Vec<ComparedScenario>,
+    /// Scenarios by total, highest first
+    pub ranking: Vec<RankedScenario>,
+}
+
+/// A forecast over the compared months
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedSeries {
+    pub forecast: Vec<f64>,
+    pub lower: Vec<f64>,
+    pub upper: Vec<f64>,
+    pub total: f64,
+    pub annual: Vec<YearTotal>,
+}
+
+/// One scenario's forecast and its change from the baseline
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedScenario {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub series: ComparedSeries,
+    /// Change from the baseline each month
+    pub deltas: Vec<f64>,
+    pub delta: f64,
+    /// Overlapping adjustments within the scenario
+    pub conflicts: usize,
+}
+
+/// Total over the compared months of one calendar year
+#[derive(Debug, Clone, Serialize)]
+pub struct YearTotal {
+    pub year: i32,
+    /// Compared months falling in the year
+    pub months: usize,
+    pub total: f64,
+    /// Change from the baseline (scenarios only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<f64>,
+}
+
+/// A row of the ranked summary table
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedScenario {
+    /// 1 for the highest total
+    pub rank: usize,
+    /// Position in the input
+    pub index: usize,
+    pub id: String,
+    pub name: String,
+    pub total: f64,
+    pub delta: f64,
+    /// Change as a share of the baseline total (absent when that is zero)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_percent: Option<f64>,
+}
+
+// ============================================================================
+// Comparison
+// ============================================================================
+
+/// Run every scenario against a shared baseline forecast
+pub fn compare_scenarios(input: &ComparisonInput) -> Result<Comparison> {
+    if input.scenarios.is_empty() {
+        return Err(Error::validation("scenarios", "At least one scenario is needed"));
+    }
+    let horizon = match input.forecast_options.get("forecast_months") {
+        Some(months) => months.as_u64()
+            .filter(|m| *m > 0)
+            .ok_or_else(|| Error::validation("forecast_months", "forecast_months must be a positive integer"))? as usize,
+        None => 12,
+    };
+
+    // The window starts after the longest adjusted history
+    let base = apply_adjustments(&input.baseline, &[], Composition::default())?;
+    let mut history = base.series.len();
+    for scenario in &input.scenarios {
+        history = history.max(apply_adjustments(&input.baseline, &scenario.adjustments, scenario.composition)?.series.len());
+    }
+    let months: Vec<(i32, u32)> = (0..horizon).map(|k| month_offset(base.start_year, base.start_month, history + k)).collect();
+
+    let (_, base_output) = forecast_window(&input.baseline, &[], Composition::default(), &input.forecast_options, history, horizon)?;
+    let baseline = compared(base_output, &months, None);
+
+    let mut scenarios = vec![];
+    for scenario in &input.scenarios {
+        let mut options = scenario.forecast_options.clone();
+        options.extend(input.forecast_options.clone());
+        let (adjusted, output) = forecast_window(&input.baseline, &scenario.adjustments, scenario.composition, &options, history, horizon)?;
+        let series = compared(output, &months, Some(&baseline));
+        let deltas: Vec<f64> = series.forecast.iter().zip(&baseline.forecast).map(|(s, b)| s - b).collect();
+        scenarios.push(ComparedScenario {
+            id: scenario.id.clone(),
+            name: scenario.name.clone(),
+            delta: series.total - baseline.total,
+            series,
+            deltas,
+            conflicts: adjusted.conflicts.len(),
+        });
+    }
+
+    let mut order: Vec<usize> = (0..scenarios.len()).collect();
+    order.sort_by(|&a, &b| scenarios[b].series.total.total_cmp(&scenarios[a].series.total));
+    let ranking = order.iter().enumerate()
+        .map(|(rank, &index)| {
+            let scenario = &scenarios[index];
+            RankedScenario {
+                rank: rank + 1,
+                index,
+                id: scenario.id.clone(),
+                name: scenario.name.clone(),
+                total: scenario.series.total,
+                delta: scenario.delta,
+                delta_percent: (baseline.total != 0.0).then(|| 100.0 * scenario.delta / baseline.total),
+            }
+        })
+        .collect();
+
+    Ok(Comparison {
+        months: months.iter().map(|(year, month)| format!("{}-{:02}", year, month)).collect(),
+        baseline,
+        scenarios,
+        ranking,
+    })
+}
+
+/// `compare_scenarios` on JSON input
+pub fn compare_scenarios_json(input_json: &str) -> Result<Comparison> {
+    let input: ComparisonInput = from_json(input_json)?;
+    compare_scenarios(&input)
+}
+
+/// Forecast with totals by calendar year, and deltas against `baseline`
+fn compared(output: ForecastOutput, months: &[(i32, u32)], baseline: Option<&ComparedSeries>) -> ComparedSeries {
+    let mut years: BTreeMap<i32, (usize, f64)> = BTreeMap::new();
+    for ((year, _), value) in months.iter().zip(&output.forecast) {
+        let entry = years.entry(*year).or_default();
+        entry.0 += 1;
+        entry.1 += value;
+    }
+    let annual = years.into_iter().enumerate()
+        .map(|(i, (year, (months, total)))| YearTotal {
+            year,
+            months,
+            total,
+            delta: baseline.map(|b| total - b.annual[i].total),
+        })
+        .collect();
+    ComparedSeries {
+        total: output.forecast.iter().sum(),
+        forecast: output.forecast,
+        lower: output.lower,
+        upper: output.upper,
+        annual,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::tests::baseline_rows;
+
+    fn input(scenarios: serde_json::Value, months: usize) -> String {
+        serde_json::json!({
+            "baseline": { "overall": { "historical": { "rows": baseline_rows() } } },
+            "scenarios": scenarios,
+            "forecast_options": { "forecast_months": months },
+        }).to_string()
+    }
+
+    #[test]
+    fn test_scenarios_are_aligned_and_ranked() {
+        let comparison = compare_scenarios_json(&input(serde_json::json!([
+            { "id": "low", "name": "Lose EU", "adjustments": [
+                { "type": "remove", "target_type": "geography", "target_key": "EU" },
+            ] },
+            { "id": "high", "name": "Grow ACME", "adjustments": [
+                { "type": "scale", "target_type": "customer", "target_key": "ACME", "factor": 1.5 },
+            ] },
+            { "id": "flat", "name": "Unchanged" },
+        ]), 18)).unwrap();
+
+        assert_eq!(comparison.months.len(), 18);
+        assert_eq!((comparison.months[0].as_str(), comparison.months[17].as_str()), ("2025-01", "2026-06"));
+        let ids: Vec<&str> = comparison.ranking.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["high", "flat", "low"]);
+        assert_eq!(comparison.ranking[0].index, 1);
+
+        let unchanged = &comparison.scenarios[2];
+        assert_eq!(unchanged.series.forecast, comparison.baseline.forecast);
+        assert!(unchanged.deltas.iter().all(|d| *d == 0.0));
+
+        // Annual totals split the window by calendar year and add up
+        let high = &comparison.scenarios[1];
+        let years: Vec<(i32, usize)> = high.series.annual.iter().map(|y| (y.year, y.months)).collect();
+        assert_eq!(years, vec![(2025, 12), (2026, 6)]);
+        let annual_delta: f64 = high.series.annual.iter().filter_map(|y| y.delta).sum();
+        assert!((annual_delta - high.delta).abs() < 1e-6);
+        assert!((high.deltas.iter().sum::<f64>() - high.delta).abs() < 1e-6);
+        assert!(comparison.baseline.annual.iter().all(|y| y.delta.is_none()));
+    }
+
+    #[test]
+    fn test_new_business_moves_the_shared_window() {
+        let comparison = compare_scenarios_json(&input(serde_json::json!([
+            { "name": "Launch", "adjustments": [{
+                "type": "new_business", "product_group": "S5", "geography": "MEAE",
+                "start_month": "2024-07", "year1_value": 600.0, "year2_value": 1200.0, "year3_value": 1800.0,
+            }] },
+            { "name": "Base", "forecast_options": { "forecast_months": 2 } },
+        ]), 6)).unwrap();
+
+        // Every run covers the six months after the launch's appended year
+        assert_eq!(comparison.months[0], "2026-01");
+        assert!(comparison.scenarios.iter().all(|s| s.series.forecast.len() == 6));
+        assert_eq!(comparison.scenarios[1].series.forecast, comparison.baseline.forecast);
+
+        let err = compare_scenarios_json(&input(serde_json::json!([]), 6)).unwrap_err();
+        assert_eq!(err.field(), Some("scenarios"));
+    }
+}
@@ -0,0 +1,405 @@
+//! Overlapping scenario adjustments
+//!
+//! Adjustments are applied in list order, so two that touch the same data
+//! compound. Every pair acting on the same stage (history or forecast) whose
+//! targets and months overlap is reported as a conflict. With a cube the
+//! overlap is exact (the cells both cover); without one it is inferred from
+//! the target codes: nested keys in the same dimension, or a target on the
+//! whole total, certainly overlap; keys in different dimensions possibly do.
+//!
+//! Scale and remove factors on the same data combine by the scenario's
+//! composition rule:
+//! - multiplicative (default): 0.85 × 1.20 = 1.02, as applied in sequence
+//! - override: the later adjustment in the list wins, 1.20
+//! - additive: the changes add, 1 + (0.85 − 1) + (1.20 − 1) = 1.05
+//!
+//! With a cube the rule applies cell by cell; without one it applies to
+//! scales of exactly the same target, and nested or cross-dimension targets
+//! multiply (and are reported as multiplicative whatever the rule). Other
+//! overlapping adjustments are applied in sequence and reported as such.
+
+use serde::{Deserialize, Serialize};
+
+use crate::cube::{SalesCube, Slice};
+use crate::easter::month_offset;
+use crate::scenario::{parse_year_month, Adjustment, AdjustmentKind, ApplyTo, TargetType};
+
+/// Months each new business adjustment appends to the history
+const APPENDED_MONTHS: i64 = 12;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// How scale and remove factors on the same data combine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Composition {
+    #[default]
+    Multiplicative,
+    Override,
+    Additive,
+}
+
+/// How sure a conflict is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overlap {
+    Certain,
+    /// Targets in different dimensions and no cube to check them against
+    Possible,
+}
+
+/// How a conflict was resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    Multiplicative,
+    Override,
+    Additive,
+    /// Applied one after the other in list order
+    Sequential,
+}
+
+/// Two adjustments that affect the same data
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    /// Positions of the adjustments in the list
+    pub adjustments: [usize; 2],
+    pub ids: [String; 2],
+    pub stage: ApplyTo,
+    pub overlap: Overlap,
+    /// Cube cells both cover
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cells: Option<usize>,
+    /// First shared month, "YYYY-MM"
+    pub from_month: String,
+    /// Last shared month (absent when both run to the end)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_month: Option<String>,
+    pub resolution: Resolution,
+    pub message: String,
+}
+
+/// The data an adjustment touches
+struct Footprint {
+    stage: ApplyTo,
+    slice: Slice,
+    /// Months as indices from year 0; `end` is exclusive, None is open
+    start: i64,
+    end: Option<i64>,
+    /// Scale or remove
+    scale: bool,
+    /// New business, which lies past the cube
+    appended: bool,
+}
+
+impl From<Composition> for Resolution {
+    fn from(composition: Composition) -> Self {
+        match composition {
+            Composition::Multiplicative => Resolution::Multiplicative,
+            Composition::Override => Resolution::Override,
+            Composition::Additive => Resolution::Additive,
+        }
+    }
+}
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+/// Report every pair of (validated) adjustments that overlap
+///
+/// `first` is the first month of the history and `history_months` its
+/// length before any new business is appended.
+pub fn detect_conflicts(
+    adjustments: &[Adjustment],
+    cube: Option<&SalesCube>,
+    first: (i32, u32),
+    history_months: usize,
+    composition: Composition,
+) -> Vec<Conflict> {
+    let footprints = footprints(adjustments, first, history_months);
+    let label = |i: usize| adjustments[i].label(i);
+
+    let mut conflicts = vec![];
+    for (i, a) in footprints.iter().enumerate() {
+        for (j, b) in footprints.iter().enumerate().skip(i + 1) {
+            if a.stage != b.stage {
+                continue;
+            }
+            let start = a.start.max(b.start);
+            let end = match (a.end, b.end) {
+                (Some(x), Some(y)) => Some(x.min(y)),
+                (x, y) => x.or(y),
+            };
+            if end.is_some_and(|end| end <= start) {
+                continue;
+            }
+
+            let (overlap, cells) = match cube {
+                Some(cube) if !(a.appended || b.appended) => {
+                    let shared = cube.cells.iter().filter(|c| a.slice.matches(c) && b.slice.matches(c)).count();
+                    if shared == 0 {
+                        continue;
+                    }
+                    (Overlap::Certain, Some(shared))
+                }
+                _ => match relation(&a.slice, &b.slice) {
+                    Some(overlap) => (overlap, None),
+                    None => continue,
+                },
+            };
+
+            // Without a cube only scales of the very same target compose
+            let resolution = match (a.scale && b.scale, cube.is_some() || a.slice == b.slice) {
+                (true, true) => composition.into(),
+                (true, false) => Resolution::Multiplicative,
+                (false, _) => Resolution::Sequential,
+            };
+            let (first_label, second_label) = (label(i), label(j));
+            let verb = if overlap == Overlap::Certain { "affect" } else { "may affect" };
+            let outcome = match resolution {
+                Resolution::Multiplicative => "their factors multiply".to_string(),
+                Resolution::Override => format!("{} overrides {}", second_label, first_label),
+                Resolution::Additive => "their changes add".to_string(),
+                Resolution::Sequential => "they are applied in list order".to_string(),
+            };
+            conflicts.push(Conflict {
+                adjustments: [i, j],
+                ids: [adjustments[i].id.clone(), adjustments[j].id.clone()],
+                stage: a.stage,
+                overlap,
+                cells,
+                from_month: month_label(start),
+                to_month: end.map(|end| month_label(end - 1)),
+                resolution,
+                message: format!("{} and {} {} the same data; {}", first_label, second_label, verb, outcome),
+            });
+        }
+    }
+    conflicts
+}
+
+/// Footprint of each adjustment, in order
+fn footprints(adjustments: &[Adjustment], first: (i32, u32), history_months: usize) -> Vec<Footprint> {
+    let history_start = month_index(first);
+    let mut history_end = history_start + history_months as i64;
+    let target_slice = |target_type: Option<TargetType>, key: Option<&str>| {
+        target_type.zip(key).map(|(target_type, key)| target_type.slice(key)).unwrap_or_default()
+    };
+    let parsed = |month: &str| month_index(parse_year_month(month).unwrap_or(first));
+
+    adjustments.iter()
+        .map(|adjustment| {
+            let (stage, slice, start, end) = match &adjustment.kind {
+                AdjustmentKind::Scale { target_type, target_key, .. } | AdjustmentKind::Remove { target_type, target_key } => {
+                    (ApplyTo::History, target_type.slice(target_key), history_start, Some(history_end))
+                }
+                AdjustmentKind::NewBusiness { product_group, geography, .. } => {
+                    let slice = Slice {
+                        product_group: Some(product_group.clone()),
+                        geography: Some(geography.clone()),
+                        ..Slice::default()
+                    };
+                    history_end += APPENDED_MONTHS;
+                    (ApplyTo::History, slice, history_end - APPENDED_MONTHS, Some(history_end))
+                }
+                AdjustmentKind::TimingShift { target_type, target_key, from_month, to_month, months, payback_months, .. } => {
+                    let (from, to) = (parsed(from_month), parsed(to_month));
+                    let taken = payback_months.unwrap_or(*months).max(*months) as i64;
+                    let end = (from + taken).max(to + *months as i64);
+                    (ApplyTo::Forecast, target_slice(*target_type, target_key.as_deref()), from.min(to), Some(end))
+                }
+                AdjustmentKind::Growth { window, .. }
+                | AdjustmentKind::Trend { window, .. }
+                | AdjustmentKind::Cap { window, .. }
+                | AdjustmentKind::Floor { window, .. } => {
+                    let start = parsed(&window.start_month);
+                    let slice = target_slice(window.target_type, window.target_key.as_deref());
+                    (window.apply_to, slice, start, window.months.map(|m| start + m as i64))
+                }
+            };
+            Footprint {
+                stage,
+                slice,
+                start,
+                end,
+                scale: matches!(adjustment.kind, AdjustmentKind::Scale { .. } | AdjustmentKind::Remove { .. }),
+                appended: matches!(adjustment.kind, AdjustmentKind::NewBusiness { .. }),
+            }
+        })
+        .collect()
+}
+
+/// Whether two slices overlap, judged from their codes alone
+fn relation(a: &Slice, b: &Slice) -> Option<Overlap> {
+    let nested = |x: &Option<String>, y: &Option<String>, exact: bool| match (x, y) {
+        (Some(x), Some(y)) if exact => Some(x == y),
+        (Some(x), Some(y)) => Some(x.starts_with(y.as_str()) || y.starts_with(x.as_str())),
+        _ => None,
+    };
+    let shared = [
+        nested(&a.customer, &b.customer, true),
+        nested(&a.product_group, &b.product_group, false),
+        nested(&a.geography, &b.geography, false),
+    ];
+    if shared.contains(&Some(false)) {
+        return None;
+    }
+    let whole = *a == Slice::default() || *b == Slice::default();
+    Some(if whole || shared.contains(&Some(true)) { Overlap::Certain } else { Overlap::Possible })
+}
+
+fn month_index((year, month): (i32, u32)) -> i64 {
+    year as i64 * 12 + month as i64 - 1
+}
+
+fn month_label(index: i64) -> String {
+    let (year, month) = month_offset(0, 1, index as usize);
+    format!("{}-{:02}", year, month)
+}
+
+// ============================================================================
+// Composition
+// ============================================================================
+
+/// Factors to apply in turn so that a sequence of factors on the same data
+/// combines by `composition`
+pub fn compose(factors: &[f64], composition: Composition) -> Vec<f64> {
+    let mut effective = vec![1.0; factors.len()];
+    match composition {
+        Composition::Multiplicative => effective.copy_from_slice(factors),
+        Composition::Override => {
+            if let (Some(last), Some(factor)) = (effective.last_mut(), factors.last()) {
+                *last = *factor;
+            }
+        }
+        Composition::Additive => {
+            if let Some(first) = effective.first_mut() {
+                *first = (1.0 + factors.iter().map(|f| f - 1.0).sum::<f64>()).max(0.0);
+            }
+        }
+    }
+    effective
+}
+
+/// Factor each scale or remove adjustment applies, composed with the others
+///
+/// With a cube there is one factor per cell (1 outside the target); without
+/// one a single factor for the target.
+pub fn composed_factors(adjustments: &[Adjustment], cube: Option<&SalesCube>, composition: Composition) -> Vec<Option<Vec<f64>>> {
+    let scales: Vec<(usize, TargetType, &str, f64)> = adjustments.iter().enumerate()
+        .filter_map(|(i, adjustment)| match &adjustment.kind {
+            AdjustmentKind::Scale { target_type, target_key, factor } => Some((i, *target_type, target_key.as_str(), *factor)),
+            AdjustmentKind::Remove { target_type, target_key } => Some((i, *target_type, target_key.as_str(), 0.0)),
+            _ => None,
+        })
+        .collect();
+
+    let units = cube.map_or(1, |cube| cube.cells.len());
+    let mut factors = vec![None; adjustments.len()];
+    for &(i, ..) in &scales {
+        factors[i] = Some(vec![1.0; units]);
+    }
+    let mut assign = |unit: usize, hits: Vec<&(usize, TargetType, &str, f64)>| {
+        let sequence: Vec<f64> = hits.iter().map(|hit| hit.3).collect();
+        for (hit, factor) in hits.iter().zip(compose(&sequence, composition)) {
+            if let Some(cells) = &mut factors[hit.0] {
+                cells[unit] = factor;
+            }
+        }
+    };
+
+    match cube {
+        Some(cube) => {
+            for (unit, cell) in cube.cells.iter().enumerate() {
+                assign(unit, scales.iter().filter(|s| s.1.slice(s.2).matches(cell)).collect());
+            }
+        }
+        None => {
+            // Each target is its own unit; only scales of that same target compose
+            for scale in &scales {
+                let hits: Vec<_> = scales.iter().filter(|s| (s.1, s.2) == (scale.1, scale.2)).collect();
+                let position = hits.iter().position(|s| s.0 == scale.0).unwrap_or(0);
+                let sequence: Vec<f64> = hits.iter().map(|hit| hit.3).collect();
+                if let Some(own) = &mut factors[scale.0] {
+                    own[0] = compose(&sequence, composition)[position];
+                }
+            }
+        }
+    }
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adjustments(json: serde_json::Value) -> Vec<Adjustment> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_compose() {
+        let product = |f: Vec<f64>| f.iter().product::<f64>();
+        assert!((product(compose(&[0.85, 1.2], Composition::Multiplicative)) - 1.02).abs() < 1e-12);
+        assert_eq!(compose(&[0.0, 1.2], Composition::Override), vec![1.0, 1.2]);
+        assert!((product(compose(&[0.85, 1.2], Composition::Additive)) - 1.05).abs() < 1e-12);
+        assert_eq!(compose(&[0.0, 0.5], Composition::Additive), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_detects_overlapping_targets_and_periods() {
+        let list = adjustments(serde_json::json!([
+            { "id": "lost", "type": "remove", "target_type": "customer", "target_key": "ACME" },
+            { "id": "uplift", "type": "scale", "target_type": "customer", "target_key": "ACME", "factor": 1.2 },
+            { "type": "scale", "target_type": "geography", "target_key": "EUDE", "factor": 0.9 },
+            { "type": "scale", "target_type": "geography", "target_key": "ME", "factor": 1.1 },
+            { "type": "cap", "target_type": "geography", "target_key": "EU", "start_month": "2023-01",
+              "months": 6, "limit": 100.0, "apply_to": "forecast" },
+            { "type": "timing_shift", "from_month": "2023-09", "to_month": "2023-11", "share": 0.5 },
+        ]));
+        let conflicts = detect_conflicts(&list, None, (2020, 1), 36, Composition::Override);
+        let pairs: Vec<[usize; 2]> = conflicts.iter().map(|c| c.adjustments).collect();
+
+        // Customer against geography is only possible without a cube; ME and
+        // EUDE are disjoint, as are the cap and shift windows
+        assert_eq!(pairs, vec![[0, 1], [0, 2], [0, 3], [1, 2], [1, 3]]);
+        assert_eq!(conflicts[0].overlap, Overlap::Certain);
+        assert_eq!(conflicts[0].resolution, Resolution::Override);
+        assert_eq!(conflicts[0].message, "lost and uplift affect the same data; uplift overrides lost");
+        assert_eq!(conflicts[1].overlap, Overlap::Possible);
+        assert_eq!((conflicts[0].from_month.as_str(), conflicts[0].to_month.as_deref()), ("2020-01", Some("2022-12")));
+    }
+
+    #[test]
+    fn test_cube_overlap_is_exact() {
+        let cube: SalesCube = serde_json::from_value(serde_json::json!({
+            "start_year": 2024, "start_month": 1,
+            "cells": [
+                { "customer": "ACME", "product_group": "S5", "geography": "EUDE", "values": [1.0] },
+                { "customer": "ACME", "product_group": "S6", "geography": "MEAE", "values": [1.0] },
+                { "customer": "Initech", "product_group": "S5", "geography": "EUFR", "values": [1.0] },
+            ]
+        })).unwrap();
+        let list = adjustments(serde_json::json!([
+            { "type": "scale", "target_type": "geography", "target_key": "EU", "factor": 0.5 },
+            { "type": "scale", "target_type": "customer", "target_key": "ACME", "factor": 2.0 },
+            { "type": "remove", "target_type": "geography", "target_key": "ME" },
+            { "type": "trend", "start_month": "2024-01", "monthly_change": 1.0, "apply_to": "history" },
+        ]));
+        let conflicts = detect_conflicts(&list, Some(&cube), (2024, 1), 1, Composition::Additive);
+        let pairs: Vec<([usize; 2], Option<usize>)> = conflicts.iter().map(|c| (c.adjustments, c.cells)).collect();
+        assert_eq!(pairs, vec![([0, 1], Some(1)), ([0, 3], Some(2)), ([1, 2], Some(1)), ([1, 3], Some(2)), ([2, 3], Some(1))]);
+        assert_eq!(conflicts[1].resolution, Resolution::Sequential);
+
+        // ACME in EUDE gets 1 + (0.5 - 1) + (2 - 1) = 1.5 from the first
+        // scale; in MEAE doubling and removal cancel out
+        let factors = composed_factors(&list, Some(&cube), Composition::Additive);
+        assert_eq!(factors[0], Some(vec![1.5, 1.0, 0.5]));
+        assert_eq!(factors[1], Some(vec![1.0, 1.0, 1.0]));
+        assert_eq!(factors[2], Some(vec![1.0, 1.0, 1.0]));
+        assert_eq!(factors[3], None);
+    }
+}
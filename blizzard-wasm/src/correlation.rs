@@ -0,0 +1,203 @@
+//! Auto-, partial and cross-correlation for interactive exploration
+//!
+//! Missing values (NaN) are handled pairwise: a lag product is skipped when
+//! either side is missing, while the mean and variance use every observed
+//! value (R's `na.action = na.pass`).
+
+use serde::Serialize;
+
+use crate::arima::solve_yule_walker;
+use crate::intervals::z_score;
+
+/// Correlations at a run of lags with white-noise significance bounds
+#[derive(Debug, Clone, Serialize)]
+pub struct Correlogram {
+    /// Lags (0.. for ACF, 1.. for PACF, -max..=max for CCF)
+    pub lags: Vec<i32>,
+    /// Correlation at each lag
+    pub values: Vec<f64>,
+    /// ±limit under the white-noise null: z / √n
+    pub limit: f64,
+    /// Bartlett limits for MA(k-1) at each lag (ACF only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bartlett_limits: Option<Vec<f64>>,
+}
+
+/// Sample autocorrelations at lags 0..=max_lag
+pub fn acf(series: &[f64], max_lag: usize) -> Vec<f64> {
+    let gamma0 = cross_covariance(series, series, 0);
+    if gamma0.is_nan() || gamma0 <= 1e-10 {
+        let mut result = vec![0.0; max_lag + 1];
+        result[0] = 1.0;
+        return result;
+    }
+    (0..=max_lag as i32).map(|k| cross_covariance(series, series, k) / gamma0).collect()
+}
+
+/// Sample partial autocorrelations at lags 1..=max_lag
+pub fn pacf(series: &[f64], max_lag: usize) -> Vec<f64> {
+    pacf_from_acf(&acf(series, max_lag))
+}
+
+/// Partial autocorrelations from autocorrelations at lags 0..: the last
+/// Yule-Walker (Levinson-Durbin) coefficient of each order
+pub fn pacf_from_acf(acf: &[f64]) -> Vec<f64> {
+    (1..acf.len())
+        .map(|k| solve_yule_walker(&acf[..=k]).last().copied().unwrap_or(0.0))
+        .collect()
+}
+
+/// Sample cross-correlations cor(x_{t+k}, y_t) for k in -max_lag..=max_lag
+///
+/// A peak at positive k means `y` leads `x` by k periods (R's `ccf(x, y)`).
+pub fn ccf(x: &[f64], y: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = x.len().min(y.len());
+    let (x, y) = (&x[..n], &y[..n]);
+    let scale = (cross_covariance(x, x, 0) * cross_covariance(y, y, 0)).sqrt();
+    let max_lag = max_lag as i32;
+    (-max_lag..=max_lag)
+        .map(|k| if scale > 1e-10 { cross_covariance(x, y, k) / scale } else { 0.0 })
+        .collect()
+}
+
+/// Two-sided white-noise limit z / √n for a series of `n` observations
+pub fn significance_limit(n: usize, confidence: f64) -> f64 {
+    z_score(confidence) / (n.max(1) as f64).sqrt()
+}
+
+/// Bartlett limits for the ACF at lags 1..: z √((1 + 2 Σ_{j<k} r_j²) / n)
+pub fn bartlett_limits(acf: &[f64], n: usize, confidence: f64) -> Vec<f64> {
+    let z = z_score(confidence);
+    let mut cumulative = 0.0;
+    acf.iter().skip(1)
+        .map(|r| {
+            let limit = z * ((1.0 + 2.0 * cumulative) / n.max(1) as f64).sqrt();
+            cumulative += r * r;
+            limit
+        })
+        .collect()
+}
+
+/// ACF correlogram at lags 0..=max_lag
+pub fn acf_correlogram(series: &[f64], max_lag: usize, confidence: f64) -> Correlogram {
+    let n = observed(series);
+    let values = acf(series, max_lag);
+    let mut bartlett = vec![0.0];
+    bartlett.extend(bartlett_limits(&values, n, confidence));
+    Correlogram {
+        lags: (0..=max_lag as i32).collect(),
+        limit: significance_limit(n, confidence),
+        bartlett_limits: Some(bartlett),
+        values,
+    }
+}
+
+/// PACF correlogram at lags 1..=max_lag
+pub fn pacf_correlogram(series: &[f64], max_lag: usize, confidence: f64) -> Correlogram {
+    Correlogram {
+        lags: (1..=max_lag as i32).collect(),
+        values: pacf(series, max_lag),
+        limit: significance_limit(observed(series), confidence),
+        bartlett_limits: None,
+    }
+}
+
+/// CCF correlogram at lags -max_lag..=max_lag
+pub fn ccf_correlogram(x: &[f64], y: &[f64], max_lag: usize, confidence: f64) -> Correlogram {
+    let n = x.iter().zip(y).filter(|(a, b)| a.is_finite() && b.is_finite()).count();
+    Correlogram {
+        lags: (-(max_lag as i32)..=max_lag as i32).collect(),
+        values: ccf(x, y, max_lag),
+        limit: significance_limit(n, confidence),
+        bartlett_limits: None,
+    }
+}
+
+/// Default maximum lag: 10 log10(n), capped at n - 1 (as in R's `acf`)
+pub fn default_max_lag(n: usize) -> usize {
+    ((10.0 * (n.max(1) as f64).log10()) as usize).min(n.saturating_sub(1))
+}
+
+fn observed(series: &[f64]) -> usize {
+    series.iter().filter(|x| x.is_finite()).count()
+}
+
+fn finite_mean(series: &[f64]) -> f64 {
+    let (sum, count) = series.iter()
+        .filter(|x| x.is_finite())
+        .fold((0.0, 0usize), |(s, c), x| (s + x, c + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+/// (1/n) Σ (x_{t+k} - x̄)(y_t - ȳ) over observed pairs, n = observed x values
+fn cross_covariance(x: &[f64], y: &[f64], k: i32) -> f64 {
+    let n = x.len().min(y.len());
+    let (mx, my) = (finite_mean(x), finite_mean(y));
+    let lag = k.unsigned_abs() as usize;
+    if lag >= n {
+        return 0.0;
+    }
+
+    let sum: f64 = (0..n - lag)
+        .map(|t| if k >= 0 { (x[t + lag], y[t]) } else { (x[t], y[t + lag]) })
+        .filter(|(a, b)| a.is_finite() && b.is_finite())
+        .map(|(a, b)| (a - mx) * (b - my))
+        .sum();
+    sum / observed(&x[..n]).max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acf_matches_textbook() {
+        let series = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let r = acf(&series, 2);
+        // gamma0 = 2, gamma1 = 0.8, gamma2 = -0.2
+        assert_eq!(r[0], 1.0);
+        assert!((r[1] - 0.4).abs() < 1e-12);
+        assert!((r[2] + 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pacf_of_ar1_cuts_off() {
+        let mut series = vec![0.0; 400];
+        let mut state: u64 = 99;
+        for t in 1..series.len() {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let e = (state >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+            series[t] = 0.6 * series[t - 1] + e;
+        }
+        let p = pacf(&series, 5);
+        let limit = significance_limit(series.len(), 0.95);
+
+        assert!((p[0] - acf(&series, 1)[1]).abs() < 1e-12);
+        assert!(p[0] > 0.5);
+        assert!(p[1..].iter().all(|v| v.abs() < limit));
+    }
+
+    #[test]
+    fn test_ccf_finds_lead() {
+        // y leads x by two periods
+        let y: Vec<f64> = (0..60).map(|i| ((i * i * 7) % 23) as f64).collect();
+        let x: Vec<f64> = (0..60).map(|i| if i >= 2 { y[i - 2] } else { 0.0 }).collect();
+        let values = ccf(&x, &y, 4);
+
+        let peak = (0..values.len()).max_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap();
+        assert_eq!(peak as i32 - 4, 2);
+    }
+
+    #[test]
+    fn test_missing_values_are_skipped() {
+        let mut series: Vec<f64> = (0..50).map(|i| (i as f64 * 0.7).sin()).collect();
+        series[10] = f64::NAN;
+        let correlogram = acf_correlogram(&series, 5, 0.95);
+
+        assert!(correlogram.values.iter().all(|v| v.is_finite()));
+        assert!((correlogram.limit - 1.96 / 7.0).abs() < 1e-12);
+        // Bartlett limits widen with lag
+        let bartlett = correlogram.bartlett_limits.unwrap();
+        assert!(bartlett[5] > bartlett[1]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Sales cube: monthly history by customer × product group × geography
+//!
+//! The server ships the leaf cells; any slice of the cube aggregates to a
+//! single series that can be forecast or adjusted on its own:
+//!
+//! ```json
+//! {
+//!   "start_year": 2019, "start_month": 1,
+//!   "cells": [
+//!     { "customer": "ACME", "product_group": "S52020", "geography": "MEAEDU", "values": [120.5, null, ...] }
+//!   ]
+//! }
+//! ```
+//!
+//! Product group and geography codes are hierarchical (two characters per
+//! level: `S5` → `S520` → `S52020`, territory → country → region), so a
+//! slice on either matches every cell whose code starts with the key.
+//! Customers match exactly.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{from_json, from_value, Error, Result};
+use crate::{deserialize_series, forecast_input, now_ms, serialize_series, ForecastInput, ForecastOutput};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Leaf cells sharing one monthly calendar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SalesCube {
+    pub start_year: i32,
+    pub start_month: u32,
+    pub cells: Vec<CubeCell>,
+}
+
+/// Monthly values for one customer, product group and geography
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CubeCell {
+    pub customer: String,
+    pub product_group: String,
+    pub geography: String,
+    /// One value per month from the cube start (null marks a missing month)
+    #[serde(serialize_with = "serialize_series", deserialize_with = "deserialize_series")]
+    pub values: Vec<f64>,
+}
+
+/// Part of the cube; an unset dimension matches everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Slice {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub customer: Option<String>,
+    /// Product group code or code prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product_group: Option<String>,
+    /// Geography code or code prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geography: Option<String>,
+}
+
+/// A slice aggregated to one series
+#[derive(Debug, Clone, Serialize)]
+pub struct SliceSeries {
+    pub start_year: i32,
+    pub start_month: u32,
+    #[serde(serialize_with = "serialize_series")]
+    pub series: Vec<f64>,
+    /// Number of cells in the slice
+    pub cells: usize,
+}
+
+/// `forecast` fields plus the cube and the slice to forecast
+#[derive(Deserialize)]
+struct SliceForecastInput {
+    cube: SalesCube,
+    #[serde(default)]
+    slice: Slice,
+    #[serde(flatten)]
+    fields: Map<String, Value>,
+}
+
+// ============================================================================
+// Slicing and aggregation
+// ============================================================================
+
+impl Slice {
+    /// Whether a cell falls in the slice
+    pub fn matches(&self, cell: &CubeCell) -> bool {
+        self.customer.as_ref().is_none_or(|c| *c == cell.customer)
+            && self.product_group.as_ref().is_none_or(|p| cell.product_group.starts_with(p.as_str()))
+            && self.geography.as_ref().is_none_or(|g| cell.geography.starts_with(g.as_str()))
+    }
+}
+
+impl SalesCube {
+    /// Check the calendar and that every cell covers the same months
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=12).contains(&self.start_month) {
+            return Err(Error::validation("start_month", "start_month must be between 1 and 12"));
+        }
+        let months = self.months();
+        if months == 0 || self.cells.iter().any(|c| c.values.len() != months) {
+            return Err(Error::validation("cells", "Every cube cell must have the same, non-zero number of months"));
+        }
+        Ok(())
+    }
+
+    /// Number of months covered
+    pub fn months(&self) -> usize {
+        self.cells.first().map_or(0, |c| c.values.len())
+    }
+
+    /// Sum the cells in a slice month by month
+    ///
+    /// Missing values count as zero unless every cell in the slice is
+    /// missing that month. A slice with no cells is all zeros.
+    pub fn aggregate(&self, slice: &Slice) -> Vec<f64> {
+        let cells: Vec<&CubeCell> = self.cells.iter().filter(|c| slice.matches(c)).collect();
+        let mut totals = vec![0.0; self.months()];
+        let mut observed = vec![cells.is_empty(); self.months()];
+        for cell in cells {
+            for (i, v) in cell.values.iter().enumerate().filter(|(_, v)| v.is_finite()) {
+                totals[i] += v;
+                observed[i] = true;
+            }
+        }
+        totals.iter().zip(&observed)
+            .map(|(&t, &seen)| if seen { t } else { f64::NAN })
+            .collect()
+    }
+
+    /// Aggregate a slice along with the cube calendar
+    pub fn slice_series(&self, slice: &Slice) -> SliceSeries {
+        SliceSeries {
+            start_year: self.start_year,
+            start_month: self.start_month,
+            series: self.aggregate(slice),
+            cells: self.cells.iter().filter(|c| slice.matches(c)).count(),
+        }
+    }
+
+    /// Aggregate the cells in a slice separately for each value of `key`
+    pub fn group_by<'a>(&'a self, slice: &Slice, key: impl Fn(&'a CubeCell) -> &'a str) -> BTreeMap<&'a str, Vec<f64>> {
+        let mut groups: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        for cell in self.cells.iter().filter(|c| slice.matches(c)) {
+            let totals = groups.entry(key(cell)).or_insert_with(|| vec![f64::NAN; self.months()]);
+            for (total, v) in totals.iter_mut().zip(&cell.values).filter(|(_, v)| v.is_finite()) {
+                *total = if total.is_finite() { *total + v } else { *v };
+            }
+        }
+        groups
+    }
+
+    /// Multiply every cell in a slice month by month by `ratios`
+    pub fn rescale(&mut self, slice: &Slice, ratios: &[f64]) {
+        for cell in self.cells.iter_mut().filter(|c| slice.matches(c)) {
+            cell.values.iter_mut().zip(ratios).for_each(|(v, r)| *v *= r);
+        }
+    }
+
+    /// Multiply each cell by its own factor, returning the number of cells changed
+    pub fn scale_cells(&mut self, factors: &[f64]) -> usize {
+        let mut changed = 0;
+        for (cell, &factor) in self.cells.iter_mut().zip(factors).filter(|(_, f)| **f != 1.0) {
+            cell.values.iter_mut().for_each(|v| *v *= factor);
+            changed += 1;
+        }
+        changed
+    }
+}
+
+// ============================================================================
+// JSON entry points
+// ============================================================================
+
+/// Aggregate a slice of a JSON cube
+pub fn aggregate_slice_json(cube_json: &str, slice_json: &str) -> Result<SliceSeries> {
+    let cube: SalesCube = from_json(cube_json)?;
+    let slice: Slice = from_json(slice_json)?;
+    cube.validate()?;
+    Ok(cube.slice_series(&slice))
+}
+
+/// Forecast a slice: `{cube, slice, ...forecast fields}`
+pub fn forecast_slice(input_json: &str) -> Result<ForecastOutput> {
+    let started = now_ms();
+    let SliceForecastInput { cube, slice, mut fields } = from_json(input_json)?;
+    cube.validate()?;
+    let sliced = cube.slice_series(&slice);
+    if sliced.cells == 0 {
+        return Err(Error::validation("slice", "No cube cells match the slice"));
+    }
+
+    fields.insert("series".into(), Value::Array(vec![]));
+    fields.insert("start_year".into(), sliced.start_year.into());
+    fields.insert("start_month".into(), sliced.start_month.into());
+
+    let mut input: ForecastInput = from_value(Value::Object(fields))?;
+    input.series = sliced.series;
+    forecast_input(input, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> SalesCube {
+        serde_json::from_value(serde_json::json!({
+            "start_year": 2023, "start_month": 1,
+            "cells": [
+                { "customer": "ACME", "product_group": "S52020", "geography": "MEAEDU", "values": [10.0, 20.0, null] },
+                { "customer": "ACME", "product_group": "S61010", "geography": "EUDEBE", "values": [1.0, 2.0, null] },
+                { "customer": "Initech", "product_group": "S52030", "geography": "MEAEAU", "values": [100.0, null, 300.0] },
+            ]
+        })).unwrap()
+    }
+
+    fn slice(json: serde_json::Value) -> Slice {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_slices() {
+        let cube = cube();
+        cube.validate().unwrap();
+
+        assert_eq!(cube.aggregate(&Slice::default()), vec![111.0, 22.0, 300.0]);
+        assert_eq!(cube.aggregate(&slice(serde_json::json!({"customer": "ACME"})))[..2], [11.0, 22.0]);
+        assert!(cube.aggregate(&slice(serde_json::json!({"customer": "ACME"})))[2].is_nan());
+        // Prefixes select whole branches of the hierarchies
+        assert_eq!(cube.aggregate(&slice(serde_json::json!({"product_group": "S520"}))), vec![110.0, 20.0, 300.0]);
+        assert_eq!(cube.aggregate(&slice(serde_json::json!({"geography": "MEAE", "customer": "Initech"})))[0], 100.0);
+        assert_eq!(cube.slice_series(&slice(serde_json::json!({"customer": "Nobody"}))).cells, 0);
+    }
+
+    #[test]
+    fn test_scale_changes_only_the_given_cells() {
+        let mut cube = cube();
+        assert_eq!(cube.scale_cells(&[2.0, 2.0, 1.0]), 2);
+        assert_eq!(cube.aggregate(&Slice::default())[..2], [122.0, 44.0]);
+        assert_eq!(cube.aggregate(&slice(serde_json::json!({"customer": "Initech"})))[0], 100.0);
+
+        cube.cells[1].values.pop();
+        assert_eq!(cube.validate().unwrap_err().field(), Some("cells"));
+    }
+
+    #[test]
+    fn test_forecast_slice_matches_forecast() {
+        let values = |level: f64| -> Vec<f64> {
+            (0..36).map(|i| level + 3.0 * i as f64 + 0.2 * level * ((i % 12) as f64 * 0.5).sin()).collect()
+        };
+        let input = serde_json::json!({
+            "cube": {
+                "start_year": 2021, "start_month": 4,
+                "cells": [
+                    { "customer": "ACME", "product_group": "S5", "geography": "ME", "values": values(500.0) },
+                    { "customer": "Initech", "product_group": "S5", "geography": "EU", "values": values(900.0) },
+                ]
+            },
+            "slice": { "customer": "ACME" },
+            "forecast_months": 6,
+        });
+        let output = forecast_slice(&input.to_string()).unwrap();
+
+        let direct = serde_json::json!({ "series": values(500.0), "start_year": 2021, "start_month": 4, "forecast_months": 6 });
+        let direct: serde_json::Value = serde_json::from_str(&crate::forecast(&direct.to_string())).unwrap();
+        assert_eq!(serde_json::to_value(&output.forecast).unwrap(), direct["forecast"]);
+
+        let mut empty = input.clone();
+        empty["slice"] = serde_json::json!({ "customer": "Nobody" });
+        assert_eq!(forecast_slice(&empty.to_string()).err().unwrap().field(), Some("slice"));
+    }
+}
@@ -0,0 +1,226 @@
+//! Residual diagnostics for a fitted model
+//!
+//! Answers "is the model adequate for this series?" from the one-step
+//! residuals:
+//! - ACF/PACF with approximate 95% limits (±1.96/√n)
+//! - Ljung-Box portmanteau test for leftover autocorrelation
+//! - Jarque-Bera normality test
+//! - Heteroscedasticity test comparing the first and last thirds
+
+use serde::Serialize;
+
+use crate::arima::mean;
+use crate::correlation::{acf, pacf_from_acf};
+use crate::distributions::{chi_square_sf, f_cdf};
+
+/// Significance level used for the pass/fail flags
+const ALPHA: f64 = 0.05;
+
+/// Full residual diagnostics report
+#[derive(Debug, Clone, Serialize)]
+pub struct ResidualDiagnostics {
+    /// Residuals the report was computed from (NaN/null where missing)
+    pub residuals: Vec<f64>,
+    /// Residual autocorrelations at lags 1..=lags
+    pub acf: Vec<f64>,
+    /// Residual partial autocorrelations at lags 1..=lags
+    pub pacf: Vec<f64>,
+    /// Approximate 95% limit for ACF/PACF under white noise
+    pub confidence_limit: f64,
+    /// Ljung-Box test for remaining autocorrelation
+    pub ljung_box: LjungBox,
+    /// Jarque-Bera normality test
+    pub jarque_bera: JarqueBera,
+    /// Variance stability test
+    pub heteroscedasticity: Heteroscedasticity,
+}
+
+/// Ljung-Box portmanteau test (null: no autocorrelation up to `lags`)
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LjungBox {
+    pub statistic: f64,
+    pub lags: usize,
+    /// Lags minus fitted ARMA parameters
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+    /// True if the null is not rejected at 5%
+    pub passed: bool,
+}
+
+/// Jarque-Bera test (null: residuals are normal)
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct JarqueBera {
+    pub statistic: f64,
+    pub skewness: f64,
+    /// Kurtosis (3 for a normal distribution)
+    pub kurtosis: f64,
+    pub p_value: f64,
+    /// True if the null is not rejected at 5%
+    pub passed: bool,
+}
+
+/// Two-sided test of equal variance in the first and last thirds
+/// (null: homoscedastic)
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Heteroscedasticity {
+    /// Ratio of the sum of squares in the last third to the first third
+    pub statistic: f64,
+    /// Observations in each third
+    pub subset_size: usize,
+    pub p_value: f64,
+    /// True if the null is not rejected at 5%
+    pub passed: bool,
+}
+
+/// Default number of lags: min(2m, n/5), at least 1 (Hyndman & Athanasopoulos)
+pub fn default_lags(n: usize, seasonal_period: usize) -> usize {
+    let m = seasonal_period.max(5);
+    (2 * m).min(n / 5).max(1)
+}
+
+/// Diagnose a residual series
+///
+/// Missing residuals (NaN) are dropped from the tests but kept in the
+/// returned `residuals` so they stay aligned with the series. `fitted_params`
+/// is the number of ARMA coefficients, used for the Ljung-Box degrees of
+/// freedom.
+pub fn diagnose_residuals(
+    residuals: &[f64],
+    seasonal_period: usize,
+    fitted_params: usize,
+    lags: Option<usize>,
+) -> ResidualDiagnostics {
+    let values: Vec<f64> = residuals.iter().copied().filter(|x| x.is_finite()).collect();
+    let n = values.len();
+    let lags = lags.unwrap_or_else(|| default_lags(n, seasonal_period)).min(n.saturating_sub(1)).max(1);
+
+    let centre = mean(&values);
+    let centered: Vec<f64> = values.iter().map(|x| x - centre).collect();
+    let acf = acf(&values, lags);
+
+    ResidualDiagnostics {
+        residuals: residuals.to_vec(),
+        acf: acf[1..].to_vec(),
+        pacf: pacf_from_acf(&acf),
+        confidence_limit: 1.96 / (n.max(1) as f64).sqrt(),
+        ljung_box: ljung_box(&acf, n, fitted_params),
+        jarque_bera: jarque_bera(&centered),
+        heteroscedasticity: heteroscedasticity(&values),
+    }
+}
+
+fn ljung_box(acf: &[f64], n: usize, fitted_params: usize) -> LjungBox {
+    let lags = acf.len() - 1;
+    let nf = n as f64;
+    let statistic = nf * (nf + 2.0) * (1..=lags)
+        .map(|k| acf[k] * acf[k] / (nf - k as f64))
+        .sum::<f64>();
+    let degrees_of_freedom = lags.saturating_sub(fitted_params).max(1);
+    let p_value = chi_square_sf(statistic, degrees_of_freedom as f64);
+
+    LjungBox { statistic, lags, degrees_of_freedom, p_value, passed: p_value >= ALPHA }
+}
+
+fn jarque_bera(centered: &[f64]) -> JarqueBera {
+    let n = centered.len().max(1) as f64;
+    let moment = |k: i32| centered.iter().map(|x| x.powi(k)).sum::<f64>() / n;
+    let m2 = moment(2);
+    let (skewness, kurtosis) = if m2 > 0.0 {
+        (moment(3) / m2.powf(1.5), moment(4) / (m2 * m2))
+    } else {
+        (0.0, 3.0)
+    };
+
+    let statistic = n / 6.0 * (skewness * skewness + (kurtosis - 3.0).powi(2) / 4.0);
+    let p_value = chi_square_sf(statistic, 2.0);
+
+    JarqueBera { statistic, skewness, kurtosis, p_value, passed: p_value >= ALPHA }
+}
+
+fn heteroscedasticity(values: &[f64]) -> Heteroscedasticity {
+    let h = (values.len() as f64 / 3.0).round() as usize;
+    let sum_squares = |slice: &[f64]| slice.iter().map(|x| x * x).sum::<f64>();
+    let first = sum_squares(&values[..h.min(values.len())]);
+    let last = sum_squares(&values[values.len().saturating_sub(h)..]);
+
+    if h == 0 || first <= 0.0 {
+        return Heteroscedasticity { statistic: f64::NAN, subset_size: h, p_value: f64::NAN, passed: true };
+    }
+
+    let statistic = last / first;
+    let cdf = f_cdf(statistic, h as f64, h as f64);
+    let p_value = (2.0 * cdf.min(1.0 - cdf)).min(1.0);
+
+    Heteroscedasticity { statistic, subset_size: h, p_value, passed: p_value >= ALPHA }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Standard normal draws via Box-Muller
+    fn gaussian(n: usize, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| {
+                let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.gen();
+                (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_white_noise_passes() {
+        let report = diagnose_residuals(&gaussian(300, 7), 12, 3, None);
+
+        assert_eq!(report.acf.len(), 24);
+        assert_eq!(report.pacf.len(), 24);
+        assert_eq!(report.ljung_box.degrees_of_freedom, 21);
+        assert!(report.ljung_box.passed);
+        assert!(report.jarque_bera.passed);
+        assert!(report.heteroscedasticity.passed);
+    }
+
+    #[test]
+    fn test_autocorrelated_residuals_fail_ljung_box() {
+        let e = gaussian(300, 11);
+        let mut ar = vec![0.0; e.len()];
+        for t in 1..e.len() {
+            ar[t] = 0.7 * ar[t - 1] + e[t];
+        }
+        let report = diagnose_residuals(&ar, 12, 0, None);
+
+        assert!(!report.ljung_box.passed);
+        // AR(1): PACF cuts off after lag 1
+        assert!((report.pacf[0] - report.acf[0]).abs() < 1e-12);
+        assert!(report.pacf[0] > 0.5);
+        assert!(report.pacf[2].abs() < report.confidence_limit);
+    }
+
+    #[test]
+    fn test_skewed_and_heteroscedastic_residuals() {
+        let skewed: Vec<f64> = gaussian(300, 3).iter().map(|x| x.exp()).collect();
+        assert!(!diagnose_residuals(&skewed, 12, 0, None).jarque_bera.passed);
+
+        let growing: Vec<f64> = gaussian(300, 5).iter().enumerate()
+            .map(|(i, x)| x * (1.0 + i as f64 / 30.0))
+            .collect();
+        let report = diagnose_residuals(&growing, 12, 0, None);
+        assert!(report.heteroscedasticity.statistic > 1.0);
+        assert!(!report.heteroscedasticity.passed);
+    }
+
+    #[test]
+    fn test_missing_residuals_are_kept_aligned() {
+        let mut residuals = gaussian(100, 1);
+        residuals[10] = f64::NAN;
+        let report = diagnose_residuals(&residuals, 12, 0, Some(5));
+
+        assert_eq!(report.residuals.len(), 100);
+        assert!(report.residuals[10].is_nan());
+        assert_eq!(report.acf.len(), 5);
+        assert!(report.ljung_box.statistic.is_finite());
+    }
+}
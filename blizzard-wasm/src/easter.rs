@@ -39,6 +39,14 @@ pub fn easter_invoice_month(easter_year: i32) -> (i32, u32) {
     }
 }
 
+/// Calendar month `offset` months after (start_year, start_month)
+///
+/// Returns (year, month) with month in 1-12.
+pub fn month_offset(start_year: i32, start_month: u32, offset: usize) -> (i32, u32) {
+    let months = start_month as usize - 1 + offset;
+    (start_year + (months / 12) as i32, (months % 12) as u32 + 1)
+}
+
 /// Create Easter regressor array for a time series
 /// 
 /// Returns a vector of 1.0 for months that are Easter invoice months, 0.0 otherwise.
@@ -62,9 +70,9 @@ pub fn create_easter_regressor(start_year: i32, start_month: u32, length: usize)
     let mut current_year = start_year;
     let mut current_month = start_month;
 
-    for i in 0..length {
+    for value in regressor.iter_mut() {
         if easter_invoice_months.contains(&(current_year, current_month)) {
-            regressor[i] = 1.0;
+            *value = 1.0;
         }
 
         current_month += 1;
@@ -107,6 +115,13 @@ mod tests {
         assert_eq!(easter_invoice_month(2026), (2026, 1));
     }
 
+    #[test]
+    fn test_month_offset() {
+        assert_eq!(month_offset(2024, 1, 0), (2024, 1));
+        assert_eq!(month_offset(2024, 11, 2), (2025, 1));
+        assert_eq!(month_offset(2019, 6, 30), (2021, 12));
+    }
+
     #[test]
     fn test_easter_regressor() {
         // Create regressor for 2024-2025 (24 months starting Jan 2024)
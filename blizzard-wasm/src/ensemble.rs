@@ -0,0 +1,347 @@
+//! Forecast combination across the methods available in the crate
+//!
+//! Each component method is fitted on the full series for the forecast and on
+//! a holdout split for weighting. The combined forecast and interval bounds
+//! are weighted averages of the components.
+
+use serde::{Deserialize, Serialize};
+
+use crate::arima::{ForecastOptions, ForecastResult};
+use crate::benchmarks::{fit_and_forecast_method, is_degenerate, Method};
+use crate::metrics::{accuracy, AccuracyMetrics};
+use crate::warnings::{Warning, WarningCode};
+
+/// How component forecasts are weighted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weighting {
+    /// Every usable component gets the same weight
+    #[default]
+    Equal,
+    /// Weights proportional to 1 / holdout MSE
+    InverseError,
+    /// Non-negative weights summing to one, fitted to the holdout actuals
+    Stacking,
+}
+
+/// Ensemble configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnsembleConfig {
+    /// Methods to combine (default: arima, seasonal_naive, drift, theta)
+    #[serde(default = "default_models")]
+    pub models: Vec<Method>,
+    /// Weighting scheme (default: equal)
+    #[serde(default)]
+    pub weighting: Weighting,
+    /// Months held back to compute weights (default: 12)
+    #[serde(default = "default_holdout_months")]
+    pub holdout_months: usize,
+}
+
+fn default_models() -> Vec<Method> {
+    vec![Method::Arima, Method::SeasonalNaive, Method::Drift, Method::Theta]
+}
+fn default_holdout_months() -> usize { 12 }
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        EnsembleConfig {
+            models: default_models(),
+            weighting: Weighting::default(),
+            holdout_months: default_holdout_months(),
+        }
+    }
+}
+
+/// One model's contribution to the ensemble
+#[derive(Debug, Clone, Serialize)]
+pub struct EnsembleComponent {
+    /// Component method
+    pub method: Method,
+    /// Point forecasts
+    pub forecast: Vec<f64>,
+    /// Lower bound of confidence interval
+    pub lower: Vec<f64>,
+    /// Upper bound of confidence interval
+    pub upper: Vec<f64>,
+    /// Weight in the combination (0 for excluded components)
+    pub weight: f64,
+    /// Holdout accuracy (None if the series is too short for a holdout)
+    pub accuracy: Option<AccuracyMetrics>,
+    /// Whether the component was excluded as degenerate
+    pub degenerate: bool,
+}
+
+/// Combined forecast plus its components
+#[derive(Debug, Clone)]
+pub struct EnsembleResult {
+    /// Combined forecast (coefficients are left empty)
+    pub combined: ForecastResult,
+    /// Weighting scheme actually applied (equal if no holdout was possible)
+    pub weighting: Weighting,
+    /// Component forecasts and weights
+    pub components: Vec<EnsembleComponent>,
+}
+
+/// Fit every configured model and combine their forecasts
+pub fn fit_ensemble(
+    config: &EnsembleConfig,
+    series: &[f64],
+    start_year: i32,
+    start_month: u32,
+    forecast_months: usize,
+    options: &ForecastOptions,
+) -> EnsembleResult {
+    let run = |method: Method, series: &[f64], months: usize| {
+        fit_and_forecast_method(method, series, start_year, start_month, months, options, config)
+    };
+
+    // Nested ensembles are not meaningful; drop them and any duplicates
+    let mut models: Vec<Method> = vec![];
+    for &m in &config.models {
+        if m != Method::Ensemble && !models.contains(&m) {
+            models.push(m);
+        }
+    }
+
+    // Holdout forecasts for weighting
+    let holdout = config.holdout_months;
+    // Same rule as `holdout_accuracy`: two seasonal cycles left for training
+    let has_holdout = holdout > 0 && series.len() >= holdout + 2 * options.seasonal_period;
+    let (training, actual) = series.split_at(if has_holdout { series.len() - holdout } else { series.len() });
+    let holdout_forecasts: Vec<Vec<f64>> = if has_holdout {
+        models.iter().map(|&m| run(m, training, holdout).forecast).collect()
+    } else {
+        vec![]
+    };
+
+    let mut warnings: Vec<Warning> = vec![];
+    let mut components: Vec<EnsembleComponent> = models.iter().enumerate()
+        .map(|(i, &method)| {
+            let result = run(method, series, forecast_months);
+            let degenerate = is_degenerate(&result, series);
+            for warning in result.warnings {
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+            EnsembleComponent {
+                method,
+                forecast: result.forecast,
+                lower: result.lower,
+                upper: result.upper,
+                weight: 0.0,
+                accuracy: holdout_forecasts.get(i).map(|f| accuracy(actual, f, training, options.seasonal_period)),
+                degenerate,
+            }
+        })
+        .collect();
+
+    let usable: Vec<usize> = (0..components.len()).filter(|&i| !components[i].degenerate).collect();
+
+    let weighting = if has_holdout { config.weighting } else { Weighting::Equal };
+    if weighting != config.weighting {
+        warnings.push(Warning::new(
+            WarningCode::EnsembleEqualWeights,
+            format!(
+                "{} months of history are too few for a {}-month holdout; equal weights used",
+                series.len(),
+                config.holdout_months,
+            ),
+        ));
+    }
+    let weights = match weighting {
+        Weighting::Equal => vec![1.0; usable.len()],
+        Weighting::InverseError => usable.iter()
+            .map(|&i| {
+                let rmse = components[i].accuracy.map(|a| a.rmse).unwrap_or(f64::INFINITY);
+                1.0 / (rmse * rmse).max(1e-10)
+            })
+            .collect(),
+        Weighting::Stacking => {
+            let forecasts: Vec<&[f64]> = usable.iter().map(|&i| holdout_forecasts[i].as_slice()).collect();
+            stacking_weights(&forecasts, actual)
+        }
+    };
+    let total: f64 = weights.iter().sum();
+    for (&i, &w) in usable.iter().zip(&weights) {
+        components[i].weight = if total > 0.0 { w / total } else { 1.0 / usable.len() as f64 };
+    }
+
+    let combine = |pick: fn(&EnsembleComponent) -> &Vec<f64>| -> Vec<f64> {
+        (0..forecast_months)
+            .map(|h| components.iter().map(|c| c.weight * pick(c)[h]).sum())
+            .collect()
+    };
+
+    let combined = ForecastResult {
+        forecast: combine(|c| &c.forecast),
+        lower: combine(|c| &c.lower),
+        upper: combine(|c| &c.upper),
+        seasonal_factors: vec![],
+        easter_coefficient: 0.0,
+        regressor_coefficient: None,
+        ar_coefficients: vec![],
+        ma_coefficients: vec![],
+        intercept: 0.0,
+        outliers: vec![],
+        differencing: None,
+        warnings,
+        fit: None,
+    };
+
+    EnsembleResult { combined, weighting, components }
+}
+
+/// Least-squares weights constrained to the probability simplex
+///
+/// Solved by projected gradient descent; the problem is small (one weight per
+/// model) so a fixed number of iterations is plenty.
+fn stacking_weights(forecasts: &[&[f64]], actual: &[f64]) -> Vec<f64> {
+    let k = forecasts.len();
+    if k == 0 {
+        return vec![];
+    }
+
+    // Missing holdout months say nothing about the weights; drop them
+    let observed: Vec<usize> = (0..actual.len()).filter(|&t| actual[t].is_finite()).collect();
+    let columns: Vec<Vec<f64>> = forecasts.iter().map(|f| observed.iter().map(|&t| f[t]).collect()).collect();
+    let actual: Vec<f64> = observed.iter().map(|&t| actual[t]).collect();
+
+    // Gram matrix F'F and F'y
+    let gram: Vec<Vec<f64>> = (0..k)
+        .map(|i| (0..k).map(|j| dot(&columns[i], &columns[j])).collect())
+        .collect();
+    let fy: Vec<f64> = (0..k).map(|i| dot(&columns[i], &actual)).collect();
+
+    // Step size from the trace, an upper bound on the largest eigenvalue
+    let trace: f64 = (0..k).map(|i| gram[i][i]).sum();
+    if trace <= 0.0 {
+        return vec![1.0 / k as f64; k];
+    }
+    let step = 1.0 / trace;
+
+    let mut w = vec![1.0 / k as f64; k];
+    for _ in 0..5000 {
+        let grad: Vec<f64> = (0..k)
+            .map(|i| dot(&gram[i], &w) - fy[i])
+            .collect();
+        let moved: Vec<f64> = w.iter().zip(&grad).map(|(wi, gi)| wi - step * gi).collect();
+        w = project_to_simplex(&moved);
+    }
+
+    w
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Euclidean projection onto {w : w >= 0, sum(w) = 1} (Duchi et al., 2008)
+fn project_to_simplex(v: &[f64]) -> Vec<f64> {
+    let mut sorted = v.to_vec();
+    sorted.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut cumsum = 0.0;
+    let mut theta = 0.0;
+    for (i, &u) in sorted.iter().enumerate() {
+        cumsum += u;
+        let t = (cumsum - 1.0) / (i + 1) as f64;
+        if u - t > 0.0 {
+            theta = t;
+        }
+    }
+
+    v.iter().map(|&x| (x - theta).max(0.0)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ForecastOptions {
+        ForecastOptions { use_easter: false, ..ForecastOptions::default() }
+    }
+
+    fn series() -> Vec<f64> {
+        (0..60)
+            .map(|i| 1000.0 + 8.0 * i as f64 + 150.0 * ((i % 12) as f64 / 12.0 * std::f64::consts::TAU).cos())
+            .collect()
+    }
+
+    #[test]
+    fn test_equal_weights() {
+        let config = EnsembleConfig {
+            models: vec![Method::Naive, Method::Mean],
+            ..EnsembleConfig::default()
+        };
+        let result = fit_ensemble(&config, &series(), 2019, 1, 6, &options());
+
+        assert_eq!(result.components.len(), 2);
+        assert!((result.components[0].weight - 0.5).abs() < 1e-10);
+        let expected = 0.5 * result.components[0].forecast[0] + 0.5 * result.components[1].forecast[0];
+        assert!((result.combined.forecast[0] - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_weights_favour_better_model() {
+        for weighting in [Weighting::InverseError, Weighting::Stacking] {
+            let config = EnsembleConfig {
+                models: vec![Method::SeasonalNaive, Method::Mean],
+                weighting,
+                holdout_months: 12,
+            };
+            let result = fit_ensemble(&config, &series(), 2019, 1, 12, &options());
+
+            let total: f64 = result.components.iter().map(|c| c.weight).sum();
+            assert!((total - 1.0).abs() < 1e-9);
+            assert!(result.components[0].weight > result.components[1].weight);
+        }
+    }
+
+    #[test]
+    fn test_stacking_skips_missing_holdout_months() {
+        let config = EnsembleConfig {
+            models: vec![Method::SeasonalNaive, Method::Mean],
+            weighting: Weighting::Stacking,
+            holdout_months: 12,
+        };
+        let mut series = series();
+        series[52] = f64::NAN;
+        let result = fit_ensemble(&config, &series, 2019, 1, 6, &options());
+
+        assert_eq!(result.weighting, Weighting::Stacking);
+        assert!(result.components.iter().all(|c| c.weight.is_finite()));
+        assert!(result.components[0].weight > 0.9);
+        assert!(result.combined.forecast.iter().all(|f| f.is_finite()));
+    }
+
+    #[test]
+    fn test_short_series_falls_back_to_equal() {
+        let config = EnsembleConfig {
+            models: vec![Method::Naive, Method::Drift],
+            weighting: Weighting::Stacking,
+            holdout_months: 12,
+        };
+        let result = fit_ensemble(&config, &series()[..30], 2019, 1, 3, &options());
+        assert_eq!(result.weighting, Weighting::Equal);
+        assert!(result.combined.warnings.iter().any(|w| w.code == WarningCode::EnsembleEqualWeights));
+
+        // The training part needs two seasonal cycles, whatever the period
+        let quarterly = ForecastOptions { seasonal_period: 4, ..options() };
+        let result = fit_ensemble(&config, &series()[..30], 2019, 1, 3, &quarterly);
+        assert_eq!(result.weighting, Weighting::Stacking);
+
+        let equal = EnsembleConfig { weighting: Weighting::Equal, ..config };
+        let result = fit_ensemble(&equal, &series()[..30], 2019, 1, 3, &options());
+        assert!(result.combined.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_project_to_simplex() {
+        let w = project_to_simplex(&[0.8, 0.6, -0.5]);
+        assert!((w[0] - 0.6).abs() < 1e-10);
+        assert!((w[1] - 0.4).abs() < 1e-10);
+        assert_eq!(w[2], 0.0);
+    }
+}
@@ -0,0 +1,232 @@
+//! Crate-wide error type and its JSON form at the WASM boundary
+//!
+//! Every exported function reports failures as
+//! `{"error": message, "code": code, "field": path?}`. `error` keeps the
+//! free-text message older callers read; `code` is one of `parse_error`,
+//! `validation_error`, `numerical_error`, `unsupported` or
+//! `version_mismatch`; `field` names the offending input for validation,
+//! unsupported-option and version errors. Throwing
+//! variants use the same JSON as the message of the thrown `Error`.
+//!
+//! Input is deserialized through `from_json` / `from_value`, which track the
+//! path into the document, so a missing field, wrong type or unknown enum
+//! value is a validation error on e.g. `ensemble.weighting` or
+//! `outliers.force[0].kind`. Malformed JSON is a parse error.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Errors raised while handling a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The input is not valid JSON or has the wrong shape
+    Parse(String),
+    /// A field has an invalid value
+    Validation { field: String, message: String },
+    /// The computation broke down (non-finite results)
+    Numerical(String),
+    /// A recognised option that this build cannot honour
+    Unsupported { field: String, message: String },
+    /// A snapshot written by an incompatible format version
+    VersionMismatch { found: u32, expected: u32 },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Stable JSON error schema
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    error: String,
+    code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+}
+
+impl Error {
+    pub fn validation(field: &str, message: impl Into<String>) -> Self {
+        Error::Validation { field: field.to_string(), message: message.into() }
+    }
+
+    pub fn unsupported(field: &str, message: impl Into<String>) -> Self {
+        Error::Unsupported { field: field.to_string(), message: message.into() }
+    }
+
+    /// Machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Parse(_) => "parse_error",
+            Error::Validation { .. } => "validation_error",
+            Error::Numerical(_) => "numerical_error",
+            Error::Unsupported { .. } => "unsupported",
+            Error::VersionMismatch { .. } => "version_mismatch",
+        }
+    }
+
+    /// Input field the error refers to, if any
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Error::Validation { field, .. } | Error::Unsupported { field, .. } => Some(field),
+            Error::VersionMismatch { .. } => Some("version"),
+            Error::Parse(_) | Error::Numerical(_) => None,
+        }
+    }
+
+    /// Serialize to the stable JSON error schema
+    pub fn to_json(&self) -> String {
+        // Plain strings only, so this cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        ErrorOutput { error: self.to_string(), code: self.code(), field: self.field() }.serialize(serializer)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(message) => write!(f, "Failed to parse input: {}", message),
+            Error::VersionMismatch { found, expected } => {
+                write!(f, "Snapshot format version {} is not supported (expected {})", found, expected)
+            }
+            Error::Validation { message, .. }
+            | Error::Unsupported { message, .. }
+            | Error::Numerical(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    /// Errors without a path are reported against the document root
+    fn from(err: serde_json::Error) -> Self {
+        deserialize_error(".", err)
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for Error {
+    fn from(err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = err.path().to_string();
+        deserialize_error(&path, err.into_inner())
+    }
+}
+
+/// Deserialize JSON input, naming the offending field by its full path
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let value = serde_path_to_error::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// `from_json` on an already-parsed value
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    Ok(serde_path_to_error::deserialize(value)?)
+}
+
+/// Classify a serde error found at `path` ("." for the root)
+///
+/// serde reports a missing field at the struct that lacks it, so its name
+/// is appended to the path. Data errors elsewhere in the document become
+/// validation errors on their path; everything else is a parse error.
+fn deserialize_error(path: &str, err: serde_json::Error) -> Error {
+    let message = err.to_string();
+    if !err.is_data() {
+        return Error::Parse(message);
+    }
+    if let Some(name) = message.strip_prefix("missing field `").and_then(|rest| rest.split('`').next()) {
+        let field = if path == "." { name.to_string() } else { format!("{}.{}", path, name) };
+        return Error::Validation { message: format!("Missing required field `{}`", field), field };
+    }
+    match path {
+        "." => Error::Parse(message),
+        _ => Error::validation(path, format!("Invalid `{}`: {}", path, message)),
+    }
+}
+
+/// Convert to a thrown JS `Error` whose message is the JSON error
+pub fn to_js_error(err: Error) -> wasm_bindgen::JsError {
+    wasm_bindgen::JsError::new(&err.to_json())
+}
+
+/// Serialize a successful result, or the error in the stable schema
+pub fn to_json_response<T: Serialize>(result: Result<T>) -> String {
+    result
+        .and_then(|output| {
+            serde_json::to_string(&output)
+                .map_err(|e| Error::Numerical(format!("Failed to serialize output: {}", e)))
+        })
+        .unwrap_or_else(|err| err.to_json())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_json_schema() {
+        let json: serde_json::Value = serde_json::from_str(
+            &Error::validation("start_month", "start_month must be between 1 and 12").to_json(),
+        ).unwrap();
+        assert_eq!(json["error"], "start_month must be between 1 and 12");
+        assert_eq!(json["code"], "validation_error");
+        assert_eq!(json["field"], "start_month");
+
+        let json: serde_json::Value = serde_json::from_str(&Error::Numerical("NaN".into()).to_json()).unwrap();
+        assert_eq!(json["code"], "numerical_error");
+        assert!(json.get("field").is_none());
+    }
+
+    #[test]
+    fn test_from_serde_error() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Input { series: Vec<f64> }
+
+        let err = from_json::<Input>("{}").unwrap_err();
+        assert_eq!(err.field(), Some("series"));
+
+        let err = from_json::<Input>("not json").unwrap_err();
+        assert_eq!(err.code(), "parse_error");
+        assert!(err.to_string().starts_with("Failed to parse input:"));
+        assert!(from_json::<Input>(r#"{"series": []} trailing"#).is_err());
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Nested { outliers: Outliers }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Outliers { force: Vec<Event> }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Event { month: u32, kind: Kind }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind { Additive, LevelShift }
+
+    #[test]
+    fn test_nested_errors_name_the_full_path() {
+        let err = from_json::<Nested>(r#"{"outliers": {"force": [{"month": "May", "kind": "additive"}]}}"#).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+        assert_eq!(err.field(), Some("outliers.force[0].month"));
+
+        let err = from_value::<Nested>(serde_json::json!({
+            "outliers": { "force": [{ "month": 3, "kind": "additive" }, { "month": 4, "kind": "spike" }] },
+        })).unwrap_err();
+        assert_eq!(err.field(), Some("outliers.force[1].kind"));
+        assert!(err.to_string().contains("unknown variant `spike`"));
+
+        let err = from_json::<Nested>(r#"{"outliers": {"force": [{"kind": "level_shift"}]}}"#).unwrap_err();
+        assert_eq!(err.field(), Some("outliers.force[0].month"));
+    }
+}
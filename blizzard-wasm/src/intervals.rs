@@ -0,0 +1,65 @@
+//! Prediction interval helpers shared by ARIMA and the benchmark forecasters
+//!
+//! Every model produces a point forecast plus a standard error per horizon;
+//! these helpers turn that into lower/upper bounds the same way everywhere.
+
+use crate::warnings::{Warning, WarningCode};
+
+/// Z-score for a two-sided confidence level
+///
+/// Only the levels used by the dashboard are tabulated; anything else
+/// falls back to 95%.
+pub fn z_score(confidence: f64) -> f64 {
+    tabulated_z(confidence).unwrap_or(1.96)
+}
+
+/// Warning if `z_score` would fall back to 95% for this confidence level
+pub fn confidence_warning(confidence: f64) -> Option<Warning> {
+    tabulated_z(confidence).is_none().then(|| Warning::new(
+        WarningCode::UnknownConfidence,
+        format!("Confidence level {} is not tabulated; 95% intervals used", confidence),
+    ))
+}
+
+/// Z-score for a tabulated confidence level
+fn tabulated_z(confidence: f64) -> Option<f64> {
+    [(0.99, 2.576), (0.95, 1.96), (0.90, 1.645), (0.80, 1.28)].iter()
+        .find(|(c, _)| (confidence - c).abs() < 0.001)
+        .map(|&(_, z)| z)
+}
+
+/// Build lower/upper bounds from point forecasts and per-step standard errors
+///
+/// The lower bound is floored at zero, matching the non-negative forecasts.
+pub fn interval_bounds(forecast: &[f64], standard_errors: &[f64], z: f64) -> (Vec<f64>, Vec<f64>) {
+    let lower = forecast.iter().zip(standard_errors)
+        .map(|(&f, &se)| (f - z * se).max(0.0))
+        .collect();
+    let upper = forecast.iter().zip(standard_errors)
+        .map(|(&f, &se)| f + z * se)
+        .collect();
+
+    (lower, upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_z_score() {
+        assert!((z_score(0.80) - 1.28).abs() < 1e-10);
+        assert!((z_score(0.95) - 1.96).abs() < 1e-10);
+        // Unknown levels fall back to 95%
+        assert!((z_score(0.42) - 1.96).abs() < 1e-10);
+        assert!(confidence_warning(0.42).is_some());
+        assert!(confidence_warning(0.90).is_none());
+    }
+
+    #[test]
+    fn test_interval_bounds() {
+        let (lower, upper) = interval_bounds(&[100.0, 5.0], &[10.0, 10.0], 1.0);
+        assert_eq!(lower, vec![90.0, 0.0]);
+        assert_eq!(upper, vec![110.0, 15.0]);
+    }
+}
@@ -4,19 +4,78 @@
 //! - Seasonal decomposition (period 12 for monthly data)
 //! - Easter regressor support (ARIMAX)
 //! - 80% confidence intervals
+//! - Benchmark forecasters (naive, seasonal naive, drift, mean, Theta)
+//! - Forecast combination (ensemble) across all of the above
+//! - Unit-root and stationarity tests (ADF, KPSS, OCSB) to choose d
+//! - Residual diagnostics (Ljung-Box, Jarque-Bera, heteroscedasticity)
+//! - ACF/PACF/CCF exploration with significance bounds
+//! - Batch forecasting of many keyed series in one call
+//! - Sales cubes by customer × product group × geography, sliced and aggregated
+//! - Scenario adjustments (scale, remove, new business, timing shifts,
+//!   growth, trend, caps and floors) over the baseline, with overlapping
+//!   adjustments reported and combined by a chosen composition rule, and
+//!   a waterfall attributing the forecast to each adjustment
+//! - Side-by-side comparison of several scenarios against a shared baseline
+//! - Hierarchical reconciliation (bottom-up, top-down, MinT) of forecasts by node
 //!
 //! The interface uses JSON for input/output to keep the WASM boundary simple.
 
 use wasm_bindgen::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+// The model API (one-shot fits, filtered and smoothed states) is wider than
+// what the WASM entry points use
+#[allow(dead_code)]
 mod arima;
+mod arrays;
+mod attribution;
+mod batch;
+mod benchmarks;
+mod comparison;
+mod conflicts;
+mod correlation;
+mod cube;
+mod diagnostics;
+mod distributions;
 mod easter;
+mod ensemble;
+mod error;
+mod intervals;
+mod linalg;
+mod metrics;
+mod model;
+mod outliers;
+mod polynomial;
+mod ramp;
+mod reconcile;
+mod scenario;
+mod seasonality;
+mod snapshot;
+#[allow(dead_code)]
+mod state_space;
+mod stationarity;
+mod warnings;
+
+use arima::{FitMetadata, ForecastOptions};
+use benchmarks::{fit_and_forecast_method, is_degenerate, Method};
+use correlation::{acf_correlogram, ccf_correlogram, default_max_lag, pacf_correlogram, Correlogram};
+use diagnostics::ResidualDiagnostics;
+use ensemble::{fit_ensemble, EnsembleComponent, EnsembleConfig, Weighting};
+use error::{to_js_error, to_json_response, Error};
+use intervals::confidence_warning;
+use metrics::{holdout_accuracy, AccuracyMetrics};
+use outliers::{DetectedOutlier, OutlierOptions};
+use stationarity::{adf_test, kpss_test, ocsb_test, suggest_differencing, DifferencingSuggestion, Regression, TestResult};
+use warnings::Warning;
+
+pub use arrays::{forecast_arrays, ForecastArrays};
+pub use model::Model;
 
 /// Input structure for forecast requests
 #[derive(Deserialize)]
 pub struct ForecastInput {
-    /// Time series values
+    /// Time series values (null marks a missing month)
+    #[serde(deserialize_with = "deserialize_series")]
     pub series: Vec<f64>,
     /// Start year of the series
     pub start_year: i32,
@@ -27,9 +86,9 @@ pub struct ForecastInput {
     /// AR order (default: 2)
     #[serde(default = "default_p")]
     pub p: usize,
-    /// Differencing order (default: 1)
-    #[serde(default = "default_d")]
-    pub d: usize,
+    /// Differencing order (default: chosen by stationarity tests)
+    #[serde(default)]
+    pub d: Option<usize>,
     /// MA order (default: 1)
     #[serde(default = "default_q")]
     pub q: usize,
@@ -39,13 +98,51 @@ pub struct ForecastInput {
     /// Whether to use Easter regressor (default: true)
     #[serde(default = "default_use_easter")]
     pub use_easter_regressor: bool,
+    /// Regressor covering the series and the forecast horizon, used instead
+    /// of the Easter regressor (default: none)
+    #[serde(default)]
+    pub regressor: Option<Vec<f64>>,
+    /// Prediction interval coverage (default: 0.80)
+    #[serde(default = "default_confidence")]
+    pub confidence: f64,
+    /// Forecasting method (default: arima)
+    #[serde(default)]
+    pub method: Method,
+    /// Benchmark methods to run alongside for comparison (default: none)
+    #[serde(default)]
+    pub benchmarks: Vec<Method>,
+    /// Method to use if the main fit is degenerate; null disables (default: seasonal_naive)
+    #[serde(default = "default_fallback")]
+    pub fallback: Option<Method>,
+    /// Months held back to score accuracy when benchmarking (default: 12)
+    #[serde(default = "default_holdout_months")]
+    pub holdout_months: usize,
+    /// Ensemble settings, used when `method` is "ensemble" (default: equal weights)
+    #[serde(default)]
+    pub ensemble: Option<EnsembleConfig>,
+    /// Outlier detection and forced/suppressed interventions (default: detection off)
+    #[serde(default)]
+    pub outliers: OutlierOptions,
+}
+
+/// Read series values, mapping null (missing) to NaN
+fn deserialize_series<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+    let values: Vec<Option<f64>> = Vec::deserialize(deserializer)?;
+    Ok(values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect())
+}
+
+/// Write series values, mapping NaN (missing) to null
+fn serialize_series<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|v| v.is_finite().then_some(*v)))
 }
 
 fn default_p() -> usize { 2 }
-fn default_d() -> usize { 1 }
 fn default_q() -> usize { 1 }
 fn default_seasonal_period() -> usize { 12 }
 fn default_use_easter() -> bool { true }
+fn default_confidence() -> f64 { 0.80 }
+fn default_fallback() -> Option<Method> { Some(Method::SeasonalNaive) }
+fn default_holdout_months() -> usize { 12 }
 
 /// Output structure for forecast results
 #[derive(Serialize)]
@@ -58,14 +155,66 @@ pub struct ForecastOutput {
     pub upper: Vec<f64>,
     /// Seasonal factors (12 values)
     pub seasonal_factors: Vec<f64>,
-    /// Easter coefficient (if ARIMAX)
+    /// Easter coefficient (0 without the calendar regressor)
     pub easter_coefficient: f64,
+    /// Custom regressor coefficient (only with a custom regressor)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regressor_coefficient: Option<f64>,
     /// AR coefficients
     pub ar_coefficients: Vec<f64>,
     /// MA coefficients
     pub ma_coefficients: Vec<f64>,
     /// Model intercept
     pub intercept: f64,
+    /// Interventions applied (detected outliers and forced events)
+    pub outliers: Vec<DetectedOutlier>,
+    /// Differencing orders suggested by the stationarity tests (only when d was not given)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub differencing: Option<DifferencingSuggestion>,
+    /// Substituted defaults and corrections, as `{code, message}`
+    pub warnings: Vec<Warning>,
+    /// Sample size, sigma², log-likelihood and AIC/AICc/BIC (ARIMA only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fit: Option<FitMetadata>,
+    /// Wall-clock time spent in `forecast`, in milliseconds
+    pub runtime_ms: f64,
+    /// Method that produced the forecast
+    pub method: Method,
+    /// Whether the requested method was degenerate and the fallback was used
+    pub fallback_used: bool,
+    /// Holdout accuracy of `method` (only when benchmarks are requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<AccuracyMetrics>,
+    /// Benchmark forecasts for comparison
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub benchmarks: Vec<BenchmarkOutput>,
+    /// Component forecasts and weights (only for the ensemble method)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ensemble: Option<EnsembleOutput>,
+}
+
+/// Breakdown of an ensemble forecast
+#[derive(Serialize)]
+pub struct EnsembleOutput {
+    /// Weighting scheme applied (equal if the series was too short for a holdout)
+    pub weighting: Weighting,
+    /// Component forecasts, intervals and weights
+    pub components: Vec<EnsembleComponent>,
+}
+
+/// Forecast from a benchmark method, scored on the same holdout
+#[derive(Serialize)]
+pub struct BenchmarkOutput {
+    /// Benchmark method
+    pub method: Method,
+    /// Point forecasts
+    pub forecast: Vec<f64>,
+    /// Lower bound of confidence interval
+    pub lower: Vec<f64>,
+    /// Upper bound of confidence interval
+    pub upper: Vec<f64>,
+    /// Holdout accuracy (None if the series is too short)
+    pub accuracy: Option<AccuracyMetrics>,
 }
 
 /// Main WASM entry point for forecasting
@@ -88,31 +237,246 @@ pub struct ForecastOutput {
 /// ```
 #[wasm_bindgen]
 pub fn forecast(input_json: &str) -> String {
-    // Parse input
-    let input: ForecastInput = match serde_json::from_str(input_json) {
-        Ok(i) => i,
-        Err(e) => {
-            return serde_json::to_string(&ErrorOutput {
-                error: format!("Failed to parse input: {}", e),
-            }).unwrap_or_else(|_| r#"{"error":"Failed to serialize error"}"#.to_string());
+    to_json_response(run_forecast(input_json))
+}
+
+/// Same as `forecast`, but throws on failure instead of returning an error object
+///
+/// The thrown `Error`'s message is the JSON error (`{error, code, field?}`),
+/// so callers can `JSON.parse(e.message)` for field-level messages.
+#[wasm_bindgen]
+pub fn forecast_or_throw(input_json: &str) -> Result<String, JsError> {
+    run_forecast(input_json)
+        .and_then(|output| {
+            serde_json::to_string(&output)
+                .map_err(|e| Error::Numerical(format!("Failed to serialize output: {}", e)))
+        })
+        .map_err(to_js_error)
+}
+
+/// Forecast many keyed series in one call, with shared defaults and
+/// per-series overrides
+///
+/// Returns `{results: {key: output | error}, succeeded, failed, runtime_ms}`;
+/// one series failing does not fail the batch. `progress`, if given, is
+/// called as `progress(completed, total, key)` after each series.
+#[wasm_bindgen]
+pub fn forecast_batch(input_json: &str, progress: Option<js_sys::Function>) -> String {
+    to_json_response(batch::run_batch(input_json, |done, total, key| {
+        if let Some(callback) = &progress {
+            // A throwing callback must not abort the batch
+            let _ = callback.call3(
+                &JsValue::NULL,
+                &JsValue::from(done as u32),
+                &JsValue::from(total as u32),
+                &JsValue::from_str(key),
+            );
+        }
+    }))
+}
+
+/// Apply a scenario's adjustments to the baseline history and forecast it
+///
+/// Returns `{id, name, adjusted: {start_year, start_month, series, applied,
+/// composition, conflicts}, forecast, attribution?}` or the JSON error object.
+#[wasm_bindgen]
+pub fn run_scenario(baseline_json: &str, scenario_json: &str) -> String {
+    to_json_response(scenario::run_scenario(baseline_json, scenario_json))
+}
+
+/// Apply a list of adjustments to the baseline history without forecasting
+///
+/// `composition` ("multiplicative", "override" or "additive") sets how
+/// overlapping scales combine. Returns `{start_year, start_month, series,
+/// applied, composition, conflicts}` or the JSON error object.
+#[wasm_bindgen]
+pub fn apply_adjustments(baseline_json: &str, adjustments_json: &str, composition: Option<String>) -> String {
+    to_json_response(scenario::apply_adjustments_json(baseline_json, adjustments_json, composition.as_deref()))
+}
+
+/// Compare several scenarios against one baseline forecast
+///
+/// Takes `{baseline, scenarios, forecast_options?}`; returns `{months,
+/// baseline, scenarios, ranking}` or the JSON error object.
+#[wasm_bindgen]
+pub fn compare_scenarios(input_json: &str) -> String {
+    to_json_response(comparison::compare_scenarios_json(input_json))
+}
+
+/// Seasonal profile for a product group in a geography, borrowed from
+/// comparable series in a sales cube
+///
+/// Takes `{cube, product_group, geography, min_months?}`; returns
+/// `{seasonal_factors, source, level?, contributors}` or the JSON error object.
+#[wasm_bindgen]
+pub fn seasonal_profile(input_json: &str) -> String {
+    to_json_response(seasonality::borrow_profile_json(input_json))
+}
+
+/// Spread year-1/2/3 totals over 36 months with a ramp curve and seasonality
+///
+/// Takes `{year1_value, year2_value, year3_value, start_month, ramp?,
+/// seasonal_factors?}`; returns `{values, year_totals}` or the JSON error object.
+#[wasm_bindgen]
+pub fn ramp_profile(input_json: &str) -> String {
+    to_json_response(ramp::ramp_profile_json(input_json))
+}
+
+/// Aggregate a slice of a sales cube (`{customer?, product_group?, geography?}`)
+///
+/// Returns `{start_year, start_month, series, cells}` or the JSON error object.
+#[wasm_bindgen]
+pub fn aggregate_slice(cube_json: &str, slice_json: &str) -> String {
+    to_json_response(cube::aggregate_slice_json(cube_json, slice_json))
+}
+
+/// Forecast a slice of a sales cube
+///
+/// Takes `{cube, slice, ...}` with any other `forecast` field except the
+/// series and start date, which come from the cube.
+#[wasm_bindgen]
+pub fn forecast_slice(input_json: &str) -> String {
+    to_json_response(cube::forecast_slice(input_json))
+}
+
+/// Reconcile base forecasts over a hierarchy so that children add up to parents
+///
+/// Takes `{method, hierarchy, forecasts: {node: {forecast, lower?, upper?,
+/// history?, residuals?}}, confidence?}`; returns `{method, nodes: {node:
+/// {forecast, lower?, upper?}}, leaves, base_incoherence, ...}` or the JSON
+/// error object.
+#[wasm_bindgen]
+pub fn reconcile(input_json: &str) -> String {
+    to_json_response(reconcile::reconcile_json(input_json))
+}
+
+/// Check ARIMA settings shared by `forecast` and `diagnose`
+fn validate_arima(
+    series: &[f64],
+    start_month: u32,
+    p: usize,
+    d: Option<usize>,
+    q: usize,
+    seasonal_period: usize,
+) -> error::Result<()> {
+    if !(1..=12).contains(&start_month) {
+        return Err(Error::validation("start_month", "start_month must be between 1 and 12"));
+    }
+    if seasonal_period == 0 {
+        return Err(Error::validation("seasonal_period", "seasonal_period must be at least 1"));
+    }
+    if d.is_some_and(|d| d > 2) {
+        return Err(Error::unsupported("d", "Differencing orders above 2 are not supported"));
+    }
+    if series.len() < p + d.unwrap_or(1) + q + seasonal_period {
+        return Err(Error::validation("series", "Series too short for specified ARIMA parameters"));
+    }
+    if !series.iter().any(|x| x.is_finite()) {
+        return Err(Error::validation("series", "Series has no observed values"));
+    }
+    Ok(())
+}
+
+fn run_forecast(input_json: &str) -> error::Result<ForecastOutput> {
+    let started = now_ms();
+    let input: ForecastInput = error::from_json(input_json)?;
+    forecast_input(input, started)
+}
+
+/// Forecast from parsed input; `started` is when timing began
+fn forecast_input(input: ForecastInput, started: f64) -> error::Result<ForecastOutput> {
+    validate_arima(&input.series, input.start_month, input.p, input.d, input.q, input.seasonal_period)?;
+    input.outliers.validate(input.start_year, input.start_month, input.series.len())?;
+    if !(input.confidence > 0.0 && input.confidence < 1.0) {
+        return Err(Error::validation("confidence", "confidence must be between 0 and 1"));
+    }
+    if let Some(regressor) = &input.regressor {
+        let needed = input.series.len() + input.forecast_months;
+        if regressor.len() < needed {
+            return Err(Error::validation(
+                "regressor",
+                format!("regressor must cover the series and forecast horizon ({} values)", needed),
+            ));
+        }
+        // Fitted as a dummy (mean difference), so only 0/1 values are meaningful
+        if regressor.iter().any(|&x| x != 0.0 && x != 1.0) {
+            return Err(Error::validation("regressor", "regressor values must be 0 or 1"));
+        }
+    }
+
+    // Run forecast, falling back to a benchmark if the fit is unusable
+    let options = ForecastOptions {
+        use_easter: input.use_easter_regressor,
+        outliers: input.outliers.clone(),
+        p: input.p,
+        d: input.d,
+        q: input.q,
+        seasonal_period: input.seasonal_period,
+        confidence: input.confidence,
+        regressor: input.regressor.clone(),
+    };
+    let ensemble_config = input.ensemble.clone().unwrap_or_default();
+    let run = |method: Method, series: &[f64], months: usize| {
+        fit_and_forecast_method(method, series, input.start_year, input.start_month, months, &options, &ensemble_config)
+    };
+
+    let mut method = input.method;
+    let mut ensemble = None;
+    let mut result = if method == Method::Ensemble {
+        let fitted = fit_ensemble(
+            &ensemble_config,
+            &input.series,
+            input.start_year,
+            input.start_month,
+            input.forecast_months,
+            &options,
+        );
+        ensemble = Some(EnsembleOutput {
+            weighting: fitted.weighting,
+            components: fitted.components,
+        });
+        fitted.combined
+    } else {
+        run(method, &input.series, input.forecast_months)
+    };
+    let mut fallback_used = false;
+
+    if let Some(fallback) = input.fallback {
+        if fallback != method && is_degenerate(&result, &input.series) {
+            method = fallback;
+            result = run(method, &input.series, input.forecast_months);
+            fallback_used = true;
+            ensemble = None;
         }
+    }
+
+    // Score against benchmarks on a common holdout
+    let score = |method: Method| {
+        holdout_accuracy(&input.series, input.holdout_months, input.seasonal_period, |training, steps| {
+            run(method, training, steps).forecast
+        })
     };
 
-    // Validate input
-    if input.series.len() < input.p + input.d + input.q + input.seasonal_period {
-        return serde_json::to_string(&ErrorOutput {
-            error: "Series too short for specified ARIMA parameters".to_string(),
-        }).unwrap_or_else(|_| r#"{"error":"Series too short"}"#.to_string());
+    let benchmarks: Vec<BenchmarkOutput> = input.benchmarks.iter()
+        .map(|&benchmark| {
+            let b = run(benchmark, &input.series, input.forecast_months);
+            BenchmarkOutput {
+                method: benchmark,
+                forecast: b.forecast,
+                lower: b.lower,
+                upper: b.upper,
+                accuracy: score(benchmark),
+            }
+        })
+        .collect();
+    let accuracy = if benchmarks.is_empty() { None } else { score(method) };
+
+    if result.forecast.iter().chain(&result.lower).chain(&result.upper).any(|x| !x.is_finite()) {
+        return Err(Error::Numerical(format!("{:?} produced non-finite forecasts", method)));
     }
 
-    // Run forecast
-    let result = arima::fit_and_forecast(
-        &input.series,
-        input.start_year,
-        input.start_month,
-        input.forecast_months,
-        input.use_easter_regressor,
-    );
+    let mut warnings: Vec<Warning> = confidence_warning(input.confidence).into_iter().collect();
+    warnings.extend(result.warnings);
 
     // Convert to output format
     let output = ForecastOutput {
@@ -121,20 +485,254 @@ pub fn forecast(input_json: &str) -> String {
         upper: result.upper,
         seasonal_factors: result.seasonal_factors,
         easter_coefficient: result.easter_coefficient,
+        regressor_coefficient: result.regressor_coefficient,
         ar_coefficients: result.ar_coefficients,
         ma_coefficients: result.ma_coefficients,
         intercept: result.intercept,
+        outliers: result.outliers,
+        differencing: result.differencing,
+        warnings,
+        fit: result.fit,
+        runtime_ms: now_ms() - started,
+        method,
+        fallback_used,
+        accuracy,
+        benchmarks,
+        ensemble,
+    };
+
+    Ok(output)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance)]
+    fn now() -> f64;
+}
+
+/// Milliseconds from an arbitrary origin, for timing
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    now()
+}
+
+/// Milliseconds from an arbitrary origin, for timing
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
+/// Input for `diagnose`: the model settings of `ForecastInput` without the forecast
+#[derive(Deserialize)]
+struct DiagnoseInput {
+    #[serde(deserialize_with = "deserialize_series")]
+    series: Vec<f64>,
+    start_year: i32,
+    start_month: u32,
+    #[serde(default = "default_p")]
+    p: usize,
+    #[serde(default)]
+    d: Option<usize>,
+    #[serde(default = "default_q")]
+    q: usize,
+    #[serde(default = "default_seasonal_period")]
+    seasonal_period: usize,
+    #[serde(default = "default_use_easter")]
+    use_easter_regressor: bool,
+    #[serde(default)]
+    outliers: OutlierOptions,
+    /// Lags for ACF/PACF and Ljung-Box (default: min(2m, n/5))
+    #[serde(default)]
+    lags: Option<usize>,
+}
+
+/// Output of `diagnose`
+#[derive(Serialize)]
+struct DiagnoseOutput {
+    #[serde(flatten)]
+    diagnostics: ResidualDiagnostics,
+    /// Index in `series` of the first residual (the first d seed the levels)
+    residual_start: usize,
+    sigma2: f64,
+    log_likelihood: f64,
+}
+
+impl DiagnoseOutput {
+    fn new(model: &arima::Arima, series_len: usize, lags: Option<usize>) -> Self {
+        DiagnoseOutput {
+            diagnostics: model.diagnostics(lags),
+            residual_start: series_len - model.residuals().len(),
+            sigma2: model.sigma2(),
+            log_likelihood: model.log_likelihood(),
+        }
+    }
+}
+
+/// Fit the ARIMA model and report residual diagnostics
+///
+/// Takes the same model settings as `forecast` (without `forecast_months`)
+/// plus optional `lags`. Residuals are standardized one-step errors.
+#[wasm_bindgen]
+pub fn diagnose(input_json: &str) -> String {
+    to_json_response(run_diagnose(input_json))
+}
+
+fn run_diagnose(input_json: &str) -> error::Result<DiagnoseOutput> {
+    let input: DiagnoseInput = error::from_json(input_json)?;
+    validate_arima(&input.series, input.start_month, input.p, input.d, input.q, input.seasonal_period)?;
+    input.outliers.validate(input.start_year, input.start_month, input.series.len())?;
+
+    let options = ForecastOptions {
+        use_easter: input.use_easter_regressor,
+        outliers: input.outliers,
+        p: input.p,
+        d: input.d,
+        q: input.q,
+        seasonal_period: input.seasonal_period,
+        ..ForecastOptions::default()
+    };
+    let fitted = arima::fit_with_options(&input.series, input.start_year, input.start_month, &options);
+
+    Ok(DiagnoseOutput::new(&fitted.model, input.series.len(), input.lags))
+}
+
+/// Input for `correlations`
+#[derive(Deserialize)]
+struct CorrelationInput {
+    #[serde(deserialize_with = "deserialize_series")]
+    series: Vec<f64>,
+    /// Optional regressor for cross-correlation (same length as `series`)
+    #[serde(default, deserialize_with = "deserialize_optional_series")]
+    regressor: Option<Vec<f64>>,
+    /// Maximum lag (default: 10 log10(n))
+    #[serde(default)]
+    max_lag: Option<usize>,
+    /// Confidence level of the bounds (default: 0.95)
+    #[serde(default = "default_correlation_confidence")]
+    confidence: f64,
+}
+
+fn default_correlation_confidence() -> f64 { 0.95 }
+
+fn deserialize_optional_series<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<f64>>, D::Error> {
+    let values: Option<Vec<Option<f64>>> = Option::deserialize(deserializer)?;
+    Ok(values.map(|v| v.into_iter().map(|x| x.unwrap_or(f64::NAN)).collect()))
+}
+
+/// Output of `correlations`
+#[derive(Serialize)]
+struct CorrelationOutput {
+    acf: Correlogram,
+    pacf: Correlogram,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ccf: Option<Correlogram>,
+    warnings: Vec<Warning>,
+}
+
+/// ACF and PACF of a series, and its CCF with a regressor if given
+///
+/// Takes `{series, regressor?, max_lag?, confidence?}` (nulls mark missing
+/// months). Each correlogram carries its lags, values and ±limit.
+#[wasm_bindgen]
+pub fn correlations(input_json: &str) -> String {
+    to_json_response(run_correlations(input_json))
+}
+
+fn run_correlations(input_json: &str) -> error::Result<CorrelationOutput> {
+    let input: CorrelationInput = error::from_json(input_json)?;
+    if !(input.confidence > 0.0 && input.confidence < 1.0) {
+        return Err(Error::validation("confidence", "confidence must be between 0 and 1"));
+    }
+    if input.regressor.as_ref().is_some_and(|x| x.len() != input.series.len()) {
+        return Err(Error::validation("regressor", "regressor must have the same length as series"));
+    }
+
+    let max_lag = input.max_lag
+        .unwrap_or_else(|| default_max_lag(input.series.len()))
+        .min(input.series.len().saturating_sub(1));
+    let output = CorrelationOutput {
+        acf: acf_correlogram(&input.series, max_lag, input.confidence),
+        pacf: pacf_correlogram(&input.series, max_lag, input.confidence),
+        ccf: input.regressor.as_ref()
+            .map(|x| ccf_correlogram(&input.series, x, max_lag, input.confidence)),
+        warnings: confidence_warning(input.confidence).into_iter().collect(),
     };
 
-    // Serialize output
-    serde_json::to_string(&output)
-        .unwrap_or_else(|_| r#"{"error":"Failed to serialize output"}"#.to_string())
+    Ok(output)
+}
+
+/// ACF at lags 0..=max_lag from a Float64Array (NaN marks missing)
+#[wasm_bindgen]
+pub fn acf_values(series: &[f64], max_lag: usize) -> Vec<f64> {
+    correlation::acf(series, max_lag.min(series.len().saturating_sub(1)))
+}
+
+/// PACF at lags 1..=max_lag from a Float64Array (NaN marks missing)
+#[wasm_bindgen]
+pub fn pacf_values(series: &[f64], max_lag: usize) -> Vec<f64> {
+    correlation::pacf(series, max_lag.min(series.len().saturating_sub(1)))
 }
 
-/// Error output structure
+/// CCF cor(series_{t+k}, regressor_t) at k = -max_lag..=max_lag from Float64Arrays
+#[wasm_bindgen]
+pub fn ccf_values(series: &[f64], regressor: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = series.len().min(regressor.len());
+    correlation::ccf(series, regressor, max_lag.min(n.saturating_sub(1)))
+}
+
+/// White-noise significance limit z/√n for `n` observations
+#[wasm_bindgen]
+pub fn correlation_limit(n: usize, confidence: f64) -> f64 {
+    correlation::significance_limit(n, confidence)
+}
+
+/// Input for `stationarity_tests`
+#[derive(Deserialize)]
+struct StationarityInput {
+    #[serde(deserialize_with = "deserialize_series")]
+    series: Vec<f64>,
+    #[serde(default = "default_seasonal_period")]
+    seasonal_period: usize,
+    /// Deterministic terms for ADF and KPSS (default: constant)
+    #[serde(default)]
+    regression: Regression,
+}
+
+/// Output of `stationarity_tests`
 #[derive(Serialize)]
-struct ErrorOutput {
-    error: String,
+struct StationarityOutput {
+    adf: Option<TestResult>,
+    kpss: Option<TestResult>,
+    seasonal: Option<TestResult>,
+    suggested: DifferencingSuggestion,
+}
+
+/// Run unit-root and stationarity tests on a series
+///
+/// Takes `{series, seasonal_period?, regression?}` and returns ADF, KPSS and
+/// OCSB seasonal results (null where the series is too short) together with
+/// the suggested `d` and `seasonal_d`. Missing values are dropped.
+#[wasm_bindgen]
+pub fn stationarity_tests(input_json: &str) -> String {
+    to_json_response(run_stationarity_tests(input_json))
+}
+
+fn run_stationarity_tests(input_json: &str) -> error::Result<StationarityOutput> {
+    let input: StationarityInput = error::from_json(input_json)?;
+
+    let series: Vec<f64> = input.series.iter().copied().filter(|x| x.is_finite()).collect();
+    let output = StationarityOutput {
+        adf: adf_test(&series, input.regression, None),
+        kpss: kpss_test(&series, input.regression, None),
+        seasonal: ocsb_test(&series, input.seasonal_period),
+        suggested: suggest_differencing(&series, input.seasonal_period),
+    };
+
+    Ok(output)
 }
 
 /// Get Easter dates for a range of years (utility function)
@@ -197,7 +795,211 @@ mod tests {
         assert_eq!(input.series.len(), 24);
         assert_eq!(input.start_year, 2022);
         assert_eq!(input.p, 2);  // default
+        assert_eq!(input.d, None);  // default: chosen by stationarity tests
         assert!(input.use_easter_regressor);  // default
+        assert_eq!(input.method, Method::Arima);  // default
+        assert_eq!(input.fallback, Some(Method::SeasonalNaive));  // default
+    }
+
+    /// Call a JSON entry point and parse its output
+    fn call(function: fn(&str) -> String, input: &serde_json::Value) -> serde_json::Value {
+        serde_json::from_str(&function(&input.to_string())).unwrap()
+    }
+
+    /// Linear trend with a December bump
+    fn seasonal_series() -> Vec<i32> {
+        (0..48).map(|i| 1000 + 10 * i + if i % 12 == 11 { 300 } else { 0 }).collect()
+    }
+
+    /// Linear trend with a little deterministic noise
+    fn noisy_series(n: i32) -> Vec<i32> {
+        (0..n).map(|i| 1000 + 10 * i + (i * 7) % 13).collect()
+    }
+
+    #[test]
+    fn test_forecast_with_benchmarks() {
+        let output = call(forecast, &serde_json::json!({
+            "series": seasonal_series(), "start_year": 2020, "start_month": 1,
+            "forecast_months": 6, "benchmarks": ["naive", "theta"],
+        }));
+        assert_eq!(output["method"], "arima");
+        assert_eq!(output["forecast"].as_array().unwrap().len(), 6);
+        assert!(output["accuracy"]["mae"].is_number());
+
+        let benchmarks = output["benchmarks"].as_array().unwrap();
+        assert_eq!(benchmarks.len(), 2);
+        assert_eq!(benchmarks[1]["method"], "theta");
+        assert_eq!(benchmarks[1]["forecast"].as_array().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_forecast_ensemble() {
+        let output = call(forecast, &serde_json::json!({
+            "series": seasonal_series(), "start_year": 2020, "start_month": 1, "forecast_months": 6,
+            "method": "ensemble",
+            "ensemble": { "models": ["arima", "seasonal_naive"], "weighting": "inverse_error" },
+        }));
+        assert_eq!(output["method"], "ensemble");
+        assert_eq!(output["ensemble"]["weighting"], "inverse_error");
+
+        let components = output["ensemble"]["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        let total: f64 = components.iter().map(|c| c["weight"].as_f64().unwrap()).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        // Holdout accuracy scores the caller's ensemble, not the default one
+        let output = call(forecast, &serde_json::json!({
+            "series": seasonal_series(), "start_year": 2020, "start_month": 1, "forecast_months": 6,
+            "method": "ensemble", "ensemble": { "models": ["seasonal_naive"] },
+            "benchmarks": ["seasonal_naive"],
+        }));
+        assert_eq!(output["accuracy"], output["benchmarks"][0]["accuracy"]);
+    }
+
+    #[test]
+    fn test_forecast_with_missing_values() {
+        let series: Vec<Option<i32>> = (0..48).map(|i| (i != 20 && i != 33).then_some(1000 + 10 * i)).collect();
+        let output = call(forecast, &serde_json::json!({
+            "series": series, "start_year": 2020, "start_month": 1, "forecast_months": 6,
+        }));
+        assert_eq!(output["method"], "arima");
+        assert_eq!(output["fallback_used"], false);
+        assert!(output["forecast"].as_array().unwrap().iter().all(|f| f.as_f64().unwrap() > 0.0));
+    }
+
+    #[test]
+    fn test_forecast_reports_outliers() {
+        let series: Vec<i32> = (0..48).map(|i| 1000 + 10 * i + (i * 37) % 23 + if i == 15 { 3000 } else { 0 }).collect();
+        let output = call(forecast, &serde_json::json!({
+            "series": series, "start_year": 2019, "start_month": 1, "forecast_months": 6,
+            "outliers": { "detect": true, "force": [{ "year": 2021, "month": 6, "kind": "level_shift" }] },
+        }));
+        let outliers = output["outliers"].as_array().unwrap();
+
+        let spike = outliers.iter().find(|o| o["index"] == 15).expect("spike detected");
+        assert_eq!(spike["kind"], "additive");
+        assert_eq!(spike["year"], 2020);
+        assert_eq!(spike["month"], 4);
+        assert!(outliers.iter().any(|o| o["forced"] == true && o["kind"] == "level_shift"));
+    }
+
+    #[test]
+    fn test_forecast_reports_suggested_differencing() {
+        let series: Vec<i32> = (0..48).map(|i| 1000 + 10 * i).collect();
+        let mut input = serde_json::json!({
+            "series": series, "start_year": 2020, "start_month": 1, "forecast_months": 6,
+        });
+        let output = call(forecast, &input);
+        assert_eq!(output["differencing"]["d"], 1);
+
+        // Explicit orders are used as given
+        input["d"] = 1.into();
+        let output = call(forecast, &input);
+        assert!(output.get("differencing").is_none());
+    }
+
+    #[test]
+    fn test_forecast_reports_warnings_and_fit() {
+        let output = call(forecast, &serde_json::json!({
+            "series": noisy_series(48), "start_year": 2020, "start_month": 1, "forecast_months": 6,
+            "d": 1, "confidence": 0.5,
+        }));
+        assert_eq!(output["warnings"][0]["code"], "unknown_confidence");
+        assert_eq!(output["fit"]["n_obs"], 47);
+        assert_eq!(output["fit"]["n_params"], 6);
+        assert!(output["fit"]["aicc"].as_f64().unwrap() > output["fit"]["aic"].as_f64().unwrap());
+        assert!(output["runtime_ms"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn test_forecast_errors() {
+        let parse = |json: &str| -> serde_json::Value { serde_json::from_str(&forecast(json)).unwrap() };
+
+        let output = parse("{not json");
+        assert_eq!(output["code"], "parse_error");
+        assert!(output["error"].as_str().unwrap().starts_with("Failed to parse input"));
+
+        let output = call(forecast, &serde_json::json!({ "series": [1, 2, 3], "start_month": 1, "forecast_months": 3 }));
+        assert_eq!(output["code"], "validation_error");
+        assert_eq!(output["field"], "start_year");
+
+        let mut input = serde_json::json!({ "series": [1, 2, 3], "start_year": 2020, "start_month": 1, "forecast_months": 3 });
+        let output = call(forecast, &input);
+        assert_eq!(output["error"], "Series too short for specified ARIMA parameters");
+        assert_eq!(output["field"], "series");
+
+        input["series"] = serde_json::json!(vec![100; 24]);
+        let with = |field: &str, value: serde_json::Value| {
+            let mut input = input.clone();
+            input[field] = value;
+            call(forecast, &input)
+        };
+        assert_eq!(with("start_month", 13.into())["field"], "start_month");
+
+        let output = with("d", 3.into());
+        assert_eq!(output["code"], "unsupported");
+        assert_eq!(output["field"], "d");
+
+        let output = with("ensemble", serde_json::json!({ "weighting": "best" }));
+        assert_eq!(output["code"], "validation_error");
+        assert_eq!(output["field"], "ensemble.weighting");
+    }
+
+    #[test]
+    fn test_forecast_or_throw_ok() {
+        let series: Vec<i32> = (0..36).map(|i| 1000 + 10 * i).collect();
+        let input = serde_json::json!({
+            "series": series, "start_year": 2020, "start_month": 1, "forecast_months": 3, "d": 1,
+        });
+        let output: serde_json::Value = serde_json::from_str(&forecast_or_throw(&input.to_string()).unwrap()).unwrap();
+        assert_eq!(output["forecast"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let output = call(diagnose, &serde_json::json!({
+            "series": noisy_series(48), "start_year": 2020, "start_month": 1, "d": 1, "lags": 6,
+        }));
+        assert_eq!(output["residual_start"], 1);
+        assert_eq!(output["residuals"].as_array().unwrap().len(), 47);
+        assert_eq!(output["acf"].as_array().unwrap().len(), 6);
+        assert!(output["ljung_box"]["p_value"].is_number());
+        assert!(output["jarque_bera"]["passed"].is_boolean());
+        assert!(output["heteroscedasticity"]["statistic"].is_number());
+    }
+
+    #[test]
+    fn test_correlations() {
+        let series: Vec<i32> = (0..48).map(|i| (i * 7) % 12).collect();
+        let regressor: Vec<Option<i32>> = (0..48).map(|i| (i != 5).then_some(i % 4)).collect();
+        let output = call(correlations, &serde_json::json!({ "series": series, "regressor": regressor, "max_lag": 12 }));
+
+        assert_eq!(output["acf"]["values"].as_array().unwrap().len(), 13);
+        assert_eq!(output["acf"]["values"][0], 1.0);
+        // Period-12 pattern is perfectly correlated at lag 12
+        assert!(output["acf"]["values"][12].as_f64().unwrap() > 0.7);
+        assert_eq!(output["pacf"]["lags"][0], 1);
+        assert_eq!(output["ccf"]["lags"].as_array().unwrap().len(), 25);
+        assert!((output["acf"]["limit"].as_f64().unwrap() - 1.96 / 48f64.sqrt()).abs() < 1e-12);
+        assert_eq!(output["warnings"], serde_json::json!([]));
+
+        let output = call(correlations, &serde_json::json!({ "series": series, "confidence": 0.85 }));
+        assert_eq!(output["warnings"][0]["code"], "unknown_confidence");
+
+        // Typed-array entry points agree with the JSON ones
+        let values: Vec<f64> = series.iter().map(|&v| v as f64).collect();
+        assert_eq!(acf_values(&values, 12)[12], output["acf"]["values"][12].as_f64().unwrap());
+        assert_eq!(pacf_values(&values, 3).len(), 3);
+        assert_eq!(ccf_values(&values, &values, 2)[2], 1.0);
+    }
+
+    #[test]
+    fn test_stationarity_tests() {
+        let output = call(stationarity_tests, &serde_json::json!({ "series": noisy_series(60) }));
+        assert_eq!(output["kpss"]["stationary"], false);
+        assert!(output["adf"]["p_value"].is_number());
+        assert!(output["seasonal"]["statistic"].is_number());
+        assert_eq!(output["suggested"]["d"], 1);
     }
 
     #[test]
@@ -0,0 +1,142 @@
+//! Small dense linear algebra for regression-based tests and reconciliation
+//!
+//! Matrices here are small (a handful of regressors, or one row per node of
+//! a forecast hierarchy), so plain Gauss-Jordan elimination with partial
+//! pivoting is all that is needed.
+
+pub type Matrix = Vec<Vec<f64>>;
+
+/// Ordinary least squares fit
+#[derive(Debug, Clone)]
+pub struct OlsFit {
+    /// Estimated coefficients, one per regressor column
+    pub coefficients: Vec<f64>,
+    /// Standard errors of the coefficients
+    pub std_errors: Vec<f64>,
+    /// Residuals y - X b
+    pub residuals: Vec<f64>,
+}
+
+/// Regress `y` on the rows of `x` (no intercept is added)
+///
+/// Returns `None` if there are fewer observations than regressors or the
+/// design matrix is singular.
+pub fn ols(x: &[Vec<f64>], y: &[f64]) -> Option<OlsFit> {
+    let n = y.len();
+    let k = x.first().map_or(0, |row| row.len());
+    if k == 0 || n <= k || x.len() != n {
+        return None;
+    }
+
+    let xtx: Matrix = (0..k)
+        .map(|i| (0..k).map(|j| x.iter().map(|row| row[i] * row[j]).sum()).collect())
+        .collect();
+    let xty: Vec<f64> = (0..k).map(|i| x.iter().zip(y).map(|(row, yi)| row[i] * yi).sum()).collect();
+
+    let inverse = invert(&xtx)?;
+    let coefficients: Vec<f64> = inverse.iter()
+        .map(|row| row.iter().zip(&xty).map(|(a, b)| a * b).sum())
+        .collect();
+
+    let residuals: Vec<f64> = x.iter().zip(y)
+        .map(|(row, yi)| yi - row.iter().zip(&coefficients).map(|(a, b)| a * b).sum::<f64>())
+        .collect();
+    let sigma2 = residuals.iter().map(|e| e * e).sum::<f64>() / (n - k) as f64;
+    let std_errors = (0..k).map(|i| (sigma2 * inverse[i][i]).max(0.0).sqrt()).collect();
+
+    Some(OlsFit { coefficients, std_errors, residuals })
+}
+
+/// Invert a square matrix, or `None` if it is (numerically) singular
+pub fn invert(matrix: &[Vec<f64>]) -> Option<Matrix> {
+    let n = matrix.len();
+    let scale = matrix.iter()
+        .flat_map(|row| row.iter())
+        .fold(0.0f64, |acc, x| acc.max(x.abs()));
+    if scale == 0.0 {
+        return None;
+    }
+
+    // Augment with the identity and reduce to [I | A^-1]
+    let mut aug: Matrix = matrix.iter().enumerate()
+        .map(|(i, row)| {
+            let mut r = row.clone();
+            r.extend((0..n).map(|j| if i == j { 1.0 } else { 0.0 }));
+            r
+        })
+        .collect();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| aug[a][col].abs().total_cmp(&aug[b][col].abs()))?;
+        if aug[pivot][col].abs() < 1e-12 * scale {
+            return None;
+        }
+        aug.swap(col, pivot);
+
+        let p = aug[col][col];
+        for value in aug[col].iter_mut() {
+            *value /= p;
+        }
+        for row in 0..n {
+            if row != col {
+                let factor = aug[row][col];
+                if factor != 0.0 {
+                    let pivot_row = aug[col].clone();
+                    for (value, pv) in aug[row].iter_mut().zip(&pivot_row) {
+                        *value -= factor * pv;
+                    }
+                }
+            }
+        }
+    }
+
+    Some(aug.into_iter().map(|row| row[n..].to_vec()).collect())
+}
+
+/// Transpose of a matrix
+pub fn transpose(matrix: &[Vec<f64>]) -> Matrix {
+    let cols = matrix.first().map_or(0, |row| row.len());
+    (0..cols).map(|j| matrix.iter().map(|row| row[j]).collect()).collect()
+}
+
+/// Matrix product `a * b`
+pub fn multiply(a: &[Vec<f64>], b: &[Vec<f64>]) -> Matrix {
+    let cols = b.first().map_or(0, |row| row.len());
+    a.iter()
+        .map(|row| (0..cols).map(|j| row.iter().zip(b).map(|(x, b_row)| x * b_row[j]).sum()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invert() {
+        let inverse = invert(&[vec![4.0, 7.0], vec![2.0, 6.0]]).unwrap();
+        assert!((inverse[0][0] - 0.6).abs() < 1e-12);
+        assert!((inverse[0][1] + 0.7).abs() < 1e-12);
+        assert!((inverse[1][0] + 0.2).abs() < 1e-12);
+        assert!((inverse[1][1] - 0.4).abs() < 1e-12);
+
+        assert!(invert(&[vec![1.0, 2.0], vec![2.0, 4.0]]).is_none());
+    }
+
+    #[test]
+    fn test_multiply_and_transpose() {
+        let a = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]];
+        assert_eq!(transpose(&a), vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]);
+        assert_eq!(multiply(&a, &transpose(&a)), vec![vec![14.0, 32.0], vec![32.0, 77.0]]);
+    }
+
+    #[test]
+    fn test_ols_recovers_line() {
+        let x: Vec<Vec<f64>> = (0..10).map(|t| vec![1.0, t as f64]).collect();
+        let y: Vec<f64> = (0..10).map(|t| 3.0 + 2.0 * t as f64).collect();
+        let fit = ols(&x, &y).unwrap();
+
+        assert!((fit.coefficients[0] - 3.0).abs() < 1e-10);
+        assert!((fit.coefficients[1] - 2.0).abs() < 1e-10);
+        assert!(fit.residuals.iter().all(|e| e.abs() < 1e-10));
+    }
+}
@@ -0,0 +1,104 @@
+//! Forecast accuracy metrics
+//!
+//! Used to score ARIMA and the benchmark forecasters on the same footing:
+//! hold back the last few months, fit on the rest, and compare.
+
+use serde::Serialize;
+
+/// Out-of-sample accuracy of a forecast against actuals
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AccuracyMetrics {
+    /// Mean absolute error
+    pub mae: f64,
+    /// Root mean squared error
+    pub rmse: f64,
+    /// Mean absolute percentage error (months with zero actuals are skipped)
+    pub mape: f64,
+    /// Mean absolute scaled error against the in-sample seasonal naive
+    pub mase: f64,
+}
+
+/// Score a forecast against actuals
+///
+/// `training` is the history the forecast was fitted on; it provides the
+/// seasonal naive scale for MASE (falling back to lag 1 for short series).
+/// Missing actuals (NaN) are skipped.
+pub fn accuracy(actual: &[f64], forecast: &[f64], training: &[f64], seasonal_period: usize) -> AccuracyMetrics {
+    let errors: Vec<f64> = actual.iter().zip(forecast)
+        .filter(|(a, _)| a.is_finite())
+        .map(|(&a, &f)| a - f)
+        .collect();
+    let n = errors.len().max(1) as f64;
+
+    let mae = errors.iter().map(|e| e.abs()).sum::<f64>() / n;
+    let rmse = (errors.iter().map(|e| e * e).sum::<f64>() / n).sqrt();
+
+    let pct: Vec<f64> = actual.iter().filter(|a| a.is_finite()).zip(&errors)
+        .filter(|(&a, _)| a.abs() > 1e-10)
+        .map(|(&a, &e)| (e / a).abs() * 100.0)
+        .collect();
+    let mape = if pct.is_empty() { f64::NAN } else { pct.iter().sum::<f64>() / pct.len() as f64 };
+
+    let lag = if training.len() > seasonal_period { seasonal_period } else { 1 };
+    let naive_errors: Vec<f64> = (lag.min(training.len())..training.len())
+        .map(|i| (training[i] - training[i - lag]).abs())
+        .filter(|e| e.is_finite())
+        .collect();
+    let scale = if naive_errors.is_empty() {
+        0.0
+    } else {
+        naive_errors.iter().sum::<f64>() / naive_errors.len() as f64
+    };
+    let mase = if scale > 1e-10 { mae / scale } else { f64::NAN };
+
+    AccuracyMetrics { mae, rmse, mape, mase }
+}
+
+/// Score a forecaster by holding back the last `holdout` observations
+///
+/// `forecaster` receives the training slice and the number of steps to
+/// forecast. Returns `None` if the series is too short to leave at least
+/// two seasonal cycles for training.
+pub fn holdout_accuracy<F>(series: &[f64], holdout: usize, seasonal_period: usize, forecaster: F) -> Option<AccuracyMetrics>
+where
+    F: Fn(&[f64], usize) -> Vec<f64>,
+{
+    if holdout == 0 || series.len() < holdout + 2 * seasonal_period {
+        return None;
+    }
+
+    let split = series.len() - holdout;
+    let (training, actual) = series.split_at(split);
+    let forecast = forecaster(training, holdout);
+
+    Some(accuracy(actual, &forecast, training, seasonal_period))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accuracy() {
+        let training = vec![10.0, 12.0, 14.0, 16.0];
+        let metrics = accuracy(&[20.0, 20.0], &[18.0, 22.0], &training, 12);
+
+        assert!((metrics.mae - 2.0).abs() < 1e-10);
+        assert!((metrics.rmse - 2.0).abs() < 1e-10);
+        assert!((metrics.mape - 10.0).abs() < 1e-10);
+        // Lag-1 naive scale is 2.0 for the short training series
+        assert!((metrics.mase - 1.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_holdout_accuracy() {
+        let series: Vec<f64> = (0..36).map(|i| 100.0 + i as f64).collect();
+        let metrics = holdout_accuracy(&series, 12, 12, |training, steps| {
+            vec![*training.last().unwrap(); steps]
+        }).unwrap();
+        assert!((metrics.mae - 6.5).abs() < 1e-10);
+
+        // Too short to hold anything back
+        assert!(holdout_accuracy(&series[..20], 12, 12, |_, steps| vec![0.0; steps]).is_none());
+    }
+}
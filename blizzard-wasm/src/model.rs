@@ -0,0 +1,388 @@
+//! Stateful model handle exported to JavaScript
+//!
+//! `forecast()` re-parses, refits and discards the model on every call. The
+//! scenario builder instead fits one baseline with `Model` and asks it for
+//! as many horizons and exogenous paths as it needs.
+//!
+//! ```javascript
+//! const model = new Model(JSON.stringify({ use_easter_regressor: true }));
+//! model.fit(new Float64Array(history), 2019, 1);
+//! const next12 = model.forecast(12);               // Float64Array
+//! const bands = JSON.parse(model.intervals(24, 0.95));
+//!
+//! // A month later: roll forward (or refit) and track the forecast error
+//! const { errors } = JSON.parse(model.update(new Float64Array([actual]), undefined, false));
+//!
+//! await db.put("models", model.to_bytes(), key);   // or model.to_json()
+//! const restored = Model.from_bytes(await db.get("models", key));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::arima::{fit_with_options, FitMetadata, FittedArima, ForecastOptions, OneStepError};
+use crate::error::{self, to_js_error, Error};
+use crate::outliers::{DetectedOutlier, OutlierOptions};
+use crate::snapshot::Snapshot;
+use crate::stationarity::DifferencingSuggestion;
+use crate::warnings::Warning;
+use crate::{default_confidence, default_p, default_q, default_seasonal_period, default_use_easter};
+use crate::{validate_arima, DiagnoseOutput};
+
+/// Model settings: the ARIMA fields of `ForecastInput`, all optional
+#[derive(Deserialize)]
+struct ModelConfig {
+    #[serde(default = "default_p")]
+    p: usize,
+    #[serde(default)]
+    d: Option<usize>,
+    #[serde(default = "default_q")]
+    q: usize,
+    #[serde(default = "default_seasonal_period")]
+    seasonal_period: usize,
+    #[serde(default = "default_use_easter")]
+    use_easter_regressor: bool,
+    #[serde(default = "default_confidence")]
+    confidence: f64,
+    #[serde(default)]
+    outliers: OutlierOptions,
+}
+
+/// Forecasts with interval bounds
+#[derive(Serialize)]
+struct IntervalOutput {
+    forecast: Vec<f64>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    warnings: Vec<Warning>,
+}
+
+/// Fitted parameters and fit metadata
+#[derive(Serialize)]
+struct CoefficientOutput<'a> {
+    ar_coefficients: &'a [f64],
+    ma_coefficients: &'a [f64],
+    intercept: f64,
+    easter_coefficient: f64,
+    seasonal_factors: &'a [f64],
+    outliers: &'a [DetectedOutlier],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    differencing: Option<DifferencingSuggestion>,
+    warnings: &'a [Warning],
+    fit: FitMetadata,
+}
+
+/// One-step-ahead error of a new month
+#[derive(Serialize)]
+struct DatedError {
+    year: i32,
+    month: u32,
+    #[serde(flatten)]
+    error: OneStepError,
+}
+
+/// Result of adding new months of actuals
+#[derive(Serialize)]
+struct UpdateOutput<'a> {
+    errors: Vec<DatedError>,
+    refitted: bool,
+    warnings: &'a [Warning],
+    fit: FitMetadata,
+}
+
+/// A fitted ARIMA baseline that can be forecast repeatedly
+#[wasm_bindgen]
+pub struct Model {
+    options: ForecastOptions,
+    fitted: Option<FittedArima>,
+    series_len: usize,
+}
+
+#[wasm_bindgen]
+impl Model {
+    /// Create an unfitted model from JSON settings (`p`, `d`, `q`,
+    /// `seasonal_period`, `use_easter_regressor`, `confidence`, `outliers`;
+    /// all optional, with the same defaults as `forecast`)
+    #[wasm_bindgen(constructor)]
+    pub fn new(config_json: &str) -> Result<Model, JsError> {
+        let config: ModelConfig = error::from_json(config_json).map_err(to_js_error)?;
+        if !(config.confidence > 0.0 && config.confidence < 1.0) {
+            return Err(to_js_error(Error::validation("confidence", "confidence must be between 0 and 1")));
+        }
+
+        Ok(Model {
+            options: ForecastOptions {
+                use_easter: config.use_easter_regressor,
+                outliers: config.outliers,
+                p: config.p,
+                d: config.d,
+                q: config.q,
+                seasonal_period: config.seasonal_period,
+                confidence: config.confidence,
+                regressor: None,
+            },
+            fitted: None,
+            series_len: 0,
+        })
+    }
+
+    /// Fit to a monthly series (NaN marks a missing month), replacing any
+    /// previous fit
+    pub fn fit(&mut self, series: &[f64], start_year: i32, start_month: u32) -> Result<(), JsError> {
+        let o = &self.options;
+        validate_arima(series, start_month, o.p, o.d, o.q, o.seasonal_period).map_err(to_js_error)?;
+        o.outliers.validate(start_year, start_month, series.len()).map_err(to_js_error)?;
+
+        self.fitted = Some(fit_with_options(series, start_year, start_month, &self.options));
+        self.series_len = series.len();
+        Ok(())
+    }
+
+    /// Whether `fit` has been called successfully
+    #[wasm_bindgen(getter)]
+    pub fn fitted(&self) -> bool {
+        self.fitted.is_some()
+    }
+
+    /// Point forecasts for `steps` months
+    ///
+    /// `exog` overrides the future Easter regressor (one value per step);
+    /// by default it comes from the calendar.
+    pub fn forecast(&self, steps: usize, exog: Option<Vec<f64>>) -> Result<Vec<f64>, JsError> {
+        let fitted = self.fitted_model().map_err(to_js_error)?;
+        check_exog(exog.as_deref(), steps).map_err(to_js_error)?;
+        Ok(fitted.forecast(steps, self.options.confidence, exog.as_deref()).forecast)
+    }
+
+    /// Forecasts with interval bounds as JSON `{forecast, lower, upper, warnings}`
+    ///
+    /// `confidence` defaults to the model's setting.
+    pub fn intervals(&self, steps: usize, confidence: Option<f64>, exog: Option<Vec<f64>>) -> Result<String, JsError> {
+        let fitted = self.fitted_model().map_err(to_js_error)?;
+        check_exog(exog.as_deref(), steps).map_err(to_js_error)?;
+
+        let confidence = confidence.unwrap_or(self.options.confidence);
+        let result = fitted.forecast(steps, confidence, exog.as_deref());
+        let mut warnings: Vec<Warning> = crate::intervals::confidence_warning(confidence).into_iter().collect();
+        warnings.extend(result.warnings);
+
+        let output = IntervalOutput { forecast: result.forecast, lower: result.lower, upper: result.upper, warnings };
+        output_json(&output)
+    }
+
+    /// Fitted coefficients, seasonal factors, interventions and fit metadata as JSON
+    pub fn coefficients(&self) -> Result<String, JsError> {
+        let fitted = self.fitted_model().map_err(to_js_error)?;
+        let model = &fitted.model;
+
+        let output = CoefficientOutput {
+            ar_coefficients: model.ar_coefficients(),
+            ma_coefficients: model.ma_coefficients(),
+            intercept: model.intercept(),
+            easter_coefficient: fitted.easter_coefficient(),
+            seasonal_factors: model.seasonal_factors(),
+            outliers: &fitted.outliers,
+            differencing: fitted.differencing,
+            warnings: model.warnings(),
+            fit: fitted.fit_metadata(),
+        };
+        output_json(&output)
+    }
+
+    /// Residual diagnostics as JSON (same shape as `diagnose`)
+    pub fn diagnostics(&self, lags: Option<usize>) -> Result<String, JsError> {
+        let fitted = self.fitted_model().map_err(to_js_error)?;
+        let output = DiagnoseOutput::new(&fitted.model, self.series_len, lags);
+        output_json(&output)
+    }
+
+    /// Append new months of actuals (NaN marks a missing month)
+    ///
+    /// Returns JSON `{errors, refitted, warnings, fit}` where `errors` holds
+    /// the one-step-ahead error of each new month. Parameters stay fixed and
+    /// the state is rolled forward unless `refit` is true, in which case they
+    /// are re-estimated on the extended history (keeping the differencing
+    /// order and interventions). `new_exog` overrides the calendar Easter
+    /// regressor for the new months.
+    pub fn update(&mut self, new_observations: &[f64], new_exog: Option<Vec<f64>>, refit: Option<bool>) -> Result<String, JsError> {
+        check_exog(new_exog.as_deref(), new_observations.len()).map_err(to_js_error)?;
+        let refit = refit.unwrap_or(false);
+        let fitted = self.fitted.as_mut()
+            .ok_or_else(|| to_js_error(not_fitted()))?;
+
+        let errors = fitted.update(new_observations, new_exog.as_deref(), refit)
+            .into_iter()
+            .map(|error| {
+                let (year, month) = fitted.date_of(error.index);
+                DatedError { year, month, error }
+            })
+            .collect();
+        self.series_len += new_observations.len();
+
+        let output = UpdateOutput { errors, refitted: refit, warnings: fitted.model.warnings(), fit: fitted.fit_metadata() };
+        output_json(&output)
+    }
+
+    /// Versioned JSON snapshot of the settings and fitted state
+    pub fn to_json(&self) -> Result<String, JsError> {
+        self.snapshot().to_json().map_err(to_js_error)
+    }
+
+    /// Versioned binary snapshot (smaller than the JSON form)
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        self.snapshot().to_bytes().map_err(to_js_error)
+    }
+
+    /// Restore a model from `to_json` output without refitting
+    pub fn from_json(json: &str) -> Result<Model, JsError> {
+        Snapshot::from_json(json).map(Model::restore).map_err(to_js_error)
+    }
+
+    /// Restore a model from `to_bytes` output without refitting
+    pub fn from_bytes(bytes: &[u8]) -> Result<Model, JsError> {
+        Snapshot::from_bytes(bytes).map(Model::restore).map_err(to_js_error)
+    }
+}
+
+impl Model {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self.options.clone(), self.fitted.as_ref().map(FittedArima::state))
+    }
+
+    fn restore(snapshot: Snapshot) -> Model {
+        let series_len = snapshot.fitted.as_ref().map_or(0, |f| f.model.series.len());
+        let fitted = snapshot.fitted.map(FittedArima::from_state);
+        Model { options: snapshot.options, fitted, series_len }
+    }
+
+    fn fitted_model(&self) -> error::Result<&FittedArima> {
+        self.fitted.as_ref().ok_or_else(not_fitted)
+    }
+}
+
+fn not_fitted() -> Error {
+    Error::validation("model", "Model has not been fitted; call fit() first")
+}
+
+/// Serialize an output, reporting failure in the stable error schema
+fn output_json<T: Serialize>(output: &T) -> Result<String, JsError> {
+    serde_json::to_string(output)
+        .map_err(|e| to_js_error(Error::Numerical(format!("Failed to serialize output: {}", e))))
+}
+
+fn check_exog(exog: Option<&[f64]>, steps: usize) -> error::Result<()> {
+    match exog {
+        Some(values) if values.len() < steps => Err(Error::validation(
+            "exog",
+            format!("exog has {} values but {} steps were requested", values.len(), steps),
+        )),
+        Some(values) if values.iter().any(|&x| x != 0.0 && x != 1.0) => {
+            Err(Error::validation("exog", "exog values must be 0 or 1"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series() -> Vec<f64> {
+        (0..48).map(|i| 1000.0 + 10.0 * i as f64 + 100.0 * ((i % 12) as f64).sin()).collect()
+    }
+
+    #[test]
+    fn test_model_matches_one_shot_forecast() {
+        let mut model = Model::new(r#"{"d": 1}"#).unwrap();
+        assert!(!model.fitted());
+        assert!(model.fitted_model().is_err());
+
+        model.fit(&series(), 2020, 1).unwrap();
+        assert!(model.fitted());
+
+        let options = ForecastOptions::default();
+        let expected = crate::arima::fit_and_forecast_with_options(&series(), 2020, 1, 12, &options);
+        assert_eq!(model.forecast(12, None).unwrap(), expected.forecast);
+
+        // Longer horizons reuse the same fit
+        let longer = model.forecast(24, None).unwrap();
+        assert_eq!(&longer[..12], &expected.forecast[..]);
+
+        let bands: serde_json::Value = serde_json::from_str(&model.intervals(12, None, None).unwrap()).unwrap();
+        assert_eq!(bands["lower"][0].as_f64().unwrap(), expected.lower[0]);
+    }
+
+    #[test]
+    fn test_exog_path_overrides_easter() {
+        let mut model = Model::new("{}").unwrap();
+        model.fit(&series(), 2020, 1).unwrap();
+
+        let without = model.forecast(3, Some(vec![0.0; 3])).unwrap();
+        let with = model.forecast(3, Some(vec![1.0; 3])).unwrap();
+        let coefficient: serde_json::Value = serde_json::from_str(&model.coefficients().unwrap()).unwrap();
+        let easter = coefficient["easter_coefficient"].as_f64().unwrap();
+        assert!(((with[0] - without[0]) - easter).abs() < 1e-9);
+
+        assert!(check_exog(Some(&[1.0]), 3).is_err());
+        assert!(check_exog(Some(&[0.0, 1.0, 0.5]), 3).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_reproduces_forecasts() {
+        let mut model = Model::new(r#"{"outliers": {"detect": true}}"#).unwrap();
+        let mut history = series();
+        history[30] += 400.0;
+        model.fit(&history, 2020, 1).unwrap();
+
+        let json = Model::from_json(&model.to_json().unwrap()).unwrap();
+        let bytes = Model::from_bytes(&model.to_bytes().unwrap()).unwrap();
+        for restored in [json, bytes] {
+            assert_eq!(restored.forecast(18, None).unwrap(), model.forecast(18, None).unwrap());
+            assert_eq!(restored.intervals(18, Some(0.95), None).unwrap(), model.intervals(18, Some(0.95), None).unwrap());
+            assert_eq!(restored.coefficients().unwrap(), model.coefficients().unwrap());
+            assert_eq!(restored.diagnostics(None).unwrap(), model.diagnostics(None).unwrap());
+        }
+
+        // Unfitted models round-trip too
+        let unfitted = Model::from_json(&Model::new("{}").unwrap().to_json().unwrap()).unwrap();
+        assert!(!unfitted.fitted());
+    }
+
+    #[test]
+    fn test_update_reports_one_step_errors() {
+        let history = series();
+        let mut model = Model::new("{}").unwrap();
+        model.fit(&history[..36], 2020, 1).unwrap();
+        let expected = model.forecast(1, None).unwrap()[0];
+
+        let output: serde_json::Value = serde_json::from_str(&model.update(&history[36..40], None, None).unwrap()).unwrap();
+        let errors = output["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 4);
+        assert_eq!((errors[0]["year"].as_i64(), errors[0]["month"].as_u64()), (Some(2023), Some(1)));
+        assert!((errors[0]["forecast"].as_f64().unwrap() - expected).abs() < 1e-9);
+        assert_eq!(output["refitted"], false);
+        assert_eq!(model.series_len, 40);
+
+        // Forecasts now start after the new months
+        assert_ne!(model.forecast(1, None).unwrap()[0], expected);
+
+        let output: serde_json::Value = serde_json::from_str(&model.update(&history[40..], None, Some(true)).unwrap()).unwrap();
+        assert_eq!(output["refitted"], true);
+        assert_eq!(output["errors"].as_array().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_coefficients_and_diagnostics() {
+        let mut model = Model::new(r#"{"p": 1, "q": 0, "d": 1}"#).unwrap();
+        model.fit(&series(), 2020, 1).unwrap();
+
+        let coefficients: serde_json::Value = serde_json::from_str(&model.coefficients().unwrap()).unwrap();
+        assert_eq!(coefficients["ar_coefficients"].as_array().unwrap().len(), 1);
+        assert!(coefficients["ma_coefficients"].as_array().unwrap().is_empty());
+        assert_eq!(coefficients["fit"]["n_obs"], 47);
+
+        let diagnostics: serde_json::Value = serde_json::from_str(&model.diagnostics(Some(6)).unwrap()).unwrap();
+        assert_eq!(diagnostics["residual_start"], 1);
+        assert_eq!(diagnostics["acf"].as_array().unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,475 @@
+//! Outlier detection and intervention regressors
+//!
+//! Iterative detection in the style of Chen & Liu (1993), as used by
+//! tsoutliers and TRAMO. Each candidate event is mapped through the fitted
+//! model's pi-weights to the effect it would have on the one-step residuals;
+//! the event with the largest t-statistic above the critical value is removed
+//! from the series and the model refitted, until nothing significant remains.
+//!
+//! Effects are additive in the original units, like the Easter regressor:
+//! - additive outlier: a one-month pulse
+//! - level shift: a permanent step from the event month onwards
+//! - temporary change: a step decaying geometrically (rate 0.7)
+
+use serde::{Deserialize, Serialize};
+
+use crate::arima::Arima;
+use crate::easter::month_offset;
+use crate::error::{Error, Result};
+
+/// Decay rate of a temporary change per month
+const TC_DECAY: f64 = 0.7;
+
+/// Type of intervention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierKind {
+    /// One-month pulse (e.g. a customer's bulk order)
+    Additive,
+    /// Permanent change in level
+    LevelShift,
+    /// Shock that decays over the following months
+    TemporaryChange,
+}
+
+impl OutlierKind {
+    /// Effect of a unit intervention at `at`, evaluated at `index`
+    pub fn pattern(&self, at: usize, index: usize) -> f64 {
+        if index < at {
+            return 0.0;
+        }
+        match self {
+            OutlierKind::Additive => if index == at { 1.0 } else { 0.0 },
+            OutlierKind::LevelShift => 1.0,
+            OutlierKind::TemporaryChange => TC_DECAY.powi((index - at) as i32),
+        }
+    }
+}
+
+/// An intervention pinned to a calendar month
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct InterventionSpec {
+    /// Year of the event
+    pub year: i32,
+    /// Month of the event (1-12)
+    pub month: u32,
+    /// Type of event; forced events default to additive, suppressions
+    /// without a kind cover every type
+    #[serde(default)]
+    pub kind: Option<OutlierKind>,
+}
+
+/// Outlier detection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlierOptions {
+    /// Search for outliers automatically (default: false)
+    #[serde(default)]
+    pub detect: bool,
+    /// |t| above which an event is accepted (default: 3.5)
+    #[serde(default = "default_critical_value")]
+    pub critical_value: f64,
+    /// Event types to search for (default: all)
+    #[serde(default = "default_kinds")]
+    pub kinds: Vec<OutlierKind>,
+    /// Maximum number of detected events (default: 10)
+    #[serde(default = "default_max_outliers")]
+    pub max_outliers: usize,
+    /// Interventions always included, whether significant or not
+    #[serde(default)]
+    pub force: Vec<InterventionSpec>,
+    /// Months (and optionally types) never reported as outliers
+    #[serde(default)]
+    pub suppress: Vec<InterventionSpec>,
+}
+
+fn default_critical_value() -> f64 { 3.5 }
+fn default_kinds() -> Vec<OutlierKind> {
+    vec![OutlierKind::Additive, OutlierKind::LevelShift, OutlierKind::TemporaryChange]
+}
+fn default_max_outliers() -> usize { 10 }
+
+impl Default for OutlierOptions {
+    fn default() -> Self {
+        OutlierOptions {
+            detect: false,
+            critical_value: default_critical_value(),
+            kinds: default_kinds(),
+            max_outliers: default_max_outliers(),
+            force: vec![],
+            suppress: vec![],
+        }
+    }
+}
+
+impl OutlierOptions {
+    /// Check that forced and suppressed events fall within the series
+    pub fn validate(&self, start_year: i32, start_month: u32, n: usize) -> Result<()> {
+        for (list, specs) in [("force", &self.force), ("suppress", &self.suppress)] {
+            for (i, spec) in specs.iter().enumerate() {
+                let field = format!("outliers.{}[{}]", list, i);
+                if !(1..=12).contains(&spec.month) {
+                    return Err(Error::validation(&field, "Event month must be between 1 and 12"));
+                }
+                if event_index(spec, start_year, start_month, n).is_none() {
+                    return Err(Error::validation(
+                        &field,
+                        format!("{}-{:02} is outside the series", spec.year, spec.month),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A detected or forced intervention
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedOutlier {
+    /// Type of event
+    pub kind: OutlierKind,
+    /// Position in the series (0-based)
+    pub index: usize,
+    /// Year of the event
+    pub year: i32,
+    /// Month of the event (1-12)
+    pub month: u32,
+    /// Estimated effect in original units
+    pub magnitude: f64,
+    /// t-statistic at the time the event was added
+    pub t_stat: f64,
+    /// Whether the event was forced by the user
+    pub forced: bool,
+}
+
+/// Model orders used while searching for outliers
+#[derive(Debug, Clone, Copy)]
+pub struct SearchModel {
+    pub p: usize,
+    pub d: usize,
+    pub q: usize,
+    pub seasonal_period: usize,
+}
+
+/// Find interventions for a series
+///
+/// `exog` is passed through to the model fits (e.g. the Easter regressor) so
+/// known effects are not mistaken for outliers.
+pub fn detect_outliers(
+    series: &[f64],
+    exog: Option<&[f64]>,
+    start_year: i32,
+    start_month: u32,
+    model: SearchModel,
+    options: &OutlierOptions,
+) -> Vec<DetectedOutlier> {
+    let n = series.len();
+    let index_of = |spec: &InterventionSpec| event_index(spec, start_year, start_month, n);
+
+    let mut adjusted = series.to_vec();
+    let mut found: Vec<DetectedOutlier> = vec![];
+    let event_at = |kind: OutlierKind, index: usize, magnitude: f64, t_stat: f64, forced: bool| {
+        let (year, month) = month_offset(start_year, start_month, index);
+        DetectedOutlier { kind, index, year, month, magnitude, t_stat, forced }
+    };
+
+    // Forced interventions first, each estimated given the ones before it
+    for spec in &options.force {
+        if let Some(index) = index_of(spec) {
+            let kind = spec.kind.unwrap_or(OutlierKind::Additive);
+            let fit = fit_search_model(&adjusted, exog, model);
+            let (magnitude, t_stat) = fit.estimate(kind, index);
+            let event = event_at(kind, index, magnitude, t_stat, true);
+            remove_effect(&mut adjusted, &event);
+            found.push(event);
+        }
+    }
+
+    if !options.detect {
+        found.sort_by_key(|o| o.index);
+        return found;
+    }
+
+    let suppressed: Vec<(usize, Option<OutlierKind>)> = options.suppress.iter()
+        .filter_map(|spec| index_of(spec).map(|i| (i, spec.kind)))
+        .collect();
+    let is_allowed = |kind: OutlierKind, index: usize, found: &[DetectedOutlier]| {
+        !suppressed.iter().any(|&(i, k)| i == index && k.is_none_or(|k| k == kind))
+            && !found.iter().any(|o| o.index == index)
+    };
+
+    for _ in 0..options.max_outliers {
+        let fit = fit_search_model(&adjusted, exog, model);
+
+        // Level shifts at the last observation are indistinguishable from pulses
+        let mut best: Option<(OutlierKind, usize, f64, f64)> = None;
+        for (index, value) in adjusted.iter().enumerate().skip(model.d) {
+            if !value.is_finite() {
+                continue;
+            }
+            for &kind in &options.kinds {
+                if kind != OutlierKind::Additive && index + 1 >= n {
+                    continue;
+                }
+                if !is_allowed(kind, index, &found) {
+                    continue;
+                }
+                let (magnitude, t_stat) = fit.estimate(kind, index);
+                if best.is_none_or(|b| t_stat.abs() > b.3.abs()) {
+                    best = Some((kind, index, magnitude, t_stat));
+                }
+            }
+        }
+
+        match best {
+            Some((kind, index, magnitude, t_stat)) if t_stat.abs() >= options.critical_value => {
+                let event = event_at(kind, index, magnitude, t_stat, false);
+                remove_effect(&mut adjusted, &event);
+                found.push(event);
+            }
+            _ => break,
+        }
+    }
+
+    // Re-estimate the effects against the cleaned model: with parameters held
+    // fixed, residuals are linear in the effect sizes, so each pass corrects
+    // the magnitudes found while earlier events still contaminated the fit
+    for _ in 0..2 {
+        let fit = fit_search_model(&adjusted, exog, model);
+        for event in found.iter_mut() {
+            let (correction, _) = fit.estimate(event.kind, event.index);
+            let correction_event = DetectedOutlier { magnitude: correction, ..event.clone() };
+            remove_effect(&mut adjusted, &correction_event);
+            event.magnitude += correction;
+            if !event.forced {
+                event.t_stat = event.magnitude * fit.precision(event.kind, event.index);
+            }
+        }
+    }
+
+    found.sort_by_key(|o| o.index);
+    found
+}
+
+/// Combined effect of interventions over `len` months starting at `offset`
+///
+/// Use offset 0 for the history and the series length for the forecast horizon.
+pub fn intervention_effects(outliers: &[DetectedOutlier], offset: usize, len: usize) -> Vec<f64> {
+    (offset..offset + len)
+        .map(|t| outliers.iter().map(|o| o.magnitude * o.kind.pattern(o.index, t)).sum())
+        .collect()
+}
+
+fn remove_effect(series: &mut [f64], event: &DetectedOutlier) {
+    for (t, y) in series.iter_mut().enumerate() {
+        *y -= event.magnitude * event.kind.pattern(event.index, t);
+    }
+}
+
+/// Position of an event in a series of `n` months, if it falls within it
+fn event_index(spec: &InterventionSpec, start_year: i32, start_month: u32, n: usize) -> Option<usize> {
+    let months = (spec.year - start_year) * 12 + spec.month as i32 - start_month as i32;
+    usize::try_from(months).ok().filter(|&i| i < n)
+}
+
+// ============================================================================
+// Residual-domain estimation
+// ============================================================================
+
+/// Fitted model quantities needed to score candidate events
+struct SearchFit {
+    residuals: Vec<f64>,
+    residual_start: usize,
+    pi_weights: Vec<f64>,
+    seasonal_factors: Vec<f64>,
+    sigma: f64,
+}
+
+fn fit_search_model(series: &[f64], exog: Option<&[f64]>, model: SearchModel) -> SearchFit {
+    let mut arima = Arima::new(model.p, model.d, model.q, model.seasonal_period);
+    arima.fit_with_exog(series, exog);
+
+    let residuals = arima.residuals().to_vec();
+    let finite: Vec<f64> = residuals.iter().copied().filter(|r| r.is_finite()).collect();
+
+    SearchFit {
+        residual_start: series.len() - residuals.len(),
+        pi_weights: pi_weights(arima.ar_coefficients(), arima.ma_coefficients(), model.d, series.len()),
+        seasonal_factors: arima.seasonal_factors().to_vec(),
+        sigma: 1.4826 * median_absolute_deviation(&finite),
+        residuals,
+    }
+}
+
+impl SearchFit {
+    /// Least-squares effect size and t-statistic of an event at `at`
+    fn estimate(&self, kind: OutlierKind, at: usize) -> (f64, f64) {
+        let (exy, xx) = self.response(kind, at);
+        if xx < 1e-12 || self.sigma < 1e-12 {
+            return (0.0, 0.0);
+        }
+        let omega = exy / xx;
+        (omega, omega * xx.sqrt() / self.sigma)
+    }
+
+    /// t-statistic per unit of effect for an event at `at`
+    fn precision(&self, kind: OutlierKind, at: usize) -> f64 {
+        let (_, xx) = self.response(kind, at);
+        if self.sigma < 1e-12 { 0.0 } else { xx.sqrt() / self.sigma }
+    }
+
+    /// Cross-product of residuals with the event's residual response, and the
+    /// response's sum of squares
+    fn response(&self, kind: OutlierKind, at: usize) -> (f64, f64) {
+        let n = self.residual_start + self.residuals.len();
+        let period = self.seasonal_factors.len().max(1);
+
+        // Intervention on the deseasonalized scale
+        let u: Vec<f64> = (at..n)
+            .map(|t| {
+                let factor = self.seasonal_factors.get(t % period).copied().unwrap_or(1.0);
+                kind.pattern(at, t) / if factor > 0.0 { factor } else { 1.0 }
+            })
+            .collect();
+
+        // Response of the residuals: x_t = sum_j pi_j u_{t-j}
+        let (mut exy, mut xx) = (0.0, 0.0);
+        for t in at.max(self.residual_start)..n {
+            let e = self.residuals[t - self.residual_start];
+            if !e.is_finite() {
+                continue;
+            }
+            let x: f64 = (0..=t - at).map(|j| self.pi_weights[j] * u[t - at - j]).sum();
+            exy += e * x;
+            xx += x * x;
+        }
+
+        (exy, xx)
+    }
+}
+
+/// Coefficients of pi(B) = phi(B) (1 - B)^d / theta(B), the model's AR(inf) form
+fn pi_weights(ar: &[f64], ma: &[f64], d: usize, len: usize) -> Vec<f64> {
+    let mut numerator = vec![1.0];
+    numerator.extend(ar.iter().map(|&phi| -phi));
+    for _ in 0..d {
+        let mut next = vec![0.0; numerator.len() + 1];
+        for (i, &c) in numerator.iter().enumerate() {
+            next[i] += c;
+            next[i + 1] -= c;
+        }
+        numerator = next;
+    }
+
+    let mut pi = vec![0.0; len];
+    for k in 0..len {
+        let mut value = numerator.get(k).copied().unwrap_or(0.0);
+        for (j, &theta) in ma.iter().enumerate() {
+            if k > j {
+                value -= theta * pi[k - j - 1];
+            }
+        }
+        pi[k] = value;
+    }
+    pi
+}
+
+fn median_absolute_deviation(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let median = |v: &mut Vec<f64>| {
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mid = v.len() / 2;
+        if v.len().is_multiple_of(2) { (v[mid - 1] + v[mid]) / 2.0 } else { v[mid] }
+    };
+    let mut sorted = values.to_vec();
+    let center = median(&mut sorted);
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+    median(&mut deviations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: SearchModel = SearchModel { p: 2, d: 1, q: 1, seasonal_period: 12 };
+
+    fn noisy_series(n: usize) -> Vec<f64> {
+        // Deterministic pseudo-noise so the test is reproducible
+        (0..n)
+            .map(|i| {
+                let noise = ((i * 7919) % 101) as f64 / 101.0 - 0.5;
+                1000.0 + i as f64 + 200.0 * noise
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pi_weights() {
+        // ARIMA(1,1,0): (1 - phi B)(1 - B) = 1 - (1 + phi) B + phi B^2
+        let pi = pi_weights(&[0.5], &[], 1, 4);
+        assert_eq!(pi, vec![1.0, -1.5, 0.5, 0.0]);
+
+        // MA(1) inverts to alternating geometric weights
+        let pi = pi_weights(&[], &[0.5], 0, 3);
+        assert_eq!(pi, vec![1.0, -0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_detects_additive_outlier() {
+        let mut series = noisy_series(60);
+        series[30] += 2000.0;
+
+        let options = OutlierOptions { detect: true, ..OutlierOptions::default() };
+        let found = detect_outliers(&series, None, 2019, 1, MODEL, &options);
+
+        let event = found.iter().find(|o| o.index == 30).expect("outlier at index 30");
+        assert_eq!(event.kind, OutlierKind::Additive);
+        assert_eq!((event.year, event.month), (2021, 7));
+        assert!((event.magnitude - 2000.0).abs() < 300.0);
+    }
+
+    #[test]
+    fn test_detects_level_shift() {
+        let mut series = noisy_series(60);
+        for value in series.iter_mut().skip(36) {
+            *value -= 1500.0;
+        }
+
+        let options = OutlierOptions { detect: true, ..OutlierOptions::default() };
+        let found = detect_outliers(&series, None, 2019, 1, MODEL, &options);
+        assert!(found.iter().any(|o| o.kind == OutlierKind::LevelShift && o.index == 36));
+
+        let future = intervention_effects(&found, 60, 3);
+        assert!(future.iter().all(|&e| e < -1000.0));
+    }
+
+    #[test]
+    fn test_force_and_suppress() {
+        let mut series = noisy_series(60);
+        series[30] += 2000.0;
+
+        let options = OutlierOptions {
+            detect: true,
+            force: vec![InterventionSpec { year: 2020, month: 3, kind: Some(OutlierKind::LevelShift) }],
+            suppress: vec![InterventionSpec { year: 2021, month: 7, kind: None }],
+            ..OutlierOptions::default()
+        };
+        let found = detect_outliers(&series, None, 2019, 1, MODEL, &options);
+
+        assert!(found.iter().any(|o| o.forced && o.index == 14 && o.kind == OutlierKind::LevelShift));
+        assert!(!found.iter().any(|o| o.index == 30));
+    }
+
+    #[test]
+    fn test_events_must_fall_within_the_series() {
+        let event = |year, month| InterventionSpec { year, month, kind: None };
+        let options = OutlierOptions { force: vec![event(2019, 3), event(2024, 1)], ..OutlierOptions::default() };
+        let err = options.validate(2019, 1, 60).unwrap_err();
+        assert_eq!(err.field(), Some("outliers.force[1]"));
+
+        let options = OutlierOptions { suppress: vec![event(2020, 13)], ..OutlierOptions::default() };
+        assert_eq!(options.validate(2019, 1, 60).unwrap_err().field(), Some("outliers.suppress[0]"));
+        assert!(OutlierOptions { force: vec![event(2023, 12)], ..OutlierOptions::default() }.validate(2019, 1, 60).is_ok());
+    }
+}
@@ -0,0 +1,210 @@
+//! Lag-polynomial root checks for AR and MA coefficients
+//!
+//! An AR polynomial 1 - φ₁B - … - φₚBᵖ is stationary, and an MA polynomial
+//! 1 + θ₁B + … + θ_qB^q invertible, when all of its roots lie outside the
+//! unit circle. Roots are found with the Durand-Kerner iteration, which is
+//! plenty for the low orders used here.
+
+/// Smallest root modulus allowed after correction (roots at >= 1/0.99)
+pub const ROOT_MARGIN: f64 = 0.99;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+
+    fn div(self, other: Complex) -> Complex {
+        let denom = other.re * other.re + other.im * other.im;
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denom,
+            (self.im * other.re - self.re * other.im) / denom,
+        )
+    }
+
+    fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// 1 / conj(z): the reflection of z through the unit circle
+    fn reflect(self) -> Complex {
+        let n2 = self.re * self.re + self.im * self.im;
+        Complex::new(self.re / n2, self.im / n2)
+    }
+}
+
+/// Roots of c₀ + c₁z + … + cₙzⁿ (trailing zero coefficients are dropped)
+fn roots(coeffs: &[f64]) -> Vec<Complex> {
+    let degree = match coeffs.iter().rposition(|c| c.abs() > 1e-14) {
+        Some(d) if d > 0 => d,
+        _ => return vec![],
+    };
+    let lead = coeffs[degree];
+    let monic: Vec<f64> = coeffs[..=degree].iter().map(|c| c / lead).collect();
+
+    let eval = |z: Complex| {
+        monic.iter().rev().fold(Complex::new(0.0, 0.0), |acc, &c| acc.mul(z).add(Complex::new(c, 0.0)))
+    };
+
+    // Standard starting points on a spiral
+    let seed = Complex::new(0.4, 0.9);
+    let mut z: Vec<Complex> = (0..degree)
+        .scan(Complex::new(1.0, 0.0), |power, _| {
+            let current = *power;
+            *power = power.mul(seed);
+            Some(current)
+        })
+        .collect();
+
+    for _ in 0..500 {
+        let mut max_step: f64 = 0.0;
+        for i in 0..degree {
+            let denom = (0..degree)
+                .filter(|&j| j != i)
+                .fold(Complex::new(1.0, 0.0), |acc, j| acc.mul(z[i].sub(z[j])));
+            let step = eval(z[i]).div(denom);
+            if step.re.is_finite() && step.im.is_finite() {
+                z[i] = z[i].sub(step);
+                max_step = max_step.max(step.norm());
+            }
+        }
+        if max_step < 1e-13 {
+            break;
+        }
+    }
+    z
+}
+
+/// Polynomial coefficients c₀.. of Π (1 - z / rᵢ)
+fn from_roots(roots: &[Complex]) -> Vec<f64> {
+    let mut poly = vec![Complex::new(1.0, 0.0)];
+    for &r in roots {
+        let factor = Complex::new(-1.0, 0.0).div(r);
+        let mut next = vec![Complex::new(0.0, 0.0); poly.len() + 1];
+        for (k, &c) in poly.iter().enumerate() {
+            next[k] = next[k].add(c);
+            next[k + 1] = next[k + 1].add(c.mul(factor));
+        }
+        poly = next;
+    }
+    poly.iter().map(|c| c.re).collect()
+}
+
+/// Smallest root modulus of the AR polynomial 1 - Σ φⱼBʲ (infinity if none)
+pub fn ar_min_root(ar: &[f64]) -> f64 {
+    let mut coeffs = vec![1.0];
+    coeffs.extend(ar.iter().map(|a| -a));
+    min_modulus(&roots(&coeffs))
+}
+
+/// Smallest root modulus of the MA polynomial 1 + Σ θⱼBʲ (infinity if none)
+pub fn ma_min_root(ma: &[f64]) -> f64 {
+    let mut coeffs = vec![1.0];
+    coeffs.extend_from_slice(ma);
+    min_modulus(&roots(&coeffs))
+}
+
+fn min_modulus(roots: &[Complex]) -> f64 {
+    roots.iter().map(|r| r.norm()).fold(f64::INFINITY, f64::min)
+}
+
+/// Shrink AR coefficients so every root lies at least 1/ROOT_MARGIN from
+/// the origin, or `None` if they already do
+///
+/// φⱼ → φⱼcʲ scales every root by 1/c, which keeps the shape of the
+/// polynomial while pulling it back inside the stationary region.
+pub fn enforce_stationarity(ar: &[f64]) -> Option<Vec<f64>> {
+    let min_root = ar_min_root(ar);
+    if min_root * ROOT_MARGIN >= 1.0 {
+        return None;
+    }
+    Some(scale_roots(ar, min_root * ROOT_MARGIN))
+}
+
+/// Make MA coefficients invertible, or `None` if they already are
+///
+/// Roots inside the unit circle are reflected to 1/conj(r), which gives the
+/// invertible model with the same autocorrelations; roots left on or near
+/// the circle are then shrunk as for the AR side.
+pub fn enforce_invertibility(ma: &[f64]) -> Option<Vec<f64>> {
+    if ma_min_root(ma) * ROOT_MARGIN >= 1.0 {
+        return None;
+    }
+
+    let mut coeffs = vec![1.0];
+    coeffs.extend_from_slice(ma);
+    let reflected: Vec<Complex> = roots(&coeffs).into_iter()
+        .map(|r| if r.norm() < 1.0 { r.reflect() } else { r })
+        .collect();
+    let mut theta: Vec<f64> = from_roots(&reflected)[1..].to_vec();
+    theta.resize(ma.len(), 0.0);
+
+    let min_root = ma_min_root(&theta);
+    if min_root * ROOT_MARGIN < 1.0 {
+        theta = scale_roots(&theta, min_root * ROOT_MARGIN);
+    }
+    Some(theta)
+}
+
+/// cⱼ → cⱼ scaleʲ
+fn scale_roots(coeffs: &[f64], scale: f64) -> Vec<f64> {
+    coeffs.iter().enumerate().map(|(j, c)| c * scale.powi(j as i32 + 1)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_moduli() {
+        // 1 - 0.5B has its root at 2
+        assert!((ar_min_root(&[0.5]) - 2.0).abs() < 1e-10);
+        // 1 - 1.5B + 0.56B² = (1 - 0.7B)(1 - 0.8B): roots 1/0.7, 1/0.8
+        assert!((ar_min_root(&[1.5, -0.56]) - 1.25).abs() < 1e-10);
+        // Complex pair: 1 + 0.81B² has roots ±i/0.9
+        assert!((ma_min_root(&[0.0, 0.81]) - 1.0 / 0.9).abs() < 1e-10);
+        assert_eq!(ar_min_root(&[]), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_enforce_stationarity() {
+        assert!(enforce_stationarity(&[0.5, 0.2]).is_none());
+
+        let corrected = enforce_stationarity(&[1.2]).unwrap();
+        assert!((corrected[0] - 0.99).abs() < 1e-10);
+
+        // Explosive AR(2) is pulled back to the margin
+        let corrected = enforce_stationarity(&[1.3, -0.2]).unwrap();
+        assert!((ar_min_root(&corrected) - 1.0 / ROOT_MARGIN).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_enforce_invertibility_reflects_roots() {
+        // 1 + 2B is the non-invertible twin of 1 + 0.5B
+        let corrected = enforce_invertibility(&[2.0]).unwrap();
+        assert!((corrected[0] - 0.5).abs() < 1e-10);
+
+        // A unit root cannot be reflected away, so it is shrunk
+        let corrected = enforce_invertibility(&[-1.0]).unwrap();
+        assert!((corrected[0] + ROOT_MARGIN).abs() < 1e-8);
+
+        assert!(enforce_invertibility(&[0.3]).is_none());
+    }
+}
@@ -0,0 +1,251 @@
+//! New-business ramp profiles
+//!
+//! A profile spreads three annual totals over 36 months from the start
+//! month. Each twelve-month year gets exactly its total; within a year the
+//! months are weighted by the ramp curve times the seasonal factors.
+//!
+//! The ramp curve is the run rate over the first `months` months, rising to
+//! full rate and staying there:
+//! - linear: a straight line from `start` (default 0.5) of full rate
+//! - s_curve: logistic, centred at `midpoint` of the ramp (default 0.5) with
+//!   `steepness` (default 10)
+//! - exponential: geometric growth from `start` (default 0.1) of full rate
+//! - step: nothing until month `at` (counting from 0), full rate after
+//! - custom: the given monthly `weights`, then full rate
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{from_json, Error, Result};
+
+/// Length of a profile
+pub const PROFILE_MONTHS: usize = 36;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Ramp curve shape, tagged by `shape`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum RampShape {
+    Linear {
+        #[serde(default = "default_linear_start")]
+        start: f64,
+    },
+    SCurve {
+        #[serde(default = "default_midpoint")]
+        midpoint: f64,
+        #[serde(default = "default_steepness")]
+        steepness: f64,
+    },
+    Exponential {
+        #[serde(default = "default_exponential_start")]
+        start: f64,
+    },
+    Step { at: usize },
+    Custom { weights: Vec<f64> },
+}
+
+/// Ramp curve and how many months it takes to reach full rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ramp {
+    #[serde(flatten)]
+    pub shape: RampShape,
+    /// Months to full rate (default: 12; ignored by custom)
+    #[serde(default = "default_ramp_months")]
+    pub months: usize,
+}
+
+impl Default for Ramp {
+    fn default() -> Self {
+        Ramp { shape: RampShape::Linear { start: default_linear_start() }, months: default_ramp_months() }
+    }
+}
+
+fn default_linear_start() -> f64 { 0.5 }
+fn default_midpoint() -> f64 { 0.5 }
+fn default_steepness() -> f64 { 10.0 }
+fn default_exponential_start() -> f64 { 0.1 }
+fn default_ramp_months() -> usize { 12 }
+
+/// Input to the `ramp_profile` export
+#[derive(Deserialize)]
+pub struct RampInput {
+    pub year1_value: f64,
+    pub year2_value: f64,
+    pub year3_value: f64,
+    /// First month (1-12)
+    pub start_month: u32,
+    #[serde(default)]
+    pub ramp: Ramp,
+    /// Twelve multiplicative factors, January first (default: none)
+    #[serde(default)]
+    pub seasonal_factors: Option<Vec<f64>>,
+}
+
+/// A generated profile
+#[derive(Debug, Clone, Serialize)]
+pub struct RampOutput {
+    /// 36 monthly values from the start month
+    pub values: Vec<f64>,
+    /// Sum of each twelve-month year
+    pub year_totals: [f64; 3],
+}
+
+// ============================================================================
+// Generation
+// ============================================================================
+
+impl Ramp {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: &str| Err(Error::validation("ramp", message));
+        if self.months == 0 {
+            return invalid("Ramp months must be at least 1");
+        }
+        match &self.shape {
+            RampShape::Linear { start } if !(0.0..=1.0).contains(start) => invalid("Linear start must be between 0 and 1"),
+            RampShape::Exponential { start } if !(*start > 0.0 && *start <= 1.0) => {
+                invalid("Exponential start must be above 0 and at most 1")
+            }
+            RampShape::SCurve { midpoint, steepness } if !(midpoint.is_finite() && steepness.is_finite() && *steepness > 0.0) => {
+                invalid("S-curve midpoint must be finite and steepness positive")
+            }
+            RampShape::Custom { weights } if weights.is_empty() || weights.iter().any(|w| !(w.is_finite() && *w >= 0.0)) => {
+                invalid("Custom weights must be non-empty, finite and non-negative")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Run rate (full rate = 1) for each month of a profile
+    pub fn curve(&self) -> Vec<f64> {
+        let ramp = self.months as f64;
+        (0..PROFILE_MONTHS)
+            .map(|i| {
+                let x = i as f64;
+                match &self.shape {
+                    RampShape::Custom { weights } => weights.get(i).copied().unwrap_or(1.0),
+                    _ if i >= self.months => 1.0,
+                    RampShape::Linear { start } => start + (1.0 - start) * x / ramp,
+                    RampShape::SCurve { midpoint, steepness } => {
+                        1.0 / (1.0 + (-steepness * ((x + 0.5) / ramp - midpoint)).exp())
+                    }
+                    RampShape::Exponential { start } => start.powf(1.0 - x / ramp),
+                    RampShape::Step { at } => if i >= *at { 1.0 } else { 0.0 },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Spread three annual totals over 36 months from `start_month` (1-12)
+///
+/// `seasonal` holds twelve factors, January first; each year's months are
+/// weighted by curve × season and scaled to the year's total.
+pub fn ramp_profile(year_totals: [f64; 3], start_month: u32, ramp: &Ramp, seasonal: &[f64]) -> Result<Vec<f64>> {
+    ramp.validate()?;
+    if !(1..=12).contains(&start_month) {
+        return Err(Error::validation("start_month", "start_month must be between 1 and 12"));
+    }
+    if seasonal.len() != 12 || seasonal.iter().any(|s| !(s.is_finite() && *s >= 0.0)) {
+        return Err(Error::validation("seasonal_factors", "Seasonal factors must be twelve non-negative numbers"));
+    }
+
+    let weights: Vec<f64> = ramp.curve().iter().enumerate()
+        .map(|(i, r)| r * seasonal[(start_month as usize - 1 + i) % 12])
+        .collect();
+
+    let mut values = Vec::with_capacity(PROFILE_MONTHS);
+    for (year, total) in year_totals.iter().enumerate() {
+        let months = &weights[year * 12..(year + 1) * 12];
+        let sum: f64 = months.iter().sum();
+        if sum <= 0.0 && *total != 0.0 {
+            return Err(Error::validation("ramp", format!("Ramp leaves no sales in year {} to carry its total", year + 1)));
+        }
+        values.extend(months.iter().map(|w| if sum > 0.0 { total * w / sum } else { 0.0 }));
+    }
+    Ok(values)
+}
+
+/// `ramp_profile` on JSON input
+pub fn ramp_profile_json(input_json: &str) -> Result<RampOutput> {
+    let input: RampInput = from_json(input_json)?;
+    let seasonal = input.seasonal_factors.unwrap_or_else(|| vec![1.0; 12]);
+    let values = ramp_profile(
+        [input.year1_value, input.year2_value, input.year3_value],
+        input.start_month,
+        &input.ramp,
+        &seasonal,
+    )?;
+    let year_total = |year: usize| values[year * 12..(year + 1) * 12].iter().sum();
+    Ok(RampOutput { year_totals: [year_total(0), year_total(1), year_total(2)], values })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(json: serde_json::Value) -> Ramp {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn year_sums(values: &[f64]) -> Vec<f64> {
+        values.chunks(12).map(|c| c.iter().sum()).collect()
+    }
+
+    #[test]
+    fn test_every_shape_preserves_year_totals() {
+        let mut seasonal = vec![1.0; 12];
+        seasonal[11] = 2.0;
+        seasonal[6] = 0.5;
+        let shapes = [
+            serde_json::json!({"shape": "linear"}),
+            serde_json::json!({"shape": "s_curve", "months": 18}),
+            serde_json::json!({"shape": "exponential", "start": 0.2}),
+            serde_json::json!({"shape": "step", "at": 3}),
+            serde_json::json!({"shape": "custom", "weights": [0.0, 0.1, 0.3, 0.6, 0.9]}),
+        ];
+        for shape in shapes {
+            let values = ramp_profile([120.0, 240.0, 360.0], 9, &ramp(shape.clone()), &seasonal).unwrap();
+            assert_eq!(values.len(), PROFILE_MONTHS);
+            for (sum, total) in year_sums(&values).iter().zip([120.0, 240.0, 360.0]) {
+                assert!((sum - total).abs() < 1e-9, "{}: {} != {}", shape, sum, total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_curves() {
+        // Linear rises from 50% by 1/24 a month
+        let linear = Ramp::default().curve();
+        assert_eq!(&linear[..3], &[0.5, 0.5 + 0.5 / 12.0, 0.5 + 1.0 / 12.0]);
+        assert_eq!(linear[12], 1.0);
+
+        let s_curve = ramp(serde_json::json!({"shape": "s_curve"})).curve();
+        assert!(s_curve[0] < 0.02 && s_curve[11] > 0.98);
+        assert!(s_curve.windows(2).all(|w| w[1] >= w[0]));
+
+        let step = ramp(serde_json::json!({"shape": "step", "at": 2})).curve();
+        assert_eq!(&step[..3], &[0.0, 0.0, 1.0]);
+
+        // Year 2 is flat apart from seasonality once the ramp is done
+        let values = ramp_profile([12.0, 24.0, 36.0], 1, &Ramp::default(), &[1.0; 12]).unwrap();
+        assert!(values[0] < values[11]);
+        assert!(values[12..24].iter().all(|v| (v - 2.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_validation() {
+        let step = ramp(serde_json::json!({"shape": "step", "at": 14}));
+        let err = ramp_profile([1.0, 1.0, 1.0], 1, &step, &[1.0; 12]).unwrap_err();
+        assert_eq!(err.field(), Some("ramp"));
+
+        let custom = ramp(serde_json::json!({"shape": "custom", "weights": [-1.0]}));
+        assert_eq!(custom.validate().unwrap_err().field(), Some("ramp"));
+
+        let output = ramp_profile_json(r#"{"year1_value": 60, "year2_value": 120, "year3_value": 180,
+            "start_month": 4, "ramp": {"shape": "exponential", "months": 6}}"#).unwrap();
+        assert!((output.year_totals[2] - 180.0).abs() < 1e-9);
+        assert!(ramp_profile_json(r#"{"year1_value": 1, "year2_value": 1, "year3_value": 1, "start_month": 0}"#).is_err());
+    }
+}
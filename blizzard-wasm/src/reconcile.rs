@@ -0,0 +1,507 @@
+//! Hierarchical forecast reconciliation
+//!
+//! The overall total, product groups, customers and geographies are forecast
+//! independently, so children rarely add up to their parent. Reconciliation
+//! maps base forecasts at every node of a hierarchy to coherent ones
+//! (`S G ŷ`, with `S` the summing matrix and `G` chosen by the method):
+//! - bottom_up: leaf forecasts, summed upwards
+//! - top_down: the root forecast split by historical proportions of the leaves
+//! - mint_shrink: minimum trace (MinT) with a shrunk residual covariance
+//!   (Wickramasuriya, Athanasopoulos & Hyndman, 2019)
+//!
+//! A hierarchy is either an explicit tree or a list of hierarchical codes
+//! (geography `FullCode`s or product group codes), which expand two
+//! characters per level: `MEAEDU` sits under `MEAE`, under `ME`, under the
+//! root.
+//!
+//! Intervals come from the base intervals, read as standard errors and
+//! propagated through `S G` (correlated through the shrunk covariance for
+//! MinT, independent otherwise).
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{from_json, Error, Result};
+use crate::intervals::{confidence_warning, interval_bounds, z_score};
+use crate::linalg::{invert, multiply, transpose, Matrix};
+use crate::warnings::Warning;
+use crate::{default_confidence, deserialize_optional_series};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Reconciliation method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileMethod {
+    BottomUp,
+    TopDown,
+    MintShrink,
+}
+
+/// Hierarchy definition
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum HierarchySpec {
+    /// Children of each interior node; nodes without an entry are leaves
+    Tree { root: String, children: BTreeMap<String, Vec<String>> },
+    /// Leaf codes, expanded two characters per level below `root`
+    Codes { root: String, codes: Vec<String> },
+}
+
+/// Base forecast for one node
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeForecast {
+    pub forecast: Vec<f64>,
+    /// Base upper bound at `confidence`, needed for reconciled intervals
+    /// (`lower` is not read, as it may be floored at zero)
+    #[serde(default)]
+    pub upper: Option<Vec<f64>>,
+    /// History, for top_down proportions (leaves)
+    #[serde(default, deserialize_with = "deserialize_optional_series")]
+    pub history: Option<Vec<f64>>,
+    /// In-sample one-step residuals, for mint_shrink (every node)
+    #[serde(default, deserialize_with = "deserialize_optional_series")]
+    pub residuals: Option<Vec<f64>>,
+}
+
+/// Input to `reconcile`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReconcileInput {
+    pub method: ReconcileMethod,
+    pub hierarchy: HierarchySpec,
+    /// Base forecasts by node
+    pub forecasts: BTreeMap<String, NodeForecast>,
+    /// Confidence level of the base and reconciled intervals (default: 0.80)
+    #[serde(default = "default_confidence")]
+    pub confidence: f64,
+}
+
+/// Reconciled forecast for one node
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciledNode {
+    pub forecast: Vec<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lower: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper: Option<Vec<f64>>,
+}
+
+/// Output of `reconcile`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileOutput {
+    pub method: ReconcileMethod,
+    pub nodes: BTreeMap<String, ReconciledNode>,
+    /// Bottom-level nodes, in summing-matrix order
+    pub leaves: Vec<String>,
+    /// Largest gap between a parent's base forecast and the sum of its children's
+    pub base_incoherence: f64,
+    /// Shrinkage intensity towards the diagonal (mint_shrink)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shrinkage: Option<f64>,
+    /// Share of the root given to each leaf (top_down)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proportions: Option<BTreeMap<String, f64>>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Nodes in breadth-first order from the root, with the summing matrix
+#[derive(Debug, Clone)]
+pub struct Hierarchy {
+    pub nodes: Vec<String>,
+    /// Index of each node's parent (`None` for the root)
+    pub parents: Vec<Option<usize>>,
+    /// Indices of the leaf nodes
+    pub leaves: Vec<usize>,
+    /// Row per node, column per leaf: 1 where the leaf sits under the node
+    pub summing: Matrix,
+}
+
+// ============================================================================
+// Hierarchy
+// ============================================================================
+
+impl Hierarchy {
+    pub fn new(spec: &HierarchySpec) -> Result<Hierarchy> {
+        match spec {
+            HierarchySpec::Tree { root, children } => Hierarchy::from_tree(root, children),
+            HierarchySpec::Codes { root, codes } => Hierarchy::from_codes(root, codes),
+        }
+    }
+
+    /// Build from each interior node's children
+    pub fn from_tree(root: &str, children: &BTreeMap<String, Vec<String>>) -> Result<Hierarchy> {
+        let mut nodes = vec![root.to_string()];
+        let mut parents = vec![None];
+        let mut index: BTreeMap<&str, usize> = BTreeMap::from([(root, 0)]);
+        let mut queue = VecDeque::from([0]);
+
+        while let Some(parent) = queue.pop_front() {
+            for child in children.get(&nodes[parent]).into_iter().flatten() {
+                if index.contains_key(child.as_str()) {
+                    return Err(Error::validation("hierarchy", format!("Node {} appears more than once", child)));
+                }
+                index.insert(child, nodes.len());
+                queue.push_back(nodes.len());
+                nodes.push(child.clone());
+                parents.push(Some(parent));
+            }
+        }
+        if let Some(orphan) = children.keys().find(|k| !index.contains_key(k.as_str())) {
+            return Err(Error::validation("hierarchy", format!("Node {} is not under the root", orphan)));
+        }
+
+        let is_parent: BTreeSet<usize> = parents.iter().flatten().copied().collect();
+        let leaves: Vec<usize> = (0..nodes.len()).filter(|i| !is_parent.contains(i)).collect();
+
+        let mut summing = vec![vec![0.0; leaves.len()]; nodes.len()];
+        for (col, &leaf) in leaves.iter().enumerate() {
+            let mut node = Some(leaf);
+            while let Some(i) = node {
+                summing[i][col] = 1.0;
+                node = parents[i];
+            }
+        }
+
+        Ok(Hierarchy { nodes, parents, leaves, summing })
+    }
+
+    /// Build from hierarchical codes, two characters per level
+    pub fn from_codes(root: &str, codes: &[String]) -> Result<Hierarchy> {
+        let mut children: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for code in codes {
+            if code.is_empty() || code == root {
+                return Err(Error::validation("hierarchy", "Codes must be non-empty and differ from the root"));
+            }
+            let mut parent = root.to_string();
+            for end in (2..code.len()).step_by(2).chain([code.len()]) {
+                let Some(level) = code.get(..end) else {
+                    return Err(Error::validation("hierarchy", format!("Code {} is not ASCII", code)));
+                };
+                children.entry(parent).or_default().insert(level.to_string());
+                parent = level.to_string();
+            }
+        }
+        let children = children.into_iter().map(|(k, v)| (k, v.into_iter().collect())).collect();
+        Hierarchy::from_tree(root, &children)
+    }
+}
+
+// ============================================================================
+// Reconciliation
+// ============================================================================
+
+/// Reconcile base forecasts over a hierarchy
+pub fn reconcile(input: &ReconcileInput) -> Result<ReconcileOutput> {
+    if !(input.confidence > 0.0 && input.confidence < 1.0) {
+        return Err(Error::validation("confidence", "confidence must be between 0 and 1"));
+    }
+    let hierarchy = Hierarchy::new(&input.hierarchy)?;
+    let n = hierarchy.nodes.len();
+    let base: Vec<Option<&NodeForecast>> = hierarchy.nodes.iter().map(|k| input.forecasts.get(k)).collect();
+
+    let horizon = base.iter().flatten().map(|f| f.forecast.len()).max().unwrap_or(0);
+    if horizon == 0 || base.iter().flatten().any(|f| f.forecast.len() != horizon) {
+        return Err(Error::validation("forecasts", "Base forecasts must be non-empty and of equal length"));
+    }
+
+    let required: Vec<usize> = match input.method {
+        ReconcileMethod::BottomUp => hierarchy.leaves.clone(),
+        ReconcileMethod::TopDown => vec![0],
+        ReconcileMethod::MintShrink => (0..n).collect(),
+    };
+    if let Some(&missing) = required.iter().find(|&&i| base[i].is_none()) {
+        return Err(Error::validation("forecasts", format!("No base forecast for {}", hierarchy.nodes[missing])));
+    }
+
+    let mut shrinkage = None;
+    let mut proportions = None;
+    let mut correlation = None;
+    let g: Matrix = match input.method {
+        ReconcileMethod::BottomUp => hierarchy.leaves.iter()
+            .map(|&leaf| (0..n).map(|i| if i == leaf { 1.0 } else { 0.0 }).collect())
+            .collect(),
+        ReconcileMethod::TopDown => {
+            let shares = historical_proportions(&hierarchy, &base)?;
+            proportions = Some(hierarchy.leaves.iter().map(|&l| hierarchy.nodes[l].clone()).zip(shares.iter().copied()).collect());
+            shares.iter().map(|&p| (0..n).map(|i| if i == 0 { p } else { 0.0 }).collect()).collect()
+        }
+        ReconcileMethod::MintShrink => {
+            let (w, lambda) = shrunk_covariance(&hierarchy, &base)?;
+            shrinkage = Some(lambda);
+            let g = mint_weights(&hierarchy.summing, &w)?;
+            correlation = Some(w.iter().enumerate()
+                .map(|(i, row)| row.iter().enumerate().map(|(j, v)| v / (w[i][i] * w[j][j]).sqrt()).collect())
+                .collect::<Matrix>());
+            g
+        }
+    };
+
+    // Coherent forecasts: P = S G applied to the base forecasts at each step
+    let projection = multiply(&hierarchy.summing, &g);
+    let used: Vec<bool> = (0..n).map(|j| projection.iter().any(|row| row[j] != 0.0)).collect();
+    let base_matrix: Matrix = base.iter()
+        .map(|f| f.map_or_else(|| vec![0.0; horizon], |f| f.forecast.clone()))
+        .collect();
+    let reconciled = multiply(&projection, &base_matrix);
+
+    let z = z_score(input.confidence);
+    let standard_errors = base_standard_errors(&base, &used, horizon, z);
+    let variances = standard_errors.as_ref().map(|se| {
+        (0..horizon).map(|h| {
+            projection.iter().map(|row| {
+                let mut var = 0.0;
+                for (k, pk) in row.iter().enumerate().filter(|(_, p)| **p != 0.0) {
+                    for (l, pl) in row.iter().enumerate().filter(|(_, p)| **p != 0.0) {
+                        let r = match &correlation {
+                            Some(c) => c[k][l],
+                            None => if k == l { 1.0 } else { 0.0 },
+                        };
+                        var += pk * pl * se[k][h] * se[l][h] * r;
+                    }
+                }
+                var
+            }).collect::<Vec<f64>>()
+        }).collect::<Vec<_>>()
+    });
+
+    let nodes = hierarchy.nodes.iter().enumerate()
+        .map(|(i, key)| {
+            let forecast = reconciled[i].clone();
+            let (lower, upper) = match &variances {
+                Some(v) => {
+                    let se: Vec<f64> = v.iter().map(|step| step[i].max(0.0).sqrt()).collect();
+                    let (lower, upper) = interval_bounds(&forecast, &se, z);
+                    (Some(lower), Some(upper))
+                }
+                None => (None, None),
+            };
+            (key.clone(), ReconciledNode { forecast, lower, upper })
+        })
+        .collect();
+
+    Ok(ReconcileOutput {
+        method: input.method,
+        nodes,
+        leaves: hierarchy.leaves.iter().map(|&l| hierarchy.nodes[l].clone()).collect(),
+        base_incoherence: base_incoherence(&hierarchy, &base),
+        shrinkage,
+        proportions,
+        warnings: confidence_warning(input.confidence).into_iter().collect(),
+    })
+}
+
+/// `reconcile` on JSON input
+pub fn reconcile_json(input_json: &str) -> Result<ReconcileOutput> {
+    let input: ReconcileInput = from_json(input_json)?;
+    reconcile(&input)
+}
+
+/// Leaf shares of the summed leaf history, over months every leaf observed
+///
+/// Histories are aligned at their most recent month.
+fn historical_proportions(hierarchy: &Hierarchy, base: &[Option<&NodeForecast>]) -> Result<Vec<f64>> {
+    let histories = hierarchy.leaves.iter()
+        .map(|&l| {
+            base[l].and_then(|f| f.history.as_deref()).ok_or_else(|| {
+                Error::validation("history", format!("top_down needs the history of {}", hierarchy.nodes[l]))
+            })
+        })
+        .collect::<Result<Vec<&[f64]>>>()?;
+
+    let rows = aligned_rows(&histories);
+    let totals: Vec<f64> = (0..histories.len()).map(|j| rows.iter().map(|row| row[j]).sum()).collect();
+    let grand_total: f64 = totals.iter().sum();
+    if grand_total <= 0.0 {
+        return Err(Error::validation("history", "Leaf histories must have a positive total over common months"));
+    }
+    Ok(totals.iter().map(|t| t / grand_total).collect())
+}
+
+/// Schäfer-Strimmer shrinkage of the residual covariance towards its diagonal
+fn shrunk_covariance(hierarchy: &Hierarchy, base: &[Option<&NodeForecast>]) -> Result<(Matrix, f64)> {
+    let residuals = hierarchy.nodes.iter().zip(base)
+        .map(|(key, f)| {
+            f.and_then(|f| f.residuals.as_deref())
+                .ok_or_else(|| Error::validation("residuals", format!("mint_shrink needs the residuals of {}", key)))
+        })
+        .collect::<Result<Vec<&[f64]>>>()?;
+
+    let rows = aligned_rows(&residuals);
+    let t = rows.len() as f64;
+    if rows.len() < 2 {
+        return Err(Error::validation("residuals", "mint_shrink needs at least two months of residuals observed at every node"));
+    }
+    let n = residuals.len();
+
+    let covariance: Matrix = (0..n)
+        .map(|i| (0..n).map(|j| rows.iter().map(|r| r[i] * r[j]).sum::<f64>() / t).collect())
+        .collect();
+    if let Some(i) = (0..n).find(|&i| covariance[i][i] <= 0.0) {
+        return Err(Error::validation("residuals", format!("Residuals of {} have zero variance", hierarchy.nodes[i])));
+    }
+
+    let scaled: Vec<Vec<f64>> = rows.iter()
+        .map(|r| r.iter().enumerate().map(|(i, e)| e / covariance[i][i].sqrt()).collect())
+        .collect();
+    let (mut variance_sum, mut correlation_sum) = (0.0, 0.0);
+    for i in 0..n {
+        for j in (0..n).filter(|&j| j != i) {
+            let products: f64 = scaled.iter().map(|r| r[i] * r[j]).sum();
+            let squares: f64 = scaled.iter().map(|r| r[i] * r[i] * r[j] * r[j]).sum();
+            variance_sum += (squares - products * products / t) / (t * (t - 1.0));
+            let correlation = covariance[i][j] / (covariance[i][i] * covariance[j][j]).sqrt();
+            correlation_sum += correlation * correlation;
+        }
+    }
+    let lambda = if correlation_sum > 0.0 { (variance_sum / correlation_sum).clamp(0.0, 1.0) } else { 1.0 };
+
+    let shrunk = covariance.iter().enumerate()
+        .map(|(i, row)| row.iter().enumerate().map(|(j, v)| if i == j { *v } else { (1.0 - lambda) * v }).collect())
+        .collect();
+    Ok((shrunk, lambda))
+}
+
+/// MinT weights `G = (S' W⁻¹ S)⁻¹ S' W⁻¹`
+fn mint_weights(summing: &Matrix, w: &Matrix) -> Result<Matrix> {
+    let singular = || Error::Numerical("Reconciliation covariance is singular".to_string());
+    let w_inv = invert(w).ok_or_else(singular)?;
+    let st_w_inv = multiply(&transpose(summing), &w_inv);
+    let inner = invert(&multiply(&st_w_inv, summing)).ok_or_else(singular)?;
+    Ok(multiply(&inner, &st_w_inv))
+}
+
+/// Base standard errors by node and step, from `upper` (the lower bound is
+/// floored at zero), or `None` if a node the result depends on has no interval
+fn base_standard_errors(base: &[Option<&NodeForecast>], used: &[bool], horizon: usize, z: f64) -> Option<Matrix> {
+    base.iter().zip(used)
+        .map(|(f, &used)| match f.and_then(|f| f.upper.as_ref().map(|u| (f, u))) {
+            Some((f, upper)) if upper.len() == horizon => {
+                Some(f.forecast.iter().zip(upper).map(|(p, u)| ((u - p) / z).max(0.0)).collect())
+            }
+            _ if !used => Some(vec![0.0; horizon]),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rows of the series aligned at their last value, keeping only rows where
+/// every series is observed
+fn aligned_rows(series: &[&[f64]]) -> Vec<Vec<f64>> {
+    let len = series.iter().map(|s| s.len()).min().unwrap_or(0);
+    (0..len)
+        .map(|t| series.iter().map(|s| s[s.len() - len + t]).collect::<Vec<f64>>())
+        .filter(|row| row.iter().all(|v| v.is_finite()))
+        .collect()
+}
+
+/// Largest |parent - sum of children| over nodes where all base forecasts exist
+fn base_incoherence(hierarchy: &Hierarchy, base: &[Option<&NodeForecast>]) -> f64 {
+    let mut gap: f64 = 0.0;
+    for (parent, forecast) in base.iter().enumerate() {
+        let children: Vec<usize> = (0..base.len()).filter(|&i| hierarchy.parents[i] == Some(parent)).collect();
+        let Some(forecast) = forecast else { continue };
+        if children.is_empty() || children.iter().any(|&c| base[c].is_none()) {
+            continue;
+        }
+        for (h, value) in forecast.forecast.iter().enumerate() {
+            let sum: f64 = children.iter().filter_map(|&c| base[c]).map(|f| f.forecast[h]).sum();
+            gap = gap.max((value - sum).abs());
+        }
+    }
+    gap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(forecast: &[f64], upper: Option<&[f64]>) -> serde_json::Value {
+        serde_json::json!({ "forecast": forecast, "upper": upper })
+    }
+
+    fn input(json: serde_json::Value) -> ReconcileInput {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_hierarchy_from_codes() {
+        let codes = ["MEAEDU", "MEAEAU", "MESA", "EUDEBE"].map(String::from);
+        let hierarchy = Hierarchy::from_codes("total", &codes).unwrap();
+
+        assert_eq!(hierarchy.nodes, ["total", "EU", "ME", "EUDE", "MEAE", "MESA", "EUDEBE", "MEAEAU", "MEAEDU"]);
+        let leaves: Vec<&str> = hierarchy.leaves.iter().map(|&l| hierarchy.nodes[l].as_str()).collect();
+        assert_eq!(leaves, ["MESA", "EUDEBE", "MEAEAU", "MEAEDU"]);
+        assert_eq!(hierarchy.summing[0], vec![1.0; 4]);
+        assert_eq!(hierarchy.summing[2], vec![1.0, 0.0, 1.0, 1.0]);
+
+        let cyclic = BTreeMap::from([("total".to_string(), vec!["A".to_string()]), ("A".to_string(), vec!["total".to_string()])]);
+        assert_eq!(Hierarchy::from_tree("total", &cyclic).unwrap_err().field(), Some("hierarchy"));
+    }
+
+    #[test]
+    fn test_bottom_up() {
+        let output = reconcile(&input(serde_json::json!({
+            "method": "bottom_up",
+            "hierarchy": { "root": "total", "children": { "total": ["A", "B"] } },
+            "forecasts": {
+                "total": node(&[100.0, 100.0], None),
+                "A": node(&[30.0, 40.0], Some(&[33.84, 43.84])),
+                "B": node(&[50.0, 50.0], Some(&[55.12, 55.12])),
+            },
+        }))).unwrap();
+
+        assert_eq!(output.nodes["total"].forecast, vec![80.0, 90.0]);
+        assert_eq!(output.nodes["A"].forecast, vec![30.0, 40.0]);
+        assert!((output.base_incoherence - 20.0).abs() < 1e-12);
+        // Standard errors 3 and 4 add to 5 in quadrature
+        let upper = output.nodes["total"].upper.as_ref().unwrap();
+        assert!((upper[0] - (80.0 + 1.28 * 5.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_top_down_uses_historical_proportions() {
+        let output = reconcile(&input(serde_json::json!({
+            "method": "top_down",
+            "hierarchy": { "root": "total", "codes": ["S510", "S520"] },
+            "forecasts": {
+                "total": node(&[200.0], Some(&[212.8])),
+                "S510": { "forecast": [0.0], "history": [10.0, null, 30.0] },
+                "S520": { "forecast": [0.0], "history": [70.0, 80.0, 90.0, 50.0] },
+            },
+        }))).unwrap();
+
+        // Aligned at the end, the month S510 is missing is skipped: 10 + 30 of 170
+        let proportions = output.proportions.unwrap();
+        assert!((proportions["S510"] - 40.0 / 170.0).abs() < 1e-12);
+        assert!((output.nodes["S510"].forecast[0] - 200.0 * 40.0 / 170.0).abs() < 1e-9);
+        assert!((output.nodes["S5"].forecast[0] - 200.0).abs() < 1e-9);
+        // Leaves are not needed for intervals
+        assert!(output.nodes["S520"].upper.is_some());
+    }
+
+    #[test]
+    fn test_mint_shrink_with_identity_covariance_is_ols() {
+        // Orthogonal residuals of equal scale: W = I whatever the shrinkage
+        let output = reconcile(&input(serde_json::json!({
+            "method": "mint_shrink",
+            "hierarchy": { "root": "total", "children": { "total": ["A", "B"] } },
+            "forecasts": {
+                "total": { "forecast": [10.0], "residuals": [1.0, 1.0, -1.0, -1.0] },
+                "A": { "forecast": [4.0], "residuals": [1.0, -1.0, 1.0, -1.0] },
+                "B": { "forecast": [4.0], "residuals": [null, 1.0, -1.0, -1.0, 1.0] },
+            },
+        }))).unwrap();
+
+        assert!((output.nodes["A"].forecast[0] - 14.0 / 3.0).abs() < 1e-12);
+        assert!((output.nodes["total"].forecast[0] - 28.0 / 3.0).abs() < 1e-12);
+        assert!(output.nodes["total"].upper.is_none());
+        assert!(output.shrinkage.is_some());
+
+        let err = reconcile_json(r#"{"method": "mint_shrink", "hierarchy": {"root": "t", "children": {"t": ["a"]}},
+            "forecasts": {"a": {"forecast": [1.0], "residuals": [1.0, 2.0]}}}"#).unwrap_err();
+        assert_eq!(err.field(), Some("forecasts"));
+    }
+}
@@ -0,0 +1,1214 @@
+//! Scenario adjustments on top of the baseline
+//!
+//! Port of `web/scenario-model.js`: a scenario is a named list of
+//! adjustments that salespeople layer over the baseline history before it is
+//! forecast:
+//! - scale: multiply a customer, product group or geography by a factor
+//! - remove: scale by 0 (a lost account)
+//! - new_business: a ramped three-year profile for a new product/geography,
+//!   shaped by any `ramp` curve (default linear)
+//! - timing_shift: move a share of a target's forecast volume between months
+//!   of the same year, month for month or paid back over several months
+//! - growth, trend, cap, floor: compound growth, a linear change, or an upper
+//!   or lower limit on a target from a start month, applied explicitly to the
+//!   history before fitting or to the forecast after it (`apply_to`)
+//!
+//! When the baseline includes a sales cube, the history is the cube total
+//! and scale and remove change exactly the target's cells. Without one the
+//! baseline carries only the overall history, so they assume the target
+//! makes up `ASSUMED_CONTRIBUTION` of the total, as in the JS. New business
+//! is appended after the last historical month either way, shaped by the
+//! product group's seasonal factors or, failing those, a profile borrowed
+//! from comparable series in the cube. Timing shifts, and the others when
+//! `apply_to` is "forecast", act on the forecast, so they are applied in
+//! order once it has been made.
+//!
+//! Overlapping adjustments are reported as conflicts, and scale and remove
+//! factors on the same data combine by the scenario's `composition` rule;
+//! see `conflicts`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::attribution::{attribute, Waterfall};
+use crate::conflicts::{composed_factors, detect_conflicts, Composition, Conflict};
+use crate::cube::{SalesCube, Slice};
+use crate::easter::month_offset;
+use crate::error::{from_json, from_value, Error, Result};
+use crate::ramp::{ramp_profile, Ramp};
+use crate::seasonality::{borrow_profile, default_min_months, BorrowedProfile, ProfileSource};
+use crate::{forecast_input, now_ms, ForecastInput, ForecastOutput};
+
+/// Share of the total a scaled or removed target is assumed to represent
+/// when the baseline has no cube
+pub const ASSUMED_CONTRIBUTION: f64 = 0.10;
+
+/// Months of new business appended to the history
+const NEW_BUSINESS_MONTHS: usize = 12;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Baseline data as served by the server and cached in IndexedDB
+#[derive(Debug, Clone, Deserialize)]
+pub struct Baseline {
+    /// Overall history (not needed when `cube` is given)
+    #[serde(default)]
+    pub overall: OverallData,
+    /// Per-product-group data, used for new-business seasonality
+    #[serde(default)]
+    pub products: BTreeMap<String, ProductData>,
+    /// History by customer, product group and geography; replaces `overall`
+    #[serde(default)]
+    pub cube: Option<SalesCube>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OverallData {
+    pub historical: Historical,
+}
+
+/// Monthly history as `["YYYY-MM", value]` rows (null marks a missing month)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Historical {
+    pub rows: Vec<(String, Option<f64>)>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProductData {
+    /// Twelve multiplicative factors, January first
+    #[serde(default)]
+    pub seasonal_factors: Option<Vec<f64>>,
+}
+
+/// A named set of adjustments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub created: String,
+    #[serde(default)]
+    pub modified: String,
+    #[serde(default)]
+    pub adjustments: Vec<Adjustment>,
+    /// How overlapping scale and remove factors combine (default: multiplicative)
+    #[serde(default)]
+    pub composition: Composition,
+    /// Also attribute the forecast to each adjustment (one extra forecast
+    /// per adjustment)
+    #[serde(default)]
+    pub attribution: bool,
+    /// `forecast` fields used when running the scenario (default: 12 months)
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub forecast_options: Map<String, Value>,
+}
+
+/// What a scale or remove adjustment targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetType {
+    Customer,
+    ProductGroup,
+    Geography,
+}
+
+impl TargetType {
+    /// The cube slice a target covers
+    pub fn slice(self, key: &str) -> Slice {
+        let key = Some(key.to_string());
+        match self {
+            TargetType::Customer => Slice { customer: key, ..Slice::default() },
+            TargetType::ProductGroup => Slice { product_group: key, ..Slice::default() },
+            TargetType::Geography => Slice { geography: key, ..Slice::default() },
+        }
+    }
+}
+
+/// One adjustment, with the fields common to every type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Adjustment {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(flatten)]
+    pub kind: AdjustmentKind,
+}
+
+/// Adjustment types, tagged by `type`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdjustmentKind {
+    /// Multiply a target's sales by `factor`
+    Scale { target_type: TargetType, target_key: String, factor: f64 },
+    /// Drop a target's sales
+    Remove { target_type: TargetType, target_key: String },
+    /// Add a ramped profile for a new product group in a geography
+    NewBusiness {
+        product_group: String,
+        geography: String,
+        /// First month, "YYYY-MM"
+        start_month: String,
+        year1_value: f64,
+        year2_value: f64,
+        year3_value: f64,
+        /// Ramp curve (default: linear from 50% over 12 months)
+        #[serde(default)]
+        ramp: Ramp,
+    },
+    /// Move a share of a target's forecast volume between months
+    TimingShift {
+        /// Target (default: the whole total)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_type: Option<TargetType>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_key: Option<String>,
+        /// First month volume is taken from, "YYYY-MM"
+        from_month: String,
+        /// First month it moves to, "YYYY-MM"
+        to_month: String,
+        /// Consecutive months shifted (default: 1)
+        #[serde(default = "default_shift_months")]
+        months: usize,
+        /// Share of the target's volume moved, above 0 and at most 1
+        share: f64,
+        /// Take the volume back evenly over this many months from
+        /// `from_month` instead of month for month
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payback_months: Option<usize>,
+    },
+    /// Compound growth of `annual_rate` a year (0.1 = 10%), held at the
+    /// level reached once the window ends
+    Growth {
+        #[serde(flatten)]
+        window: Window,
+        annual_rate: f64,
+    },
+    /// Linear change of `monthly_change` per month, held once the window ends
+    Trend {
+        #[serde(flatten)]
+        window: Window,
+        monthly_change: f64,
+    },
+    /// At most `limit` a month within the window
+    Cap {
+        #[serde(flatten)]
+        window: Window,
+        limit: f64,
+    },
+    /// At least `limit` a month within the window
+    Floor {
+        #[serde(flatten)]
+        window: Window,
+        limit: f64,
+    },
+}
+
+fn default_shift_months() -> usize { 1 }
+
+/// Target, period and stage of a growth, trend, cap or floor adjustment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Window {
+    /// Target (default: the whole total)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_type: Option<TargetType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_key: Option<String>,
+    /// First month affected, "YYYY-MM"
+    pub start_month: String,
+    /// Months affected (default: through the end of the series)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub months: Option<usize>,
+    /// Whether the history or the forecast is changed
+    pub apply_to: ApplyTo,
+}
+
+/// Stage an adjustment acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyTo {
+    /// The history, before the model is fitted
+    History,
+    /// The forecast, after the model is fitted
+    Forecast,
+}
+
+/// How a growth, trend, cap or floor changes a target's monthly volume
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reshape {
+    Growth(f64),
+    Trend(f64),
+    Cap(f64),
+    Floor(f64),
+}
+
+/// Record of an applied adjustment
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AppliedAdjustment {
+    Scale {
+        target_type: TargetType,
+        target_key: String,
+        factor: f64,
+        /// Share of the total history the target made up
+        contribution: f64,
+        /// Resulting change in the total history, as a factor
+        overall_factor: f64,
+    },
+    NewBusiness {
+        product_group: String,
+        geography: String,
+        start_month: String,
+        ramp: Ramp,
+        /// Seasonal factors used and where they came from
+        seasonality: BorrowedProfile,
+        /// Seasonalized profile (all 36 months; the first 12 are appended)
+        monthly_values: Vec<f64>,
+    },
+    TimingShift {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target_type: Option<TargetType>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target_key: Option<String>,
+        from_month: String,
+        to_month: String,
+        months: usize,
+        share: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        payback_months: Option<usize>,
+        /// Share of the total the target makes up
+        contribution: f64,
+        /// Change to each forecast month, once applied to a forecast
+        #[serde(skip_serializing_if = "Option::is_none")]
+        changes: Option<Vec<f64>>,
+    },
+    Growth {
+        #[serde(flatten)]
+        window: Window,
+        annual_rate: f64,
+        #[serde(flatten)]
+        effect: Effect,
+    },
+    Trend {
+        #[serde(flatten)]
+        window: Window,
+        monthly_change: f64,
+        #[serde(flatten)]
+        effect: Effect,
+    },
+    Cap {
+        #[serde(flatten)]
+        window: Window,
+        limit: f64,
+        #[serde(flatten)]
+        effect: Effect,
+    },
+    Floor {
+        #[serde(flatten)]
+        window: Window,
+        limit: f64,
+        #[serde(flatten)]
+        effect: Effect,
+    },
+}
+
+/// What a growth, trend, cap or floor adjustment did
+#[derive(Debug, Clone, Serialize)]
+pub struct Effect {
+    /// Share of the total the target makes up
+    pub contribution: f64,
+    /// Change to each month of the history or forecast (per `apply_to`),
+    /// once applied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<f64>>,
+}
+
+/// Baseline history after adjustments
+#[derive(Debug, Clone, Serialize)]
+pub struct AdjustedSeries {
+    pub start_year: i32,
+    pub start_month: u32,
+    pub series: Vec<f64>,
+    pub applied: Vec<AppliedAdjustment>,
+    pub composition: Composition,
+    /// Pairs of adjustments that affect the same data
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Result of running a scenario
+#[derive(Serialize)]
+pub struct ScenarioOutput {
+    pub id: String,
+    pub name: String,
+    pub adjusted: AdjustedSeries,
+    pub forecast: ForecastOutput,
+    /// Waterfall from the baseline forecast, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Waterfall>,
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+impl Adjustment {
+    /// Name shown to users: the id, or the adjustment's place in the list
+    pub fn label(&self, index: usize) -> String {
+        match self.id.as_str() {
+            "" => format!("Adjustment {}", index + 1),
+            id => id.to_string(),
+        }
+    }
+
+    /// Check the parameters (mirrors `validateAdjustment` in the JS)
+    pub fn validate(&self) -> Result<()> {
+        match &self.kind {
+            AdjustmentKind::Scale { target_key, factor, .. } => {
+                require_key("target_key", target_key)?;
+                if !(factor.is_finite() && *factor >= 0.0) {
+                    return Err(Error::validation("factor", "Scale factor must be non-negative"));
+                }
+            }
+            AdjustmentKind::Remove { target_key, .. } => require_key("target_key", target_key)?,
+            AdjustmentKind::NewBusiness { product_group, geography, start_month, year1_value, year2_value, year3_value, ramp } => {
+                require_key("product_group", product_group)?;
+                require_key("geography", geography)?;
+                parse_year_month(start_month)
+                    .ok_or_else(|| Error::validation("start_month", "Start month must be \"YYYY-MM\""))?;
+                for (field, value) in [("year1_value", year1_value), ("year2_value", year2_value), ("year3_value", year3_value)] {
+                    if !(value.is_finite() && *value > 0.0) {
+                        return Err(Error::validation(field, format!("{} must be positive", field)));
+                    }
+                }
+                ramp.validate()?;
+            }
+            AdjustmentKind::TimingShift { target_type, target_key, from_month, to_month, months, share, payback_months } => {
+                if target_type.is_some() != target_key.is_some() {
+                    return Err(Error::validation("target_key", "target_type and target_key must be given together"));
+                }
+                if let Some(key) = target_key {
+                    require_key("target_key", key)?;
+                }
+                let from = parse_year_month(from_month)
+                    .ok_or_else(|| Error::validation("from_month", "From month must be \"YYYY-MM\""))?;
+                let to = parse_year_month(to_month)
+                    .ok_or_else(|| Error::validation("to_month", "To month must be \"YYYY-MM\""))?;
+                if *months == 0 {
+                    return Err(Error::validation("months", "months must be at least 1"));
+                }
+                if payback_months == &Some(0) {
+                    return Err(Error::validation("payback_months", "payback_months must be at least 1"));
+                }
+                if !(share.is_finite() && *share > 0.0 && *share <= 1.0) {
+                    return Err(Error::validation("share", "share must be above 0 and at most 1"));
+                }
+                if from == to {
+                    return Err(Error::validation("to_month", "from_month and to_month must differ"));
+                }
+                // Volume only moves within a calendar year, so annual totals hold
+                let ends_in_year = |(year, month): (i32, u32), len: usize| month_offset(year, month, len - 1).0 == from.0;
+                if !ends_in_year(from, payback_months.unwrap_or(*months).max(*months)) {
+                    return Err(Error::validation("from_month", "Timing shifts must stay within one calendar year"));
+                }
+                if to.0 != from.0 || !ends_in_year(to, *months) {
+                    return Err(Error::validation("to_month", "Timing shifts must stay within one calendar year"));
+                }
+            }
+            AdjustmentKind::Growth { window, .. }
+            | AdjustmentKind::Trend { window, .. }
+            | AdjustmentKind::Cap { window, .. }
+            | AdjustmentKind::Floor { window, .. } => {
+                window.validate()?;
+                let invalid = match self.kind.reshape() {
+                    Some((_, Reshape::Growth(rate))) if !(rate.is_finite() && rate > -1.0) => {
+                        Some(("annual_rate", "annual_rate must be above -1"))
+                    }
+                    Some((_, Reshape::Trend(change))) if !change.is_finite() => {
+                        Some(("monthly_change", "monthly_change must be a number"))
+                    }
+                    Some((_, Reshape::Cap(limit) | Reshape::Floor(limit))) if !(limit.is_finite() && limit >= 0.0) => {
+                        Some(("limit", "limit must be non-negative"))
+                    }
+                    _ => None,
+                };
+                if let Some((field, message)) = invalid {
+                    return Err(Error::validation(field, message));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Window {
+    fn validate(&self) -> Result<()> {
+        if self.target_type.is_some() != self.target_key.is_some() {
+            return Err(Error::validation("target_key", "target_type and target_key must be given together"));
+        }
+        if let Some(key) = &self.target_key {
+            require_key("target_key", key)?;
+        }
+        parse_year_month(&self.start_month)
+            .ok_or_else(|| Error::validation("start_month", "Start month must be \"YYYY-MM\""))?;
+        if self.months == Some(0) {
+            return Err(Error::validation("months", "months must be at least 1"));
+        }
+        Ok(())
+    }
+
+    fn target(&self) -> Option<(TargetType, &str)> {
+        self.target_type.zip(self.target_key.as_deref())
+    }
+}
+
+impl AdjustmentKind {
+    /// Window and effect of a growth, trend, cap or floor adjustment
+    fn reshape(&self) -> Option<(&Window, Reshape)> {
+        match self {
+            AdjustmentKind::Growth { window, annual_rate } => Some((window, Reshape::Growth(*annual_rate))),
+            AdjustmentKind::Trend { window, monthly_change } => Some((window, Reshape::Trend(*monthly_change))),
+            AdjustmentKind::Cap { window, limit } => Some((window, Reshape::Cap(*limit))),
+            AdjustmentKind::Floor { window, limit } => Some((window, Reshape::Floor(*limit))),
+            _ => None,
+        }
+    }
+}
+
+impl AppliedAdjustment {
+    fn reshaped(window: Window, reshape: Reshape, effect: Effect) -> Self {
+        match reshape {
+            Reshape::Growth(annual_rate) => AppliedAdjustment::Growth { window, annual_rate, effect },
+            Reshape::Trend(monthly_change) => AppliedAdjustment::Trend { window, monthly_change, effect },
+            Reshape::Cap(limit) => AppliedAdjustment::Cap { window, limit, effect },
+            Reshape::Floor(limit) => AppliedAdjustment::Floor { window, limit, effect },
+        }
+    }
+
+    /// Window, effect and record of a growth, trend, cap or floor adjustment
+    fn reshape_mut(&mut self) -> Option<(&Window, Reshape, &mut Effect)> {
+        match self {
+            AppliedAdjustment::Growth { window, annual_rate, effect } => Some((window, Reshape::Growth(*annual_rate), effect)),
+            AppliedAdjustment::Trend { window, monthly_change, effect } => Some((window, Reshape::Trend(*monthly_change), effect)),
+            AppliedAdjustment::Cap { window, limit, effect } => Some((window, Reshape::Cap(*limit), effect)),
+            AppliedAdjustment::Floor { window, limit, effect } => Some((window, Reshape::Floor(*limit), effect)),
+            _ => None,
+        }
+    }
+}
+
+impl Reshape {
+    /// New target values, with the window starting at index `start` (which
+    /// may be negative) and lasting `months` (default: to the end)
+    fn apply(self, target: &[f64], start: i64, months: Option<usize>) -> Vec<f64> {
+        let months = months.map_or(i64::MAX, |m| m as i64);
+        target.iter().enumerate()
+            .map(|(i, &value)| {
+                let k = i as i64 - start;
+                if k < 0 || !value.is_finite() {
+                    return value;
+                }
+                // Growth and trend steps accrue through the window, then hold
+                let steps = (k + 1).min(months) as f64;
+                let in_window = k < months;
+                match self {
+                    Reshape::Growth(rate) => value * (1.0 + rate).powf(steps / 12.0),
+                    Reshape::Trend(change) => (value + change * steps).max(0.0),
+                    Reshape::Cap(limit) if in_window => value.min(limit),
+                    Reshape::Floor(limit) if in_window => value.max(limit),
+                    Reshape::Cap(_) | Reshape::Floor(_) => value,
+                }
+            })
+            .collect()
+    }
+}
+
+fn require_key(field: &str, value: &str) -> Result<()> {
+    if value.trim().is_empty() {
+        return Err(Error::validation(field, format!("{} is required", field)));
+    }
+    Ok(())
+}
+
+/// Parse "YYYY-MM"
+pub fn parse_year_month(value: &str) -> Option<(i32, u32)> {
+    let (year, month) = value.split_once('-')?;
+    let year = year.parse().ok()?;
+    let month = month.parse().ok()?;
+    (1..=12).contains(&month).then_some((year, month))
+}
+
+// ============================================================================
+// Applying adjustments
+// ============================================================================
+
+/// Apply adjustments in order to the baseline history
+pub fn apply_adjustments(baseline: &Baseline, adjustments: &[Adjustment], composition: Composition) -> Result<AdjustedSeries> {
+    let mut cube = baseline.cube.clone();
+    let mut adjusted = match &cube {
+        Some(cube) => {
+            cube.validate()?;
+            let total = cube.slice_series(&Slice::default());
+            AdjustedSeries {
+                start_year: total.start_year,
+                start_month: total.start_month,
+                series: total.series,
+                applied: vec![],
+                composition,
+                conflicts: vec![],
+            }
+        }
+        None => {
+            let rows = &baseline.overall.historical.rows;
+            let (start_year, start_month) = rows.first()
+                .and_then(|(date, _)| parse_year_month(date))
+                .ok_or_else(|| Error::validation("overall", "Baseline history must start with a \"YYYY-MM\" row"))?;
+            let series = rows.iter().map(|(_, v)| v.unwrap_or(f64::NAN)).collect();
+            AdjustedSeries { start_year, start_month, series, applied: vec![], composition, conflicts: vec![] }
+        }
+    };
+
+    for adjustment in adjustments {
+        adjustment.validate()?;
+    }
+    let first = (adjusted.start_year, adjusted.start_month);
+    adjusted.conflicts = detect_conflicts(adjustments, cube.as_ref(), first, adjusted.series.len(), composition);
+    let scale_factors = composed_factors(adjustments, cube.as_ref(), composition);
+
+    for (adjustment, factors) in adjustments.iter().zip(&scale_factors) {
+        let applied = match (&adjustment.kind, factors) {
+            (AdjustmentKind::Scale { target_type, target_key, factor }, Some(factors)) => {
+                apply_scale(&mut adjusted.series, cube.as_mut(), *target_type, target_key, *factor, factors)?
+            }
+            (AdjustmentKind::Remove { target_type, target_key }, Some(factors)) => {
+                apply_scale(&mut adjusted.series, cube.as_mut(), *target_type, target_key, 0.0, factors)?
+            }
+            (AdjustmentKind::NewBusiness { .. }, _) => apply_new_business(baseline, &mut adjusted.series, &adjustment.kind)?,
+            (AdjustmentKind::TimingShift { target_type, target_key, from_month, to_month, months, share, payback_months }, _) => {
+                let target = target_type.zip(target_key.as_deref());
+                AppliedAdjustment::TimingShift {
+                    target_type: *target_type,
+                    target_key: target_key.clone(),
+                    from_month: from_month.clone(),
+                    to_month: to_month.clone(),
+                    months: *months,
+                    share: *share,
+                    payback_months: *payback_months,
+                    contribution: target_contribution(cube.as_ref(), target)?,
+                    changes: None,
+                }
+            }
+            (kind, _) => {
+                let Some((window, reshape)) = kind.reshape() else {
+                    unreachable!("every other adjustment type is handled above")
+                };
+                let contribution = target_contribution(cube.as_ref(), window.target())?;
+                let changes = match window.apply_to {
+                    ApplyTo::History => Some(reshape_history(&mut adjusted, cube.as_mut(), window, reshape, contribution)?),
+                    ApplyTo::Forecast => None,
+                };
+                AppliedAdjustment::reshaped(window.clone(), reshape, Effect { contribution, changes })
+            }
+        };
+        adjusted.applied.push(applied);
+    }
+
+    Ok(adjusted)
+}
+
+/// Scale the target's cells and move the total by the same amount, or
+/// without a cube scale the total as if the target were
+/// `ASSUMED_CONTRIBUTION` of it
+///
+/// `composed` holds the factors after composition with overlapping scales:
+/// one per cube cell, or one for the target without a cube.
+fn apply_scale(
+    series: &mut [f64],
+    cube: Option<&mut SalesCube>,
+    target_type: TargetType,
+    target_key: &str,
+    factor: f64,
+    composed: &[f64],
+) -> Result<AppliedAdjustment> {
+    let contribution = target_contribution(cube.as_deref(), Some((target_type, target_key)))?;
+    let overall_factor = match cube {
+        Some(cube) => {
+            let before = cube.aggregate(&Slice::default());
+            cube.scale_cells(composed);
+            let after = cube.aggregate(&Slice::default());
+            // Months appended by new business lie past the cube and are untouched
+            for ((value, old), new) in series.iter_mut().zip(&before).zip(&after).filter(|((_, old), _)| old.is_finite()) {
+                *value += new - old;
+            }
+            let observed_sum = |values: &[f64]| values.iter().filter(|v| v.is_finite()).sum::<f64>();
+            let total = observed_sum(&before);
+            if total != 0.0 { observed_sum(&after) / total } else { 1.0 }
+        }
+        None => {
+            let overall_factor = 1.0 + (composed[0] - 1.0) * contribution;
+            for value in series.iter_mut() {
+                *value *= overall_factor;
+            }
+            overall_factor
+        }
+    };
+    Ok(AppliedAdjustment::Scale { target_type, target_key: target_key.to_string(), factor, contribution, overall_factor })
+}
+
+/// Share of the total history a target makes up: from the cube if there is
+/// one, else `ASSUMED_CONTRIBUTION`; the whole total when there is no target
+fn target_contribution(cube: Option<&SalesCube>, target: Option<(TargetType, &str)>) -> Result<f64> {
+    let Some((target_type, target_key)) = target else {
+        return Ok(1.0);
+    };
+    let Some(cube) = cube else {
+        return Ok(ASSUMED_CONTRIBUTION);
+    };
+    let slice = target_type.slice(target_key);
+    if !cube.cells.iter().any(|c| slice.matches(c)) {
+        return Err(Error::validation("target_key", format!("No baseline data for {}", target_key)));
+    }
+    let observed_sum = |values: Vec<f64>| values.iter().filter(|v| v.is_finite()).sum::<f64>();
+    let total = observed_sum(cube.aggregate(&Slice::default()));
+    Ok(if total != 0.0 { observed_sum(cube.aggregate(&slice)) / total } else { 0.0 })
+}
+
+/// Reshape the target's part of the history, returning the change to each month
+///
+/// With a cube the target's cells are rescaled month by month to match;
+/// without one the target is `contribution` of the total.
+fn reshape_history(
+    adjusted: &mut AdjustedSeries,
+    cube: Option<&mut SalesCube>,
+    window: &Window,
+    reshape: Reshape,
+    contribution: f64,
+) -> Result<Vec<f64>> {
+    let start = months_from((adjusted.start_year, adjusted.start_month), &window.start_month);
+    if start >= adjusted.series.len() as i64 {
+        return Err(Error::validation("start_month", "Start month is after the end of the history"));
+    }
+    let slice = window.target().map(|(target_type, key)| target_type.slice(key)).unwrap_or_default();
+
+    // Months appended by new business lie past the cube and are left alone
+    let target: Vec<f64> = match &cube {
+        Some(cube) => {
+            let mut target = cube.aggregate(&slice);
+            target.resize(adjusted.series.len(), f64::NAN);
+            target
+        }
+        None => adjusted.series.iter().map(|v| v * contribution).collect(),
+    };
+    let reshaped = reshape.apply(&target, start, window.months);
+    let changes: Vec<f64> = reshaped.iter().zip(&target)
+        .map(|(new, old)| if old.is_finite() { new - old } else { 0.0 })
+        .collect();
+
+    if let Some(cube) = cube {
+        let ratios: Vec<f64> = reshaped.iter().zip(&target)
+            .map(|(new, old)| if *old > 0.0 { new / old } else { 1.0 })
+            .collect();
+        cube.rescale(&slice, &ratios);
+    }
+    for (value, change) in adjusted.series.iter_mut().zip(&changes) {
+        *value += change;
+    }
+    Ok(changes)
+}
+
+/// Months from `first` to a "YYYY-MM" month (validated by the caller)
+fn months_from(first: (i32, u32), month: &str) -> i64 {
+    let (year, month) = parse_year_month(month).unwrap_or(first);
+    (year - first.0) as i64 * 12 + month as i64 - first.1 as i64
+}
+
+/// Append the first year of a seasonalized new-business profile after the history
+fn apply_new_business(baseline: &Baseline, series: &mut Vec<f64>, kind: &AdjustmentKind) -> Result<AppliedAdjustment> {
+    let AdjustmentKind::NewBusiness { product_group, geography, start_month, year1_value, year2_value, year3_value, ramp } = kind else {
+        unreachable!("apply_new_business called with another adjustment type")
+    };
+    // Validated by the caller
+    let (_, month) = parse_year_month(start_month).unwrap_or((0, 1));
+
+    let seasonality = seasonal_profile(baseline, product_group, geography)?;
+    let monthly_values = ramp_profile([*year1_value, *year2_value, *year3_value], month, ramp, &seasonality.seasonal_factors)?;
+
+    series.extend(&monthly_values[..NEW_BUSINESS_MONTHS]);
+
+    Ok(AppliedAdjustment::NewBusiness {
+        product_group: product_group.clone(),
+        geography: geography.clone(),
+        start_month: start_month.clone(),
+        ramp: ramp.clone(),
+        seasonality,
+        monthly_values,
+    })
+}
+
+/// Seasonal factors of the product group, else borrowed from the cube, else none
+fn seasonal_profile(baseline: &Baseline, product_group: &str, geography: &str) -> Result<BorrowedProfile> {
+    let supplied = baseline.products.get(product_group)
+        .and_then(|p| p.seasonal_factors.clone())
+        .filter(|f| f.len() == 12);
+    match (supplied, &baseline.cube) {
+        (Some(factors), _) => Ok(BorrowedProfile::from_factors(factors, ProfileSource::ProductData)),
+        (None, Some(cube)) => borrow_profile(cube, product_group, geography, default_min_months()),
+        (None, None) => Ok(BorrowedProfile::flat()),
+    }
+}
+
+// ============================================================================
+// Running a scenario
+// ============================================================================
+
+/// Apply a scenario's adjustments to the baseline and forecast the result
+pub fn run_scenario(baseline_json: &str, scenario_json: &str) -> Result<ScenarioOutput> {
+    let started = now_ms();
+    let baseline: Baseline = from_json(baseline_json)?;
+    let scenario: Scenario = from_json(scenario_json)?;
+
+    let mut adjusted = apply_adjustments(&baseline, &scenario.adjustments, scenario.composition)?;
+    let mut forecast = forecast_adjusted(&adjusted, scenario.forecast_options.clone(), started)?;
+    apply_to_forecast(&mut adjusted, &mut forecast)?;
+    let attribution = if scenario.attribution {
+        Some(attribute(&baseline, &scenario, &adjusted, &forecast.forecast)?)
+    } else {
+        None
+    };
+
+    Ok(ScenarioOutput { id: scenario.id, name: scenario.name, adjusted, forecast, attribution })
+}
+
+/// Adjust the baseline and forecast the `horizon` months that follow
+/// `history_months` of adjusted history, with the forecast-side adjustments
+/// applied
+///
+/// Runs whose history is shorter (less new business) are forecast further
+/// ahead and their earlier forecast months dropped, so runs line up.
+pub fn forecast_window(
+    baseline: &Baseline,
+    adjustments: &[Adjustment],
+    composition: Composition,
+    options: &Map<String, Value>,
+    history_months: usize,
+    horizon: usize,
+) -> Result<(AdjustedSeries, ForecastOutput)> {
+    let mut adjusted = apply_adjustments(baseline, adjustments, composition)?;
+    let lead = history_months.saturating_sub(adjusted.series.len());
+    let mut options = options.clone();
+    options.insert("forecast_months".into(), (lead + horizon).into());
+    let mut output = forecast_adjusted(&adjusted, options, now_ms())?;
+    apply_to_forecast(&mut adjusted, &mut output)?;
+    for values in [&mut output.forecast, &mut output.lower, &mut output.upper] {
+        values.drain(..lead);
+    }
+    Ok((adjusted, output))
+}
+
+/// Apply the forecast-side adjustments (timing shifts, and growth, trend,
+/// cap and floor with `apply_to: "forecast"`) in order, recording the changes
+///
+/// Each acts on the target's part of the forecast (the forecast times its
+/// contribution); intervals move with the forecast.
+pub fn apply_to_forecast(adjusted: &mut AdjustedSeries, forecast: &mut ForecastOutput) -> Result<()> {
+    let first = month_offset(adjusted.start_year, adjusted.start_month, adjusted.series.len());
+    let horizon = forecast.forecast.len();
+
+    for applied in &mut adjusted.applied {
+        let (change, changes) = match applied {
+            AppliedAdjustment::TimingShift { from_month, to_month, months, share, payback_months, contribution, changes, .. } => {
+                let index = |field: &str, value: &str, len: usize| -> Result<usize> {
+                    let offset = months_from(first, value);
+                    if offset < 0 || offset as usize + len > horizon {
+                        return Err(Error::validation(field, format!("Shifted months must lie within the {}-month forecast horizon", horizon)));
+                    }
+                    Ok(offset as usize)
+                };
+                let from = index("from_month", from_month, payback_months.unwrap_or(*months).max(*months))?;
+                let to = index("to_month", to_month, *months)?;
+
+                let mut change = vec![0.0; horizon];
+                let amounts: Vec<f64> = (0..*months).map(|k| *share * *contribution * forecast.forecast[from + k]).collect();
+                for (k, amount) in amounts.iter().enumerate() {
+                    change[to + k] += amount;
+                    if payback_months.is_none() {
+                        change[from + k] -= amount;
+                    }
+                }
+                if let Some(payback) = *payback_months {
+                    let each = amounts.iter().sum::<f64>() / payback as f64;
+                    change[from..from + payback].iter_mut().for_each(|c| *c -= each);
+                }
+                (change, changes)
+            }
+            applied => match applied.reshape_mut() {
+                Some((window, reshape, effect)) if window.apply_to == ApplyTo::Forecast => {
+                    let start = months_from(first, &window.start_month);
+                    if start >= horizon as i64 {
+                        return Err(Error::validation("start_month", "Start month is after the end of the forecast horizon"));
+                    }
+                    let target: Vec<f64> = forecast.forecast.iter().map(|v| v * effect.contribution).collect();
+                    let change = reshape.apply(&target, start, window.months).iter().zip(&target)
+                        .map(|(new, old)| new - old)
+                        .collect();
+                    (change, &mut effect.changes)
+                }
+                _ => continue,
+            },
+        };
+
+        for (i, c) in change.iter().enumerate() {
+            forecast.forecast[i] += c;
+            forecast.upper[i] += c;
+            forecast.lower[i] = (forecast.lower[i] + c).max(0.0);
+        }
+        *changes = Some(change);
+    }
+    Ok(())
+}
+
+/// `apply_adjustments` on JSON baseline data and a JSON array of adjustments,
+/// with the composition rule by name (default: multiplicative)
+pub fn apply_adjustments_json(baseline_json: &str, adjustments_json: &str, composition: Option<&str>) -> Result<AdjustedSeries> {
+    let baseline: Baseline = from_json(baseline_json)?;
+    let adjustments: Vec<Adjustment> = from_json(adjustments_json)?;
+    let composition = match composition {
+        Some(name) => serde_json::from_value(Value::String(name.to_string()))
+            .map_err(|_| Error::validation("composition", "composition must be \"multiplicative\", \"override\" or \"additive\""))?,
+        None => Composition::default(),
+    };
+    apply_adjustments(&baseline, &adjustments, composition)
+}
+
+/// Forecast an adjusted history with the scenario's forecast options
+pub fn forecast_adjusted(adjusted: &AdjustedSeries, mut fields: Map<String, Value>, started: f64) -> Result<ForecastOutput> {
+    fields.insert("series".into(), Value::Array(vec![]));
+    fields.insert("start_year".into(), adjusted.start_year.into());
+    fields.insert("start_month".into(), adjusted.start_month.into());
+    fields.entry("forecast_months").or_insert(12.into());
+
+    let mut input: ForecastInput = from_value(Value::Object(fields))?;
+    input.series = adjusted.series.clone();
+    forecast_input(input, started)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::conflicts::Resolution;
+    use crate::easter::month_offset;
+
+    /// 36 months of history from 2022-01 with a trend and a yearly cycle,
+    /// as `["YYYY-MM", value]` rows
+    pub(crate) fn baseline_rows() -> Vec<serde_json::Value> {
+        (0..36)
+            .map(|i| {
+                let (year, month) = month_offset(2022, 1, i);
+                let value = 1000.0 + 20.0 * i as f64 + 200.0 * (month as f64 / 12.0 * std::f64::consts::TAU).sin();
+                serde_json::json!([format!("{}-{:02}", year, month), value])
+            })
+            .collect()
+    }
+
+    /// `baseline_rows` as baseline JSON
+    pub(crate) fn baseline_json() -> String {
+        serde_json::json!({ "overall": { "historical": { "rows": baseline_rows() } } }).to_string()
+    }
+
+    fn baseline() -> Baseline {
+        let mut factors = vec![1.0; 12];
+        factors[11] = 2.0;
+        serde_json::from_value(serde_json::json!({
+            "overall": { "historical": { "rows": baseline_rows() } },
+            "products": { "S5": { "seasonal_factors": factors } },
+        })).unwrap()
+    }
+
+    fn adjustment(json: serde_json::Value) -> Adjustment {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_scale_and_remove() {
+        let baseline = baseline();
+        let original = apply_adjustments(&baseline, &[], Composition::default()).unwrap();
+        assert_eq!((original.start_year, original.start_month), (2022, 1));
+
+        let adjusted = apply_adjustments(&baseline, &[
+            adjustment(serde_json::json!({"type": "scale", "target_type": "customer", "target_key": "ACME", "factor": 1.5})),
+            adjustment(serde_json::json!({"type": "remove", "target_type": "geography", "target_key": "EUR"})),
+        ], Composition::default()).unwrap();
+
+        // 1 + 0.5 * 0.1, then 1 - 0.1
+        let expected = 1.05 * 0.9;
+        assert!((adjusted.series[3] - original.series[3] * expected).abs() < 1e-9);
+        assert_eq!(adjusted.applied.len(), 2);
+        assert!(matches!(adjusted.applied[1], AppliedAdjustment::Scale { factor, .. } if factor == 0.0));
+    }
+
+    #[test]
+    fn test_scale_with_cube_changes_only_the_target() {
+        let baseline: Baseline = serde_json::from_value(serde_json::json!({
+            "cube": {
+                "start_year": 2024, "start_month": 1,
+                "cells": [
+                    { "customer": "ACME", "product_group": "S52020", "geography": "MEAEDU", "values": [100.0, 100.0, null] },
+                    { "customer": "ACME", "product_group": "S61010", "geography": "EUDEBE", "values": [50.0, 50.0, 50.0] },
+                    { "customer": "Initech", "product_group": "S52030", "geography": "EUDEBE", "values": [850.0, 850.0, 850.0] },
+                ]
+            }
+        })).unwrap();
+        let original = apply_adjustments(&baseline, &[], Composition::default()).unwrap();
+        assert_eq!(original.series, vec![1000.0, 1000.0, 900.0]);
+
+        let adjusted = apply_adjustments(&baseline, &[
+            adjustment(serde_json::json!({"type": "scale", "target_type": "customer", "target_key": "ACME", "factor": 2.0})),
+            adjustment(serde_json::json!({"type": "remove", "target_type": "product_group", "target_key": "S6"})),
+        ], Composition::default()).unwrap();
+
+        // ACME doubles to 300, then its S6 cell (now 100) goes
+        assert_eq!(adjusted.series, vec![1050.0, 1050.0, 850.0]);
+        let AppliedAdjustment::Scale { contribution, overall_factor, .. } = adjusted.applied[0] else { panic!() };
+        assert!((contribution - 350.0 / 2900.0).abs() < 1e-12);
+        assert!((overall_factor - 3250.0 / 2900.0).abs() < 1e-12);
+
+        let err = apply_adjustments(&baseline, &[
+            adjustment(serde_json::json!({"type": "remove", "target_type": "geography", "target_key": "US"})),
+        ], Composition::default()).unwrap_err();
+        assert_eq!(err.field(), Some("target_key"));
+    }
+
+    #[test]
+    fn test_overlapping_scales_follow_the_composition_rule() {
+        let baseline: Baseline = serde_json::from_value(serde_json::json!({
+            "cube": {
+                "start_year": 2024, "start_month": 1,
+                "cells": [
+                    { "customer": "ACME", "product_group": "S5", "geography": "EUDE", "values": [100.0, 100.0] },
+                    { "customer": "Initech", "product_group": "S5", "geography": "EUFR", "values": [200.0, 200.0] },
+                ]
+            }
+        })).unwrap();
+        let adjustments = [
+            adjustment(serde_json::json!({"id": "lost", "type": "remove", "target_type": "customer", "target_key": "ACME"})),
+            adjustment(serde_json::json!({"id": "eu", "type": "scale", "target_type": "geography", "target_key": "EU", "factor": 1.5})),
+        ];
+        let totals: Vec<f64> = [Composition::Multiplicative, Composition::Override, Composition::Additive].iter()
+            .map(|&rule| apply_adjustments(&baseline, &adjustments, rule).unwrap().series[0])
+            .collect();
+        // ACME: 0 × 1.5, 1.5 (the later scale wins) or 1 - 1 + 0.5; Initech: 1.5 throughout
+        assert_eq!(totals, vec![300.0, 450.0, 350.0]);
+
+        let adjusted = apply_adjustments(&baseline, &adjustments, Composition::Override).unwrap();
+        assert_eq!(adjusted.conflicts.len(), 1);
+        assert_eq!(adjusted.conflicts[0].cells, Some(1));
+        assert_eq!(adjusted.conflicts[0].message, "lost and eu affect the same data; eu overrides lost");
+
+        let err = apply_adjustments_json(&serde_json::json!({ "cube": baseline.cube }).to_string(), "[]", Some("max"));
+        assert_eq!(err.unwrap_err().field(), Some("composition"));
+    }
+
+    #[test]
+    fn test_without_a_cube_only_the_same_target_composes() {
+        let baseline = baseline();
+        let original = apply_adjustments(&baseline, &[], Composition::default()).unwrap().series[0];
+        let scales = |first: &str, second: &str| [
+            adjustment(serde_json::json!({"id": "a", "type": "scale", "target_type": "geography", "target_key": first, "factor": 2.0})),
+            adjustment(serde_json::json!({"id": "b", "type": "scale", "target_type": "geography", "target_key": second, "factor": 3.0})),
+        ];
+        let rules = [Composition::Multiplicative, Composition::Override, Composition::Additive];
+
+        // Nested targets multiply under every rule and are reported that way
+        for rule in rules {
+            let adjusted = apply_adjustments(&baseline, &scales("EU", "EUDE"), rule).unwrap();
+            assert!((adjusted.series[0] - original * 1.1 * 1.2).abs() < 1e-9);
+            assert_eq!(adjusted.conflicts[0].resolution, Resolution::Multiplicative);
+        }
+
+        // The same target: 1.1 × 1.2, 1.2 (the later wins) or 1 + 0.1 × (1 + 2)
+        for (rule, expected) in rules.into_iter().zip([1.32, 1.2, 1.3]) {
+            let adjusted = apply_adjustments(&baseline, &scales("EU", "EU"), rule).unwrap();
+            assert!((adjusted.series[0] - original * expected).abs() < 1e-9);
+            assert_eq!(adjusted.conflicts[0].resolution, Resolution::from(rule));
+        }
+    }
+
+    #[test]
+    fn test_new_business_is_appended_with_product_seasonality() {
+        let baseline = baseline();
+        let adjusted = apply_adjustments(&baseline, &[adjustment(serde_json::json!({
+            "type": "new_business", "product_group": "S5", "geography": "MEAEDU",
+            "start_month": "2025-09", "year1_value": 120.0, "year2_value": 240.0, "year3_value": 360.0,
+        }))], Composition::default()).unwrap();
+
+        assert_eq!(adjusted.series.len(), 48);
+        assert_eq!(month_offset(2022, 1, adjusted.series.len()), (2026, 1));
+        // The first year keeps its total; December carries the S5 factor of 2
+        let appended = &adjusted.series[36..];
+        assert!((appended.iter().sum::<f64>() - 120.0).abs() < 1e-9);
+        assert!(appended[3] > 1.8 * appended[2]);
+
+        let stepped = apply_adjustments(&baseline, &[adjustment(serde_json::json!({
+            "type": "new_business", "product_group": "S5", "geography": "MEAEDU",
+            "start_month": "2025-09", "year1_value": 120.0, "year2_value": 240.0, "year3_value": 360.0,
+            "ramp": { "shape": "step", "at": 6 },
+        }))], Composition::default()).unwrap();
+        let AppliedAdjustment::NewBusiness { monthly_values, seasonality, .. } = &stepped.applied[0] else { panic!() };
+        assert_eq!(seasonality.source, ProfileSource::ProductData);
+        assert_eq!(&monthly_values[..6], &[0.0; 6]);
+        assert!((monthly_values[6..12].iter().sum::<f64>() - 120.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_timing_shift_moves_volume_within_the_year() {
+        let input = baseline_json();
+        let run = |adjustments: serde_json::Value| {
+            let scenario = serde_json::json!({ "name": "s", "adjustments": adjustments });
+            run_scenario(&input, &scenario.to_string())
+        };
+        let base = run(serde_json::json!([])).unwrap().forecast.forecast;
+
+        // Q1 orders slip to Q2 (the forecast starts January 2025)
+        let output = run(serde_json::json!([{
+            "type": "timing_shift", "from_month": "2025-01", "to_month": "2025-04", "months": 3, "share": 0.5,
+        }])).unwrap();
+        let shifted = &output.forecast.forecast;
+        assert!((shifted[0] - 0.5 * base[0]).abs() < 1e-9);
+        assert!((shifted[3] - base[3] - 0.5 * base[0]).abs() < 1e-9);
+        assert!((shifted.iter().sum::<f64>() - base.iter().sum::<f64>()).abs() < 1e-6);
+
+        // Pulled forward into March from June, paid back over June to August
+        let output = run(serde_json::json!([{
+            "type": "timing_shift", "target_type": "customer", "target_key": "ACME",
+            "from_month": "2025-06", "to_month": "2025-03", "share": 1.0, "payback_months": 3,
+        }])).unwrap();
+        let AppliedAdjustment::TimingShift { changes: Some(changes), contribution, .. } = &output.adjusted.applied[0] else { panic!() };
+        assert_eq!(*contribution, ASSUMED_CONTRIBUTION);
+        let moved = ASSUMED_CONTRIBUTION * base[5];
+        assert!((changes[2] - moved).abs() < 1e-9);
+        assert!((changes[6] + moved / 3.0).abs() < 1e-9);
+        assert!(changes.iter().sum::<f64>().abs() < 1e-9);
+
+        let across_years = run(serde_json::json!([{
+            "type": "timing_shift", "from_month": "2025-12", "to_month": "2026-01", "share": 0.2,
+        }]));
+        assert_eq!(across_years.err().unwrap().field(), Some("to_month"));
+
+        // The source months (with their payback) run past December
+        let source_across_years = run(serde_json::json!([{
+            "type": "timing_shift", "from_month": "2025-11", "to_month": "2025-02", "months": 3, "share": 0.2,
+        }]));
+        assert_eq!(source_across_years.err().unwrap().field(), Some("from_month"));
+
+        let past_horizon = run(serde_json::json!([{
+            "type": "timing_shift", "from_month": "2024-11", "to_month": "2024-12", "share": 0.2,
+        }]));
+        assert_eq!(past_horizon.err().unwrap().field(), Some("from_month"));
+    }
+
+    #[test]
+    fn test_growth_trend_cap_and_floor() {
+        let cube_baseline: Baseline = serde_json::from_value(serde_json::json!({
+            "cube": {
+                "start_year": 2024, "start_month": 1,
+                "cells": [
+                    { "customer": "ACME", "product_group": "S5", "geography": "EU", "values": vec![100.0; 6] },
+                    { "customer": "Initech", "product_group": "S6", "geography": "EU", "values": vec![300.0; 6] },
+                ]
+            }
+        })).unwrap();
+        let apply = |json: serde_json::Value| apply_adjustments(&cube_baseline, &[adjustment(json)], Composition::default());
+
+        // 12% a year on ACME for two months from March, held afterwards
+        let adjusted = apply(serde_json::json!({
+            "type": "growth", "target_type": "customer", "target_key": "ACME",
+            "start_month": "2024-03", "months": 2, "annual_rate": 0.12, "apply_to": "history",
+        })).unwrap();
+        let step = 1.12f64.powf(1.0 / 12.0);
+        assert_eq!(adjusted.series[1], 400.0);
+        assert!((adjusted.series[2] - (300.0 + 100.0 * step)).abs() < 1e-9);
+        assert!((adjusted.series[5] - (300.0 + 100.0 * step * step)).abs() < 1e-9);
+
+        // A cap only holds within its window and a falling trend stops at zero
+        let capped = apply(serde_json::json!({
+            "type": "cap", "target_type": "customer", "target_key": "Initech",
+            "start_month": "2024-02", "months": 3, "limit": 250.0, "apply_to": "history",
+        })).unwrap();
+        assert_eq!(capped.series, vec![400.0, 350.0, 350.0, 350.0, 400.0, 400.0]);
+        let trend = apply(serde_json::json!({
+            "type": "trend", "target_type": "customer", "target_key": "ACME",
+            "start_month": "2024-05", "monthly_change": -60.0, "apply_to": "history",
+        })).unwrap();
+        assert_eq!(&trend.series[3..], &[400.0, 340.0, 300.0]);
+
+        // After fitting, the change lands on the forecast and its intervals
+        let input = baseline_json();
+        let run = |adjustments: serde_json::Value| {
+            let scenario = serde_json::json!({ "name": "s", "adjustments": adjustments });
+            run_scenario(&input, &scenario.to_string()).unwrap()
+        };
+        let base = run(serde_json::json!([])).forecast;
+        let output = run(serde_json::json!([{
+            "type": "floor", "start_month": "2025-01", "limit": 5000.0, "apply_to": "forecast",
+        }]));
+        assert!(output.forecast.forecast.iter().all(|v| *v == 5000.0));
+        assert!((output.forecast.upper[0] - base.upper[0] - (5000.0 - base.forecast[0])).abs() < 1e-9);
+        let AppliedAdjustment::Floor { effect, .. } = &output.adjusted.applied[0] else { panic!() };
+        assert_eq!(effect.changes.as_ref().map(Vec::len), Some(base.forecast.len()));
+    }
+
+    #[test]
+    fn test_validation() {
+        let baseline = baseline();
+        let err = apply_adjustments(&baseline, &[
+            adjustment(serde_json::json!({"type": "scale", "target_type": "customer", "target_key": "ACME", "factor": -1.0})),
+        ], Composition::default()).unwrap_err();
+        assert_eq!(err.field(), Some("factor"));
+
+        let err = apply_adjustments(&baseline, &[adjustment(serde_json::json!({
+            "type": "new_business", "product_group": "S5", "geography": "EUR",
+            "start_month": "Sept", "year1_value": 1.0, "year2_value": 1.0, "year3_value": 1.0,
+        }))], Composition::default()).unwrap_err();
+        assert_eq!(err.field(), Some("start_month"));
+
+        let err = apply_adjustments(&baseline, &[adjustment(serde_json::json!({
+            "type": "growth", "start_month": "2024-01", "annual_rate": -1.5, "apply_to": "history",
+        }))], Composition::default()).unwrap_err();
+        assert_eq!(err.field(), Some("annual_rate"));
+        let err = apply_adjustments(&baseline, &[adjustment(serde_json::json!({
+            "type": "cap", "start_month": "2030-01", "limit": 10.0, "apply_to": "history",
+        }))], Composition::default()).unwrap_err();
+        assert_eq!(err.field(), Some("start_month"));
+        // The stage must be chosen explicitly
+        let implicit = serde_json::json!({"type": "trend", "start_month": "2024-01", "monthly_change": 5.0});
+        assert!(serde_json::from_value::<Adjustment>(implicit).is_err());
+
+        let unknown = serde_json::from_value::<Adjustment>(serde_json::json!({"type": "merge"}));
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn test_run_scenario_matches_forecast() {
+        let input = baseline_json();
+        let scenario = serde_json::json!({
+            "id": "s1", "name": "Lose ACME",
+            "adjustments": [{"type": "remove", "target_type": "customer", "target_key": "ACME", "note": "lost"}],
+            "forecast_options": {"forecast_months": 6, "confidence": 0.95},
+        });
+
+        let output = run_scenario(&input, &scenario.to_string()).unwrap();
+        assert_eq!(output.name, "Lose ACME");
+        assert_eq!(output.forecast.forecast.len(), 6);
+
+        let direct = serde_json::json!({
+            "series": output.adjusted.series, "start_year": 2022, "start_month": 1,
+            "forecast_months": 6, "confidence": 0.95,
+        });
+        let direct: serde_json::Value = serde_json::from_str(&crate::forecast(&direct.to_string())).unwrap();
+        assert_eq!(serde_json::to_value(&output.forecast.forecast).unwrap(), direct["forecast"]);
+    }
+}
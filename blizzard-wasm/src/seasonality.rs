@@ -0,0 +1,223 @@
+//! Seasonal profiles borrowed from comparable series
+//!
+//! New business has no history of its own, so its seasonal shape comes from
+//! the sales cube, trying each pool in turn until one has enough history:
+//! 1. the same product group in other geographies
+//! 2. every product group in the parent geography, then the grandparent, ...
+//! 3. the overall total
+//!
+//! Within a pool each series gets multiplicative factors (as for the ARIMA
+//! fit) and the pool's profile is their average weighted by sales volume.
+//! The result records which series contributed and with what weight.
+
+use serde::{Deserialize, Serialize};
+
+use crate::arima::calculate_seasonal_factors;
+use crate::cube::{SalesCube, Slice};
+use crate::error::{from_json, Result};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Where a seasonal profile came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileSource {
+    /// Factors supplied with the baseline product data
+    ProductData,
+    SameProductGroup,
+    ParentGeography,
+    Overall,
+    /// No usable history: flat
+    None,
+}
+
+/// One series in the pool a profile was borrowed from
+#[derive(Debug, Clone, Serialize)]
+pub struct Contributor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geography: Option<String>,
+    /// Share of the profile (weights sum to one)
+    pub weight: f64,
+    /// Months of positive sales behind its factors
+    pub months: usize,
+}
+
+/// Seasonal profile with its provenance
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowedProfile {
+    /// Twelve multiplicative factors averaging one, January first
+    pub seasonal_factors: Vec<f64>,
+    pub source: ProfileSource,
+    /// Geography code of the pool, for parent_geography
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub contributors: Vec<Contributor>,
+}
+
+/// Input to the `seasonal_profile` export
+#[derive(Deserialize)]
+pub struct ProfileInput {
+    pub cube: SalesCube,
+    pub product_group: String,
+    pub geography: String,
+    /// Months of history a series needs to join a pool (default: 24, at least 12)
+    #[serde(default = "default_min_months")]
+    pub min_months: usize,
+}
+
+pub fn default_min_months() -> usize { 24 }
+
+// ============================================================================
+// Borrowing
+// ============================================================================
+
+impl BorrowedProfile {
+    /// Profile with the given factors and no pool
+    pub fn from_factors(seasonal_factors: Vec<f64>, source: ProfileSource) -> Self {
+        BorrowedProfile { seasonal_factors, source, level: None, contributors: vec![] }
+    }
+
+    /// No seasonality
+    pub fn flat() -> Self {
+        BorrowedProfile::from_factors(vec![1.0; 12], ProfileSource::None)
+    }
+}
+
+/// Borrow a seasonal profile for a product group in a geography
+pub fn borrow_profile(cube: &SalesCube, product_group: &str, geography: &str, min_months: usize) -> Result<BorrowedProfile> {
+    cube.validate()?;
+
+    let same_group = Slice { product_group: Some(product_group.to_string()), ..Slice::default() };
+    let pool: Vec<_> = cube.group_by(&same_group, |c| c.geography.as_str()).into_iter()
+        .map(|(geo, series)| (Some(product_group), Some(geo), series))
+        .collect();
+    if let Some(profile) = pooled_profile(cube, pool, min_months, ProfileSource::SameProductGroup, None) {
+        return Ok(profile);
+    }
+
+    let parents = (1..geography.len().div_ceil(2)).rev().filter_map(|level| geography.get(..2 * level));
+    for parent in parents {
+        let region = Slice { geography: Some(parent.to_string()), ..Slice::default() };
+        let pool: Vec<_> = cube.group_by(&region, |c| c.product_group.as_str()).into_iter()
+            .map(|(group, series)| (Some(group), Some(parent), series))
+            .collect();
+        if let Some(profile) = pooled_profile(cube, pool, min_months, ProfileSource::ParentGeography, Some(parent)) {
+            return Ok(profile);
+        }
+    }
+
+    let overall = vec![(None, None, cube.aggregate(&Slice::default()))];
+    Ok(pooled_profile(cube, overall, min_months, ProfileSource::Overall, None).unwrap_or_else(BorrowedProfile::flat))
+}
+
+/// `borrow_profile` on JSON input
+pub fn borrow_profile_json(input_json: &str) -> Result<BorrowedProfile> {
+    let input: ProfileInput = from_json(input_json)?;
+    borrow_profile(&input.cube, &input.product_group, &input.geography, input.min_months)
+}
+
+/// Volume-weighted profile of the series with enough history, if any
+fn pooled_profile(
+    cube: &SalesCube,
+    pool: Vec<(Option<&str>, Option<&str>, Vec<f64>)>,
+    min_months: usize,
+    source: ProfileSource,
+    level: Option<&str>,
+) -> Option<BorrowedProfile> {
+    let mut factors = [0.0; 12];
+    let mut contributors = vec![];
+    let mut total_volume = 0.0;
+
+    for (product_group, geography, series) in pool {
+        let months = series.iter().filter(|v| **v > 0.0).count();
+        let volume: f64 = series.iter().filter(|v| **v > 0.0).sum();
+        if months < min_months.max(12) {
+            continue;
+        }
+        // Factors follow the series from the cube start; rotate to January first
+        let by_position = calculate_seasonal_factors(&series, 12);
+        for (month, factor) in factors.iter_mut().enumerate() {
+            *factor += volume * by_position[(month + 12 - (cube.start_month as usize - 1)) % 12];
+        }
+        total_volume += volume;
+        contributors.push(Contributor {
+            product_group: product_group.map(str::to_string),
+            geography: geography.map(str::to_string),
+            weight: volume,
+            months,
+        });
+    }
+    if contributors.is_empty() {
+        return None;
+    }
+
+    let mean = factors.iter().sum::<f64>() / 12.0;
+    for contributor in &mut contributors {
+        contributor.weight /= total_volume;
+    }
+    Some(BorrowedProfile {
+        seasonal_factors: factors.iter().map(|f| f / mean).collect(),
+        source,
+        level: level.map(str::to_string),
+        contributors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 36 months from July with a December peak of `peak` times the base
+    fn values(base: f64, peak: f64) -> Vec<f64> {
+        (0..36).map(|i| if (i + 6) % 12 == 11 { base * peak } else { base }).collect()
+    }
+
+    fn cube(cells: serde_json::Value) -> SalesCube {
+        serde_json::from_value(serde_json::json!({ "start_year": 2022, "start_month": 7, "cells": cells })).unwrap()
+    }
+
+    #[test]
+    fn test_same_product_group_pool_is_volume_weighted() {
+        let cube = cube(serde_json::json!([
+            { "customer": "A", "product_group": "S520", "geography": "EUDE", "values": values(100.0, 3.0) },
+            { "customer": "B", "product_group": "S520", "geography": "EUFR", "values": values(300.0, 1.0) },
+            { "customer": "C", "product_group": "S610", "geography": "MEAE", "values": values(1000.0, 5.0) },
+        ]));
+        let profile = borrow_profile(&cube, "S520", "MEAE", 24).unwrap();
+
+        assert_eq!(profile.source, ProfileSource::SameProductGroup);
+        assert_eq!(profile.contributors.len(), 2);
+        let weights: Vec<f64> = profile.contributors.iter().map(|c| c.weight).collect();
+        assert!((weights[0] - 4200.0 / 15000.0).abs() < 1e-12);
+        // December is the peak and the profile averages one
+        assert!(profile.seasonal_factors[11] > profile.seasonal_factors[10]);
+        assert!((profile.seasonal_factors.iter().sum::<f64>() - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_falls_back_to_parent_geography_then_overall() {
+        let mut short = values(50.0, 2.0);
+        short[..20].fill(f64::NAN);
+        let cube = cube(serde_json::json!([
+            { "customer": "A", "product_group": "S520", "geography": "EUDE", "values": short },
+            { "customer": "B", "product_group": "S610", "geography": "MEAEAU", "values": values(200.0, 4.0) },
+        ]));
+
+        let profile = borrow_profile(&cube, "S520", "MEAEDU", 24).unwrap();
+        assert_eq!(profile.source, ProfileSource::ParentGeography);
+        assert_eq!(profile.level.as_deref(), Some("MEAE"));
+        assert_eq!(profile.contributors[0].product_group.as_deref(), Some("S610"));
+        assert!((profile.seasonal_factors[11] / profile.seasonal_factors[0] - 4.0).abs() < 1e-9);
+
+        let profile = borrow_profile(&cube, "S520", "USCA", 24).unwrap();
+        assert_eq!(profile.source, ProfileSource::Overall);
+
+        let profile = borrow_profile(&cube, "S520", "USCA", 48).unwrap();
+        assert_eq!(profile.source, ProfileSource::None);
+        assert_eq!(profile.seasonal_factors, vec![1.0; 12]);
+    }
+}
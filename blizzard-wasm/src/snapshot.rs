@@ -0,0 +1,129 @@
+//! Versioned snapshots of a `Model` for storage alongside cached baselines
+//!
+//! A snapshot holds the model settings and, once fitted, the estimated
+//! parameters and the history they were fitted to. Restoring reruns only
+//! the Kalman filter over the stored history, so forecasts are identical to
+//! the original model's without re-estimating anything.
+//!
+//! Two encodings carry the same content:
+//! - JSON: `{"version": 2, "options": {...}, "fitted": {...} | null}`
+//! - binary: the bytes `BLZM` followed by the bincode encoding, whose first
+//!   four bytes are the version (little-endian u32)
+//!
+//! Bump `SNAPSHOT_VERSION` whenever the stored fields change; older or newer
+//! snapshots are rejected with a `version_mismatch` error rather than being
+//! misread.
+
+use serde::{Deserialize, Serialize};
+
+use crate::arima::{FittedState, ForecastOptions};
+use crate::error::{from_json, Error, Result};
+
+/// Current snapshot format version
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Leading bytes of a binary snapshot
+const MAGIC: &[u8; 4] = b"BLZM";
+
+/// Model settings and fitted state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Format version (always first, so it can be read on its own)
+    pub version: u32,
+    pub options: ForecastOptions,
+    pub fitted: Option<FittedState>,
+}
+
+/// Just the version, read before the rest of a JSON snapshot
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32,
+}
+
+impl Snapshot {
+    pub fn new(options: ForecastOptions, fitted: Option<FittedState>) -> Self {
+        Snapshot { version: SNAPSHOT_VERSION, options, fitted }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::Numerical(format!("Failed to serialize snapshot: {}", e)))
+    }
+
+    pub fn from_json(json: &str) -> Result<Snapshot> {
+        let probe: VersionProbe = from_json(json)?;
+        check_version(probe.version)?;
+        from_json(json)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let body = bincode::serialize(self)
+            .map_err(|e| Error::Numerical(format!("Failed to serialize snapshot: {}", e)))?;
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(body);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot> {
+        let body = bytes.strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| Error::Parse("not a model snapshot".to_string()))?;
+        let version = body.get(..4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| Error::Parse("snapshot is truncated".to_string()))?;
+        check_version(version)?;
+        bincode::deserialize(body).map_err(|e| Error::Parse(e.to_string()))
+    }
+}
+
+fn check_version(found: u32) -> Result<()> {
+    if found == SNAPSHOT_VERSION {
+        Ok(())
+    } else {
+        Err(Error::VersionMismatch { found, expected: SNAPSHOT_VERSION })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arima::fit_with_options;
+
+    fn snapshot() -> Snapshot {
+        let mut series: Vec<f64> = (0..48).map(|i| 500.0 + 5.0 * i as f64 + 50.0 * ((i % 12) as f64).cos()).collect();
+        series[7] = f64::NAN;
+        let options = ForecastOptions::default();
+        let fitted = fit_with_options(&series, 2020, 1, &options);
+        Snapshot::new(options, Some(fitted.state()))
+    }
+
+    #[test]
+    fn test_json_and_binary_round_trip() {
+        let original = snapshot();
+
+        let from_json = Snapshot::from_json(&original.to_json().unwrap()).unwrap();
+        let from_bytes = Snapshot::from_bytes(&original.to_bytes().unwrap()).unwrap();
+
+        for restored in [from_json, from_bytes] {
+            let (a, b) = (original.fitted.as_ref().unwrap(), restored.fitted.as_ref().unwrap());
+            assert_eq!(a.model.ar_coeffs, b.model.ar_coeffs);
+            assert_eq!(a.model.seasonal_factors, b.model.seasonal_factors);
+            assert_eq!(a.model.intercept, b.model.intercept);
+            assert!(b.model.series[7].is_nan());
+            assert_eq!(restored.options.p, original.options.p);
+        }
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let mut json: serde_json::Value = serde_json::to_value(snapshot()).unwrap();
+        json["version"] = 99.into();
+        let err = Snapshot::from_json(&json.to_string()).unwrap_err();
+        assert_eq!(err, Error::VersionMismatch { found: 99, expected: SNAPSHOT_VERSION });
+        assert_eq!(err.code(), "version_mismatch");
+
+        let mut bytes = snapshot().to_bytes().unwrap();
+        bytes[4] = 9;
+        assert!(matches!(Snapshot::from_bytes(&bytes), Err(Error::VersionMismatch { found: 9, .. })));
+
+        assert_eq!(Snapshot::from_bytes(b"nope").unwrap_err().code(), "parse_error");
+    }
+}
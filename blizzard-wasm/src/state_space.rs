@@ -0,0 +1,446 @@
+//! State-space form of ARIMA(p,d,q) with Kalman filter and smoother
+//!
+//! The state vector uses Harvey's representation: an ARMA block of size
+//! r = max(p, q + 1) followed by the d lagged levels needed to integrate the
+//! differenced series. Observations have no measurement noise, so filtering
+//! reproduces the ARIMA recursion exactly while giving:
+//! - the exact Gaussian log-likelihood (conditional on the first d levels)
+//! - skipping of missing observations (NaN) in the update step
+//! - filtered and smoothed states
+//! - forecast variances straight from the propagated state covariance
+//!
+//! Everything runs with unit innovation variance; sigma² is concentrated out
+//! of the likelihood and applied to variances on the way out.
+
+use crate::linalg::Matrix;
+
+/// Prior variance for level slots that cannot be initialised from data
+const DIFFUSE_VARIANCE: f64 = 1e8;
+
+/// Linear Gaussian state-space model
+///
+/// ```text
+/// y_t     = Z a_t + d
+/// a_{t+1} = T a_t + c + R e_t,   e_t ~ N(0, sigma²)
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StateSpace {
+    transition: Matrix,
+    design: Vec<f64>,
+    selection: Vec<f64>,
+    state_intercept: Vec<f64>,
+    obs_intercept: f64,
+    arma_dim: usize,
+    d: usize,
+}
+
+/// Output of a Kalman filter pass
+#[derive(Debug, Clone, Default)]
+pub struct FilterOutput {
+    /// One-step predicted states a_{t|t-1}
+    pub predicted_states: Vec<Vec<f64>>,
+    /// One-step predicted state covariances (unit variance scale)
+    pub predicted_covs: Vec<Matrix>,
+    /// Filtered states a_{t|t}
+    pub filtered_states: Vec<Vec<f64>>,
+    /// One-step prediction errors (NaN where the observation is missing)
+    pub innovations: Vec<f64>,
+    /// Innovation variances (unit variance scale; NaN where missing)
+    pub innovation_vars: Vec<f64>,
+    /// Predicted state after the last observation
+    pub next_state: Vec<f64>,
+    /// Predicted state covariance after the last observation
+    pub next_cov: Matrix,
+    /// Maximum likelihood innovation variance
+    pub sigma2: f64,
+    /// Exact log-likelihood at `sigma2`
+    pub log_likelihood: f64,
+    /// Number of observations contributing to the likelihood
+    pub n_obs: usize,
+    /// Index of the first filtered observation (the first d seed the levels)
+    pub start: usize,
+}
+
+impl FilterOutput {
+    /// Append a pass continued from `next_state` (see `StateSpace::filter_from`),
+    /// re-estimating sigma² and the likelihood over both
+    pub fn append(&mut self, next: FilterOutput) {
+        self.predicted_states.extend(next.predicted_states);
+        self.predicted_covs.extend(next.predicted_covs);
+        self.filtered_states.extend(next.filtered_states);
+        self.innovations.extend(next.innovations);
+        self.innovation_vars.extend(next.innovation_vars);
+        self.next_state = next.next_state;
+        self.next_cov = next.next_cov;
+        self.concentrate_likelihood();
+    }
+
+    /// Set sigma², the log-likelihood and n_obs from the innovations
+    fn concentrate_likelihood(&mut self) {
+        let mut weighted_sse = 0.0;
+        let mut log_det = 0.0;
+        let mut n_obs = 0;
+        for (&v, &f) in self.innovations.iter().zip(&self.innovation_vars) {
+            // Observations dominated by the diffuse prior carry no information
+            if v.is_finite() && f > 1e-12 && f < DIFFUSE_VARIANCE / 10.0 {
+                weighted_sse += v * v / f;
+                log_det += f.ln();
+                n_obs += 1;
+            }
+        }
+
+        self.n_obs = n_obs;
+        if n_obs > 0 {
+            let n = n_obs as f64;
+            self.sigma2 = weighted_sse / n;
+            self.log_likelihood = -0.5 * (n * (2.0 * std::f64::consts::PI * self.sigma2.max(1e-300)).ln() + n + log_det);
+        }
+    }
+}
+
+impl StateSpace {
+    /// Build the state-space form of an ARIMA(p,d,q) with drift `intercept`
+    /// on the differenced scale
+    pub fn arima(ar: &[f64], ma: &[f64], d: usize, intercept: f64) -> Self {
+        let r = ar.len().max(ma.len() + 1);
+        let m = r + d;
+
+        let mut transition = vec![vec![0.0; m]; m];
+        for (i, &phi) in ar.iter().enumerate() {
+            transition[i][0] = phi;
+        }
+        for i in 0..r - 1 {
+            transition[i][i + 1] = 1.0;
+        }
+
+        let mut selection = vec![0.0; m];
+        selection[0] = 1.0;
+        selection[1..=ma.len()].copy_from_slice(ma);
+
+        // y_t = x_t + intercept + sum_k a_k y_{t-k}, with a_k from (1 - B)^d
+        let level_coeffs = integration_coefficients(d);
+        let mut design = vec![0.0; m];
+        design[0] = 1.0;
+        design[r..].copy_from_slice(&level_coeffs);
+
+        let mut state_intercept = vec![0.0; m];
+        if d > 0 {
+            // First level slot becomes y_t; the rest shift down one lag
+            transition[r] = design.clone();
+            state_intercept[r] = intercept;
+            for k in 1..d {
+                transition[r + k][r + k - 1] = 1.0;
+            }
+        }
+
+        StateSpace {
+            transition,
+            design,
+            selection,
+            state_intercept,
+            obs_intercept: intercept,
+            arma_dim: r,
+            d,
+        }
+    }
+
+    /// State dimension
+    pub fn dim(&self) -> usize {
+        self.design.len()
+    }
+
+    /// Run the Kalman filter over a series (NaN marks missing values)
+    ///
+    /// The ARMA block starts from its stationary distribution and the level
+    /// block from the first d observations.
+    pub fn filter(&self, series: &[f64]) -> FilterOutput {
+        let start = self.d.min(series.len());
+        let (state, cov) = self.initial_state(series);
+        self.filter_from(&series[start..], state, cov, start)
+    }
+
+    /// Continue filtering new observations from a predicted state
+    pub fn filter_from(&self, series: &[f64], mut a: Vec<f64>, mut p: Matrix, start: usize) -> FilterOutput {
+        let mut out = FilterOutput { start, ..FilterOutput::default() };
+
+        for &y in series {
+            out.predicted_states.push(a.clone());
+            out.predicted_covs.push(p.clone());
+
+            let (filtered_a, filtered_p) = if y.is_finite() {
+                let pz = mat_vec(&p, &self.design);
+                let f = dot(&self.design, &pz);
+                let v = y - dot(&self.design, &a) - self.obs_intercept;
+                out.innovations.push(v);
+                out.innovation_vars.push(f);
+
+                if f > 1e-12 {
+                    let fa = a.iter().zip(&pz).map(|(ai, gi)| ai + gi * v / f).collect();
+                    let fp = (0..p.len())
+                        .map(|i| (0..p.len()).map(|j| p[i][j] - pz[i] * pz[j] / f).collect())
+                        .collect();
+                    (fa, fp)
+                } else {
+                    (a.clone(), p.clone())
+                }
+            } else {
+                out.innovations.push(f64::NAN);
+                out.innovation_vars.push(f64::NAN);
+                (a.clone(), p.clone())
+            };
+
+            out.filtered_states.push(filtered_a.clone());
+            a = self.predict_state(&filtered_a);
+            p = self.predict_cov(&filtered_p);
+        }
+
+        out.next_state = a;
+        out.next_cov = p;
+        out.concentrate_likelihood();
+        out
+    }
+
+    /// Smoothed states E[a_t | all observations] (Durbin & Koopman, 2012, 4.4)
+    pub fn smooth(&self, out: &FilterOutput) -> Vec<Vec<f64>> {
+        let m = self.dim();
+        let mut r = vec![0.0; m];
+        let mut smoothed = vec![vec![]; out.predicted_states.len()];
+
+        for t in (0..out.predicted_states.len()).rev() {
+            let a = &out.predicted_states[t];
+            let p = &out.predicted_covs[t];
+            let v = out.innovations[t];
+            let f = out.innovation_vars[t];
+
+            r = if v.is_finite() && f > 1e-12 {
+                // r_{t-1} = Z'v/F + L'r_t with L = T - T P Z' Z / F
+                let pz = mat_vec(p, &self.design);
+                let tr = mat_t_vec(&self.transition, &r);
+                let correction = dot(&pz, &tr);
+                (0..m)
+                    .map(|i| self.design[i] * (v - correction) / f + tr[i])
+                    .collect()
+            } else {
+                mat_t_vec(&self.transition, &r)
+            };
+
+            let pr = mat_vec(p, &r);
+            smoothed[t] = a.iter().zip(&pr).map(|(ai, pi)| ai + pi).collect();
+        }
+
+        smoothed
+    }
+
+    /// Forecast means and variances (scaled by `out.sigma2`) for `steps` ahead
+    pub fn forecast(&self, out: &FilterOutput, steps: usize) -> (Vec<f64>, Vec<f64>) {
+        let mut a = out.next_state.clone();
+        let mut p = out.next_cov.clone();
+        let mut means = Vec::with_capacity(steps);
+        let mut variances = Vec::with_capacity(steps);
+
+        for _ in 0..steps {
+            means.push(dot(&self.design, &a) + self.obs_intercept);
+            variances.push(out.sigma2 * dot(&self.design, &mat_vec(&p, &self.design)));
+            a = self.predict_state(&a);
+            p = self.predict_cov(&p);
+        }
+
+        (means, variances)
+    }
+
+    /// Observation implied by a state
+    pub fn observe(&self, state: &[f64]) -> f64 {
+        dot(&self.design, state) + self.obs_intercept
+    }
+
+    fn predict_state(&self, a: &[f64]) -> Vec<f64> {
+        mat_vec(&self.transition, a).iter()
+            .zip(&self.state_intercept)
+            .map(|(x, c)| x + c)
+            .collect()
+    }
+
+    fn predict_cov(&self, p: &Matrix) -> Matrix {
+        let m = self.dim();
+        let tp: Matrix = (0..m)
+            .map(|i| (0..m).map(|j| (0..m).map(|k| self.transition[i][k] * p[k][j]).sum()).collect())
+            .collect();
+        (0..m)
+            .map(|i| {
+                (0..m)
+                    .map(|j| {
+                        let tpt: f64 = (0..m).map(|k| tp[i][k] * self.transition[j][k]).sum();
+                        tpt + self.selection[i] * self.selection[j]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Stationary ARMA covariance plus levels seeded from the first d values
+    fn initial_state(&self, series: &[f64]) -> (Vec<f64>, Matrix) {
+        let m = self.dim();
+        let r = self.arma_dim;
+        let mut a = vec![0.0; m];
+        let mut p = vec![vec![0.0; m]; m];
+
+        let arma = self.stationary_arma_cov();
+        for i in 0..r {
+            p[i][..r].copy_from_slice(&arma[i]);
+        }
+
+        // Level slot k holds y_{d-1-k}; a missing seed gets a diffuse prior
+        let fallback = series.iter().copied().find(|x| x.is_finite()).unwrap_or(0.0);
+        for k in 0..self.d {
+            match series.get(self.d - 1 - k) {
+                Some(&y) if y.is_finite() => a[r + k] = y,
+                _ => {
+                    a[r + k] = fallback;
+                    p[r + k][r + k] = DIFFUSE_VARIANCE;
+                }
+            }
+        }
+
+        (a, p)
+    }
+
+    /// Solve P = T P T' + R R' for the ARMA block by fixed-point iteration
+    ///
+    /// Non-stationary AR parameters never converge; they get a large prior
+    /// variance instead.
+    fn stationary_arma_cov(&self) -> Matrix {
+        let r = self.arma_dim;
+        let t: Matrix = self.transition[..r].iter().map(|row| row[..r].to_vec()).collect();
+        let rr = &self.selection[..r];
+        let mut p = vec![vec![0.0; r]; r];
+
+        for _ in 0..2000 {
+            let mut next = vec![vec![0.0; r]; r];
+            let mut change: f64 = 0.0;
+            for i in 0..r {
+                for j in 0..r {
+                    let mut sum = rr[i] * rr[j];
+                    for k in 0..r {
+                        for l in 0..r {
+                            sum += t[i][k] * p[k][l] * t[j][l];
+                        }
+                    }
+                    change = change.max((sum - p[i][j]).abs());
+                    next[i][j] = sum;
+                }
+            }
+            p = next;
+            if !change.is_finite() {
+                break;
+            }
+            if change < 1e-10 {
+                return p;
+            }
+        }
+
+        (0..r)
+            .map(|i| (0..r).map(|j| if i == j { DIFFUSE_VARIANCE } else { 0.0 }).collect())
+            .collect()
+    }
+}
+
+/// Coefficients a_k with y_t - sum_k a_k y_{t-k} = (1 - B)^d y_t
+fn integration_coefficients(d: usize) -> Vec<f64> {
+    // Expand (1 - B)^d
+    let mut poly = vec![1.0];
+    for _ in 0..d {
+        let mut next = vec![0.0; poly.len() + 1];
+        for (i, &c) in poly.iter().enumerate() {
+            next[i] += c;
+            next[i + 1] -= c;
+        }
+        poly = next;
+    }
+    poly[1..].iter().map(|&c| -c).collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn mat_vec(m: &Matrix, v: &[f64]) -> Vec<f64> {
+    m.iter().map(|row| dot(row, v)).collect()
+}
+
+fn mat_t_vec(m: &Matrix, v: &[f64]) -> Vec<f64> {
+    let cols = m.first().map_or(0, |row| row.len());
+    (0..cols).map(|j| m.iter().zip(v).map(|(row, x)| row[j] * x).sum()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integration_coefficients() {
+        assert_eq!(integration_coefficients(0), Vec::<f64>::new());
+        assert_eq!(integration_coefficients(1), vec![1.0]);
+        assert_eq!(integration_coefficients(2), vec![2.0, -1.0]);
+    }
+
+    #[test]
+    fn test_random_walk_forecast() {
+        // ARIMA(0,1,0) with drift: forecasts extend linearly, variance grows with h
+        let model = StateSpace::arima(&[], &[], 1, 2.0);
+        let series = vec![10.0, 13.0, 14.0, 17.0, 18.0];
+        let out = model.filter(&series);
+
+        assert!((out.sigma2 - 1.0).abs() < 1e-10);
+        let (means, vars) = model.forecast(&out, 3);
+        assert!((means[0] - 20.0).abs() < 1e-10);
+        assert!((means[2] - 24.0).abs() < 1e-10);
+        assert!((vars[0] - 1.0).abs() < 1e-10);
+        assert!((vars[2] - 3.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_ar1_matches_recursion() {
+        let phi = 0.6;
+        let model = StateSpace::arima(&[phi], &[], 0, 0.0);
+        let series = vec![1.0, 0.5, -0.2, 0.3];
+        let out = model.filter(&series);
+
+        // After the first observation, innovations are y_t - phi * y_{t-1}
+        for t in 1..series.len() {
+            assert!((out.innovations[t] - (series[t] - phi * series[t - 1])).abs() < 1e-10);
+        }
+        let (means, _) = model.forecast(&out, 1);
+        assert!((means[0] - phi * 0.3).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_missing_values_are_smoothed() {
+        let model = StateSpace::arima(&[], &[], 1, 0.0);
+        let series = vec![10.0, 12.0, f64::NAN, 16.0];
+        let out = model.filter(&series);
+
+        assert!(out.innovations[1].is_nan());
+        assert_eq!(out.n_obs, 2);
+
+        // Random walk bridge: the gap is filled halfway between its neighbours
+        let smoothed = model.smooth(&out);
+        assert!((model.observe(&smoothed[1]) - 14.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_filter_continues_from_next_state() {
+        let model = StateSpace::arima(&[0.5], &[0.3], 1, 1.0);
+        let series = vec![10.0, 12.0, 11.0, 14.0, 15.0, 13.0, 17.0, 18.0];
+        let full = model.filter(&series);
+
+        let mut out = model.filter(&series[..5]);
+        let next = model.filter_from(&series[5..], out.next_state.clone(), out.next_cov.clone(), 5);
+        out.append(next);
+
+        assert_eq!(out.innovations, full.innovations);
+        assert_eq!(out.next_state, full.next_state);
+        assert_eq!(out.n_obs, full.n_obs);
+        assert_eq!(out.sigma2, full.sigma2);
+        assert_eq!(out.log_likelihood, full.log_likelihood);
+    }
+}
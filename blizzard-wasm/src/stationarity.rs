@@ -0,0 +1,398 @@
+//! Unit-root and stationarity tests used to choose the differencing orders
+//!
+//! - Augmented Dickey-Fuller (null: unit root), p-values interpolated from
+//!   Fuller's (1976) tables as in R's `tseries::adf.test`
+//! - KPSS (null: stationarity), p-values interpolated from the
+//!   Kwiatkowski et al. (1992) table as in `tseries::kpss.test`
+//! - OCSB seasonal unit-root test (Osborn et al., 1988) with the
+//!   simulation-based 5% critical value used by `forecast::nsdiffs`
+//!
+//! `suggest_differencing` follows `auto.arima`: OCSB decides the seasonal
+//! order, then repeated KPSS tests decide d.
+
+use serde::{Deserialize, Serialize};
+
+use crate::arima::{calculate_seasonal_factors, deseasonalize, difference};
+use crate::linalg::{ols, OlsFit};
+
+/// Deterministic terms in the test regression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Regression {
+    /// Intercept only (level stationarity)
+    #[default]
+    Constant,
+    /// Intercept and linear trend (trend stationarity)
+    Trend,
+}
+
+/// Result of a unit-root or stationarity test
+#[derive(Debug, Clone, Serialize)]
+pub struct TestResult {
+    /// Test statistic
+    pub statistic: f64,
+    /// Interpolated p-value, clamped to the tabulated range (None if not tabulated)
+    pub p_value: Option<f64>,
+    /// Lag order used (augmentation lags or long-run variance bandwidth)
+    pub lags: usize,
+    /// Critical values as (significance level, value) pairs
+    pub critical_values: Vec<(f64, f64)>,
+    /// Whether the series looks stationary at the 5% level
+    pub stationary: bool,
+}
+
+/// Suggested differencing orders
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DifferencingSuggestion {
+    /// Non-seasonal differences
+    pub d: usize,
+    /// Seasonal differences
+    pub seasonal_d: usize,
+}
+
+// ============================================================================
+// Augmented Dickey-Fuller
+// ============================================================================
+
+const ADF_SIZES: [f64; 6] = [25.0, 50.0, 100.0, 250.0, 500.0, 100_000.0];
+const ADF_PROBS: [f64; 8] = [0.01, 0.025, 0.05, 0.10, 0.90, 0.95, 0.975, 0.99];
+
+/// Fuller's tau_mu percentiles (rows: sample size, columns: ADF_PROBS)
+#[allow(clippy::approx_constant)]
+const ADF_CONSTANT: [[f64; 8]; 6] = [
+    [-3.75, -3.33, -3.00, -2.63, -0.37, 0.00, 0.34, 0.72],
+    [-3.58, -3.22, -2.93, -2.60, -0.40, -0.03, 0.29, 0.66],
+    [-3.51, -3.17, -2.89, -2.58, -0.42, -0.05, 0.26, 0.63],
+    [-3.46, -3.14, -2.88, -2.57, -0.42, -0.06, 0.24, 0.62],
+    [-3.44, -3.13, -2.87, -2.57, -0.43, -0.07, 0.24, 0.61],
+    [-3.43, -3.12, -2.86, -2.57, -0.44, -0.07, 0.23, 0.60],
+];
+
+/// Fuller's tau_tau percentiles (rows: sample size, columns: ADF_PROBS)
+const ADF_TREND: [[f64; 8]; 6] = [
+    [-4.38, -3.95, -3.60, -3.24, -1.14, -0.80, -0.50, -0.15],
+    [-4.15, -3.80, -3.50, -3.18, -1.19, -0.87, -0.58, -0.24],
+    [-4.04, -3.73, -3.45, -3.15, -1.22, -0.90, -0.62, -0.28],
+    [-3.99, -3.69, -3.43, -3.13, -1.23, -0.92, -0.64, -0.31],
+    [-3.98, -3.68, -3.42, -3.13, -1.24, -0.93, -0.65, -0.32],
+    [-3.96, -3.66, -3.41, -3.12, -1.25, -0.94, -0.66, -0.33],
+];
+
+/// Augmented Dickey-Fuller test (null hypothesis: unit root)
+///
+/// `lags` defaults to trunc((n - 1)^(1/3)). Returns `None` if the series is
+/// too short for the regression.
+pub fn adf_test(series: &[f64], regression: Regression, lags: Option<usize>) -> Option<TestResult> {
+    let n = series.len();
+    let k = lags.unwrap_or_else(|| ((n as f64 - 1.0).cbrt()) as usize);
+    let dy = difference(series, 1);
+
+    // dy_t = a (+ b t) + gamma y_{t-1} + sum_j delta_j dy_{t-j}
+    let mut x = vec![];
+    let mut y = vec![];
+    for t in k..dy.len() {
+        let mut row = vec![1.0];
+        if regression == Regression::Trend {
+            row.push((t + 1) as f64);
+        }
+        row.push(series[t]);
+        row.extend((1..=k).map(|j| dy[t - j]));
+        x.push(row);
+        y.push(dy[t]);
+    }
+
+    let fit = ols(&x, &y)?;
+    let gamma = if regression == Regression::Trend { 2 } else { 1 };
+    let statistic = fit.coefficients[gamma] / fit.std_errors[gamma];
+
+    let table = if regression == Regression::Trend { &ADF_TREND } else { &ADF_CONSTANT };
+    let row = interpolate_table_row(table, y.len() as f64);
+    let p_value = interpolate(&row, &ADF_PROBS, statistic);
+
+    Some(TestResult {
+        statistic,
+        p_value: Some(p_value),
+        lags: k,
+        critical_values: vec![(0.01, row[0]), (0.05, row[2]), (0.10, row[3])],
+        stationary: p_value < 0.05,
+    })
+}
+
+// ============================================================================
+// KPSS
+// ============================================================================
+
+const KPSS_PROBS: [f64; 4] = [0.10, 0.05, 0.025, 0.01];
+const KPSS_LEVEL: [f64; 4] = [0.347, 0.463, 0.574, 0.739];
+const KPSS_TREND: [f64; 4] = [0.119, 0.146, 0.176, 0.216];
+
+/// KPSS test (null hypothesis: stationarity)
+///
+/// `lags` (the Bartlett bandwidth) defaults to trunc(4 (n/100)^(1/4)).
+pub fn kpss_test(series: &[f64], regression: Regression, lags: Option<usize>) -> Option<TestResult> {
+    let n = series.len();
+    if n < 3 {
+        return None;
+    }
+    let l = lags.unwrap_or_else(|| (4.0 * (n as f64 / 100.0).powf(0.25)) as usize);
+
+    let x: Vec<Vec<f64>> = (0..n)
+        .map(|t| match regression {
+            Regression::Constant => vec![1.0],
+            Regression::Trend => vec![1.0, (t + 1) as f64],
+        })
+        .collect();
+    let OlsFit { residuals: e, .. } = ols(&x, series)?;
+
+    let nf = n as f64;
+    let mut partial = 0.0;
+    let eta = e.iter().map(|&r| {
+        partial += r;
+        partial * partial
+    }).sum::<f64>() / (nf * nf);
+
+    // Newey-West long-run variance with Bartlett weights
+    let mut long_run = e.iter().map(|r| r * r).sum::<f64>() / nf;
+    for s in 1..=l.min(n - 1) {
+        let w = 1.0 - s as f64 / (l + 1) as f64;
+        let cov: f64 = (s..n).map(|t| e[t] * e[t - s]).sum::<f64>() / nf;
+        long_run += 2.0 * w * cov;
+    }
+    if long_run <= 0.0 {
+        return None;
+    }
+    let statistic = eta / long_run;
+
+    let table = if regression == Regression::Trend { &KPSS_TREND } else { &KPSS_LEVEL };
+    let p_value = interpolate(table, &KPSS_PROBS, statistic);
+
+    Some(TestResult {
+        statistic,
+        p_value: Some(p_value),
+        lags: l,
+        critical_values: vec![(0.01, table[3]), (0.05, table[1]), (0.10, table[0])],
+        stationary: p_value >= 0.05,
+    })
+}
+
+// ============================================================================
+// OCSB seasonal unit-root test
+// ============================================================================
+
+/// OCSB test for a seasonal unit root at period `period`
+///
+/// Regresses the doubly differenced series on lagged seasonal and regular
+/// differences, with AR augmentation (up to 3 lags) chosen by AIC. Only the
+/// 5% critical value is available, so no p-value is reported.
+pub fn ocsb_test(series: &[f64], period: usize) -> Option<TestResult> {
+    if period < 2 || series.len() < 2 * period + 5 {
+        return None;
+    }
+
+    let seasonal_diff = seasonal_difference(series, period);
+    let regular_diff = difference(series, 1);
+    let target = difference(&seasonal_diff, 1);
+
+    // target index i corresponds to time t = i + period + 1
+    let fit_with_lags = |lags: usize| -> Option<(OlsFit, usize)> {
+        let mut x = vec![];
+        let mut y = vec![];
+        for i in lags..target.len() {
+            let t = i + period + 1;
+            let mut row = vec![1.0];
+            row.extend((1..=lags).map(|j| target[i - j]));
+            row.push(seasonal_diff[t - 1 - period]); // Delta_m y_{t-1}
+            row.push(regular_diff[t - period - 1]); // Delta y_{t-m}
+            x.push(row);
+            y.push(target[i]);
+        }
+        let n = y.len();
+        ols(&x, &y).map(|fit| (fit, n))
+    };
+
+    let (fit, lags) = (0..=3)
+        .filter_map(|lags| fit_with_lags(lags).map(|(fit, n)| (fit, n, lags)))
+        .min_by(|a, b| aic(&a.0, a.1).total_cmp(&aic(&b.0, b.1)))
+        .map(|(fit, _, lags)| (fit, lags))?;
+
+    let last = fit.coefficients.len() - 1;
+    let statistic = fit.coefficients[last] / fit.std_errors[last];
+    let critical = ocsb_critical_value(period);
+
+    Some(TestResult {
+        statistic,
+        p_value: None,
+        lags,
+        critical_values: vec![(0.05, critical)],
+        stationary: statistic <= critical,
+    })
+}
+
+/// 5% critical value of the OCSB statistic as a function of the period
+/// (response surface from `forecast:::calcOCSBCritVal`)
+fn ocsb_critical_value(period: usize) -> f64 {
+    let log_m = (period as f64).ln() - 0.7656451;
+    -0.2937411 * (-0.2850853 * log_m - 0.05983644 * log_m * log_m).exp() - 1.652202
+}
+
+fn aic(fit: &OlsFit, n: usize) -> f64 {
+    let sse: f64 = fit.residuals.iter().map(|e| e * e).sum();
+    n as f64 * (sse / n as f64).ln() + 2.0 * fit.coefficients.len() as f64
+}
+
+fn seasonal_difference(series: &[f64], period: usize) -> Vec<f64> {
+    (period..series.len()).map(|t| series[t] - series[t - period]).collect()
+}
+
+// ============================================================================
+// Order selection
+// ============================================================================
+
+/// Suggest differencing orders for a series
+///
+/// The seasonal order comes from OCSB on the raw series. Non-seasonal
+/// differences are added while KPSS rejects level stationarity (up to 2),
+/// testing the series the model actually differences: deseasonalized with
+/// the crate's multiplicative factors. Missing values are dropped.
+pub fn suggest_differencing(series: &[f64], period: usize) -> DifferencingSuggestion {
+    let clean: Vec<f64> = series.iter().copied().filter(|x| x.is_finite()).collect();
+
+    let seasonal_d = match ocsb_test(&clean, period) {
+        Some(result) if !result.stationary => 1,
+        _ => 0,
+    };
+
+    let mut current = if period > 1 && clean.len() >= 2 * period {
+        deseasonalize(&clean, &calculate_seasonal_factors(&clean, period))
+    } else {
+        clean
+    };
+
+    let mut d = 0;
+    while d < 2 {
+        match kpss_test(&current, Regression::Constant, None) {
+            Some(result) if !result.stationary => {
+                d += 1;
+                current = difference(&current, 1);
+            }
+            _ => break,
+        }
+    }
+
+    DifferencingSuggestion { d, seasonal_d }
+}
+
+// ============================================================================
+// Table interpolation
+// ============================================================================
+
+/// Critical values for sample size `n`, interpolated between table rows
+fn interpolate_table_row(table: &[[f64; 8]; 6], n: f64) -> Vec<f64> {
+    (0..8)
+        .map(|j| {
+            let column: Vec<f64> = table.iter().map(|row| row[j]).collect();
+            interpolate(&ADF_SIZES, &column, n)
+        })
+        .collect()
+}
+
+/// Piecewise-linear interpolation of y at x, clamped to the end points
+///
+/// `xs` may be increasing or decreasing.
+fn interpolate(xs: &[f64], ys: &[f64], x: f64) -> f64 {
+    let increasing = xs[xs.len() - 1] >= xs[0];
+    let key = |v: f64| if increasing { v } else { -v };
+
+    if key(x) <= key(xs[0]) {
+        return ys[0];
+    }
+    if key(x) >= key(xs[xs.len() - 1]) {
+        return ys[ys.len() - 1];
+    }
+
+    for i in 1..xs.len() {
+        if key(x) <= key(xs[i]) {
+            let w = (x - xs[i - 1]) / (xs[i] - xs[i - 1]);
+            return ys[i - 1] + w * (ys[i] - ys[i - 1]);
+        }
+    }
+    ys[ys.len() - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reproducible pseudo-random standard-ish noise
+    fn noise(n: usize) -> Vec<f64> {
+        let mut state: u64 = 12345;
+        (0..n)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                ((state >> 11) as f64 / (1u64 << 53) as f64) - 0.5
+            })
+            .collect()
+    }
+
+    fn random_walk(n: usize) -> Vec<f64> {
+        let mut level = 100.0;
+        noise(n).iter().map(|e| { level += 1.0 + 10.0 * e; level }).collect()
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert!((interpolate(&[1.0, 2.0, 3.0], &[10.0, 20.0, 30.0], 2.5) - 25.0).abs() < 1e-12);
+        assert_eq!(interpolate(&[1.0, 2.0], &[10.0, 20.0], 0.0), 10.0);
+        // Decreasing abscissa, as in the KPSS table read backwards
+        assert!((interpolate(&[3.0, 2.0], &[0.1, 0.2], 2.5) - 0.15).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_adf() {
+        let white = noise(200);
+        let result = adf_test(&white, Regression::Constant, None).unwrap();
+        assert!(result.stationary);
+        assert_eq!(result.p_value, Some(0.01));
+
+        let walk = random_walk(200);
+        let result = adf_test(&walk, Regression::Constant, None).unwrap();
+        assert!(!result.stationary);
+    }
+
+    #[test]
+    fn test_kpss() {
+        let white = noise(200);
+        let result = kpss_test(&white, Regression::Constant, None).unwrap();
+        assert!(result.stationary);
+        assert_eq!(result.lags, 4);
+
+        let walk = random_walk(200);
+        let result = kpss_test(&walk, Regression::Constant, None).unwrap();
+        assert!(!result.stationary);
+        assert_eq!(result.p_value, Some(0.01));
+    }
+
+    #[test]
+    fn test_ocsb_critical_value() {
+        assert!((ocsb_critical_value(12) + 1.803).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_suggest_differencing() {
+        let walk = random_walk(120);
+        assert_eq!(suggest_differencing(&walk, 12).d, 1);
+
+        let white: Vec<f64> = noise(120).iter().map(|e| 100.0 + e).collect();
+        assert_eq!(suggest_differencing(&white, 12).d, 0);
+
+        assert_eq!(suggest_differencing(&white, 12).seasonal_d, 0);
+
+        // y_t = y_{t-12} + e_t has a seasonal unit root
+        let e = noise(120);
+        let mut seasonal_walk = vec![100.0; 120];
+        for t in 12..120 {
+            seasonal_walk[t] = seasonal_walk[t - 12] + 10.0 * e[t];
+        }
+        assert_eq!(suggest_differencing(&seasonal_walk, 12).seasonal_d, 1);
+    }
+}
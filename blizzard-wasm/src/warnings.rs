@@ -0,0 +1,72 @@
+//! Machine-readable warnings for results that rest on substituted defaults
+//!
+//! The pipeline keeps going when data is thin (empty seasonal months, no
+//! Easter in history, an untabulated confidence level, negative forecasts)
+//! and records what it did here so the dashboard can flag the forecast.
+
+use serde::{Deserialize, Serialize};
+
+/// What kind of substitution or correction happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// AR coefficients were shrunk into the stationary region
+    NonStationaryAr,
+    /// MA coefficients were made invertible
+    NonInvertibleMa,
+    /// A position in the seasonal cycle had no positive observations; factor 1.0 used
+    EmptySeasonalMonth,
+    /// Confidence level not tabulated; 95% (z = 1.96) used
+    UnknownConfidence,
+    /// The Easter regressor is never active in the observed history; coefficient 0 used
+    EasterNotObserved,
+    /// The custom regressor is never active in the observed history; coefficient 0 used
+    RegressorNotObserved,
+    /// Negative forecasts or bounds were clamped to zero
+    NegativeForecastClamped,
+    /// The series was too short for the ensemble holdout; equal weights used
+    EnsembleEqualWeights,
+}
+
+/// A warning with a stable code and a human-readable message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Warning { code, message: message.into() }
+    }
+}
+
+/// Clamp negative values to zero, with a warning if any were clamped
+pub fn clamp_non_negative(values: &mut [f64], what: &str) -> Option<Warning> {
+    let clamped = values.iter().filter(|&&x| x < 0.0).count();
+    for value in values.iter_mut() {
+        *value = value.max(0.0);
+    }
+    (clamped > 0).then(|| Warning::new(
+        WarningCode::NegativeForecastClamped,
+        format!("{} negative {} clamped to zero", clamped, what),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp_non_negative() {
+        let mut values = vec![3.0, -1.0, -0.5];
+        let warning = clamp_non_negative(&mut values, "forecasts").unwrap();
+
+        assert_eq!(values, vec![3.0, 0.0, 0.0]);
+        assert_eq!(warning.code, WarningCode::NegativeForecastClamped);
+        assert_eq!(warning.message, "2 negative forecasts clamped to zero");
+        assert_eq!(serde_json::to_value(&warning).unwrap()["code"], "negative_forecast_clamped");
+
+        assert!(clamp_non_negative(&mut values, "forecasts").is_none());
+    }
+}